@@ -0,0 +1,300 @@
+use colored::Colorize;
+use log::info;
+use serde_derive::Serialize;
+
+use crate::{
+    generator::{Generator, GeneratorResult},
+    parser::{
+        builder::{get_builder_account_metas, BuilderAccountMeta},
+        instruction::InstructionEnum,
+        processor::{get_processor_accounts, ProcessorAccount},
+    },
+    utils::{
+        common::{snake_from_pascal, success, warn},
+        idl::convert_account_name,
+        known_accounts::KnownAccounts,
+    },
+};
+
+/// Compares the account metas of the instruction builder functions with the accounts that the
+/// processors expect
+pub struct AccountAnalyzer<'a> {
+    generator: &'a Generator,
+    /// The reports are printed as JSON once every program is analyzed
+    json: bool,
+}
+
+impl<'a> AccountAnalyzer<'a> {
+    pub fn new(generator: &'a Generator, json: bool) -> Self {
+        Self { generator, json }
+    }
+
+    pub fn run(&self) -> ProgramReport {
+        let program_name = &self.generator.program_info.name;
+        info!("Analyzing accounts of {}...", program_name.bold());
+
+        let report = ProgramReport {
+            program: program_name.to_owned(),
+            instructions: self.analyze(),
+        };
+        if !self.json {
+            report.log();
+        }
+
+        report
+    }
+
+    fn analyze(&self) -> Vec<InstructionReport> {
+        let modules = &self.generator.modules;
+        let known_accounts = &self.generator.program_info.known_accounts;
+        let instruction_path = self.generator.program_info.layout.instruction.as_deref();
+        let instruction_enum = match InstructionEnum::find(modules, instruction_path) {
+            Some(instruction_enum) => instruction_enum,
+            None => {
+                warn("Could not find instruction enum.");
+                return vec![];
+            }
+        };
+
+        instruction_enum
+            .variants
+            .iter()
+            .filter(|variant| !variant.is_reserved())
+            .map(|variant| {
+                let builder_metas = get_builder_account_metas(
+                    modules,
+                    &instruction_enum.module,
+                    &snake_from_pascal(&variant.name),
+                );
+                let processor_accounts =
+                    get_processor_accounts(modules, &instruction_enum, &variant.name);
+                let mismatches = match (&builder_metas, &processor_accounts) {
+                    (Some(metas), Some(accounts)) => {
+                        get_mismatches(metas, accounts, known_accounts)
+                    }
+                    _ => vec![],
+                };
+
+                InstructionReport {
+                    name: variant.name.to_owned(),
+                    builder_accounts: builder_metas.map(|metas| {
+                        metas
+                            .iter()
+                            .map(|meta| get_builder_account_name(meta, known_accounts))
+                            .collect()
+                    }),
+                    processor_accounts: processor_accounts
+                        .map(|accounts| accounts.into_iter().map(|acc| acc.name).collect()),
+                    mismatches,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Print the reports of every program as a single JSON array with `--json`, and fail if any
+/// program has mismatches with `--deny`
+pub fn finish_analysis(reports: &[ProgramReport], json: bool, deny: bool) -> GeneratorResult {
+    if json {
+        println!("{}", serde_json::to_string_pretty(reports)?);
+    }
+
+    let mismatched_count = reports
+        .iter()
+        .filter(|report| report.mismatch_count() != 0)
+        .count();
+    match deny && mismatched_count != 0 {
+        true => Err(format!("{mismatched_count} program(s) have account mismatches").into()),
+        false => Ok(()),
+    }
+}
+
+#[derive(Serialize)]
+pub struct ProgramReport {
+    program: String,
+    instructions: Vec<InstructionReport>,
+}
+
+impl ProgramReport {
+    fn mismatch_count(&self) -> usize {
+        self.instructions.iter().map(|ix| ix.mismatches.len()).sum()
+    }
+
+    /// Instructions that don't have either a builder function or a processor
+    fn unchecked_count(&self) -> usize {
+        self.instructions
+            .iter()
+            .filter(|ix| ix.builder_accounts.is_none() || ix.processor_accounts.is_none())
+            .count()
+    }
+
+    fn log(&self) {
+        for ix in &self.instructions {
+            match (&ix.builder_accounts, &ix.processor_accounts) {
+                (None, _) => info!("{}: builder function not found", ix.name),
+                (_, None) => info!("{}: processor not found", ix.name),
+                _ if ix.mismatches.is_empty() => info!("{}: ok", ix.name),
+                _ => {
+                    warn(format!("{}: {} mismatch(es)", ix.name, ix.mismatches.len()));
+                    for mismatch in &ix.mismatches {
+                        warn(format!("  - {mismatch}"));
+                    }
+                }
+            }
+        }
+
+        match (self.mismatch_count(), self.unchecked_count()) {
+            (0, 0) => success("No mismatches."),
+            (0, unchecked_count) => warn(format!(
+                "No mismatches in the checked instructions, {unchecked_count} instruction(s) could not be checked."
+            )),
+            (mismatch_count, _) => warn(format!("Found {mismatch_count} mismatch(es).")),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct InstructionReport {
+    name: String,
+    /// Account names of the builder function, `None` if the function doesn't exist
+    builder_accounts: Option<Vec<String>>,
+    /// Account names of the processor, `None` if the processor doesn't exist
+    processor_accounts: Option<Vec<String>>,
+    mismatches: Vec<Mismatch>,
+}
+
+/// Difference between the builder function and the processor of an instruction
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+enum Mismatch {
+    /// Different number of accounts
+    Count { builder: usize, processor: usize },
+    /// Same account at a different index
+    #[serde(rename_all = "camelCase")]
+    Order {
+        account: String,
+        builder_index: usize,
+        processor_index: usize,
+    },
+    /// The processor checks `is_signer` but the builder doesn't sign
+    Signer { index: usize, account: String },
+    /// The builder and the processor disagree on whether the account is written to
+    Writable {
+        index: usize,
+        account: String,
+        builder: bool,
+        processor: bool,
+    },
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Count { builder, processor } => write!(
+                f,
+                "builder has {builder} accounts but the processor expects {processor} accounts"
+            ),
+            Self::Order {
+                account,
+                builder_index,
+                processor_index,
+            } => write!(
+                f,
+                "`{account}` is account {builder_index} in the builder but account {processor_index} in the processor"
+            ),
+            Self::Signer { index, account } => write!(
+                f,
+                "account {index} `{account}` is required to sign by the processor but the builder doesn't sign"
+            ),
+            Self::Writable {
+                index,
+                account,
+                builder: true,
+                ..
+            } => write!(
+                f,
+                "account {index} `{account}` is writable in the builder but the processor never writes to it"
+            ),
+            Self::Writable { index, account, .. } => write!(
+                f,
+                "account {index} `{account}` is read-only in the builder but the processor writes to it"
+            ),
+        }
+    }
+}
+
+/// Compare the account metas of the builder with the accounts of the processor in order.
+///
+/// Dynamic builder flags can't be compared, writable flags of the accounts that are given to
+/// other programs are only compared if the processor writes to them.
+fn get_mismatches(
+    metas: &[BuilderAccountMeta],
+    accounts: &[ProcessorAccount],
+    known_accounts: &KnownAccounts,
+) -> Vec<Mismatch> {
+    let mut mismatches = vec![];
+    if metas.len() != accounts.len() {
+        mismatches.push(Mismatch::Count {
+            builder: metas.len(),
+            processor: accounts.len(),
+        });
+    }
+
+    let builder_names = metas
+        .iter()
+        .map(|meta| get_builder_account_name(meta, known_accounts))
+        .collect::<Vec<_>>();
+    for (i, (meta, account)) in metas.iter().zip(accounts).enumerate() {
+        // Names are compared loosely e.g `signer` and `signers`
+        let builder_name = normalize(&builder_names[i]);
+        if builder_name != normalize(&account.name) {
+            let processor_index = accounts
+                .iter()
+                .position(|account| normalize(&account.name) == builder_name);
+            if let Some(processor_index) = processor_index {
+                mismatches.push(Mismatch::Order {
+                    account: builder_names[i].to_owned(),
+                    builder_index: i,
+                    processor_index,
+                });
+                continue;
+            }
+        }
+
+        if account.is_signer && !meta.is_signer && !meta.is_dynamic_signer {
+            mismatches.push(Mismatch::Signer {
+                index: i,
+                account: account.name.to_owned(),
+            });
+        }
+
+        let is_writable_different = match (meta.is_writable, account.is_writable) {
+            (true, false) => !account.is_cpi,
+            (false, true) => true,
+            _ => false,
+        };
+        if is_writable_different && !meta.is_dynamic_writable {
+            mismatches.push(Mismatch::Writable {
+                index: i,
+                account: account.name.to_owned(),
+                builder: meta.is_writable,
+                processor: account.is_writable,
+            });
+        }
+    }
+
+    mismatches
+}
+
+fn get_builder_account_name(meta: &BuilderAccountMeta, known_accounts: &KnownAccounts) -> String {
+    convert_account_name(meta.pubkey.replace(['*', '&', ' '], ""), known_accounts)
+}
+
+fn normalize(name: &str) -> String {
+    let name = name.replace('_', "").to_lowercase();
+    match name.strip_suffix('s') {
+        Some(singular) => singular.to_owned(),
+        None => name,
+    }
+}
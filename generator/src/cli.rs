@@ -18,10 +18,18 @@ struct Cli {
     #[clap(short, long, action, global = true)]
     silent: bool,
 
-    /// Show debug info
+    /// Show debug info and write the program model as `model.json`
     #[clap(long, action, global = true)]
     debug: bool,
 
+    /// Comma separated list of features to enable while evaluating `#[cfg(...)]` attributes
+    #[clap(short = 'F', long, global = true, value_delimiter = ',')]
+    features: Vec<String>,
+
+    /// Do not enable the `default` feature
+    #[clap(long, action, global = true)]
+    no_default_features: bool,
+
     #[clap(subcommand)]
     command: Commands,
 }
@@ -36,6 +44,9 @@ pub enum Commands {
 
     /// Generate Anchor client as an importable JS package
     Package(PackageArgs),
+
+    /// Compare the accounts of the instruction builder functions with the processors
+    Analyze(AnalyzeArgs),
 }
 
 #[derive(Args)]
@@ -51,13 +62,17 @@ pub struct IdlArgs {
     #[clap(value_parser)]
     pub path: Option<PathBuf>,
 
-    /// Keep generated Anchor dummy program after generating the IDL
+    /// Write the Anchor dummy program that the IDL is generated with
     #[clap(short, long, action)]
     pub keep_dummy_program: bool,
 
     /// Path of existing Anchor dummy program file(lib.rs)
     #[clap(short, long, value_parser)]
     pub dummy_program_path: Option<PathBuf>,
+
+    /// Also write the IDL in the legacy format(before Anchor 0.30) as idl.legacy.json
+    #[clap(long, action)]
+    pub legacy: bool,
 }
 
 #[derive(Args)]
@@ -70,7 +85,7 @@ pub struct PackageArgs {
     #[clap(short, long, action)]
     pub keep: bool,
 
-    /// Keep generated Anchor dummy program after generating the IDL
+    /// Write the Anchor dummy program that the IDL is generated with
     #[clap(long, action)]
     pub keep_dummy_program: bool,
 
@@ -90,11 +105,26 @@ pub struct PackageArgs {
     #[clap(short, long, value_parser)]
     pub dummy_program_path: Option<PathBuf>,
 
-    /// Path of existing Anchor IDL file(JSON)
+    /// Path of existing legacy Anchor IDL file(JSON)
     #[clap(short, long, value_parser)]
     pub idl_path: Option<PathBuf>,
 }
 
+#[derive(Args)]
+pub struct AnalyzeArgs {
+    /// Path of the directory that contains Solana program(s)
+    #[clap(value_parser)]
+    pub path: Option<PathBuf>,
+
+    /// Print the reports as a JSON array, one object per program
+    #[clap(long, action)]
+    pub json: bool,
+
+    /// Exit with an error if any mismatches are found e.g in CI
+    #[clap(long, action)]
+    pub deny: bool,
+}
+
 struct GeneratorLogger;
 
 impl log::Log for GeneratorLogger {
@@ -129,10 +159,14 @@ fn init_logger(silent: bool, debug: bool) {
 
 pub fn parse() -> GeneratorResult {
     let cli = Cli::parse();
-    init_logger(cli.silent, cli.debug);
-    match cli.command {
-        Commands::Program(args) => generate(GeneratorConfig::program(args, cli.output_dir)),
-        Commands::Idl(args) => generate(GeneratorConfig::idl(args, cli.output_dir)),
-        Commands::Package(args) => generate(GeneratorConfig::package(args, cli.output_dir)),
-    }
+    // Only the report is printed with JSON output
+    let is_json = matches!(&cli.command, Commands::Analyze(args) if args.json);
+    init_logger(cli.silent || is_json, cli.debug);
+    let config = match cli.command {
+        Commands::Program(args) => GeneratorConfig::program(args, cli.output_dir),
+        Commands::Idl(args) => GeneratorConfig::idl(args, cli.output_dir),
+        Commands::Package(args) => GeneratorConfig::package(args, cli.output_dir),
+        Commands::Analyze(args) => GeneratorConfig::analyze(args, cli.output_dir),
+    };
+    generate(config.features(cli.features, cli.no_default_features))
 }
@@ -1,32 +1,45 @@
 use std::{
+    error::Error,
     fs,
     path::{Path, PathBuf},
 };
 
-use anchor_syn::idl::{Idl, IdlTypeDefinitionTy};
+use anchor_syn::idl::{Idl, IdlType, IdlTypeDefinitionTy};
 use colored::Colorize;
 use log::info;
-use serde_json::to_string_pretty;
+use serde_json::{json, to_string_pretty, Value};
 
 use crate::{
-    constants::{coder::*, common::*},
+    constants::{coder::*, common::*, idl::INT_TYPES},
     generator::{Generator, GeneratorResult},
+    model::ProgramModel,
+    parser::{instruction::InstructionTag, module::ModuleGraph},
     utils::{
-        coder::{get_buffer_type, get_max_span, get_total_space_for_account, AccountSpace},
+        coder::{
+            get_buffer_type, get_field_offsets, get_int_size, get_max_span,
+            get_total_space_for_account, AccountSpace,
+        },
         common::{
-            camel_from_pascal, check_command, error, get_inside_item_line, info, pascal_from_camel,
+            camel_from_pascal, camel_from_snake, check_command, error, info, pascal_from_camel,
             pascal_from_kebab, snake_from_kebab, snake_from_pascal, spawn_process, success, warn,
         },
         generator::ProgramInfo,
+        idl::{get_program_id, Discriminants},
     },
 };
 
 pub struct CoderGenerator<'a> {
     program_info: &'a ProgramInfo,
     native_src_path: &'a Path,
+    modules: &'a ModuleGraph,
     all_content: &'a str,
     idl_path: &'a Path,
+    model: &'a ProgramModel,
     idl: Idl,
+    /// IDL with the fields that are not part of the Anchor IDL e.g `isOptional`
+    idl_json: Value,
+    /// Discriminants from the model
+    discriminants: Discriminants,
     coder_path: PathBuf,
     config: CoderConfig,
 }
@@ -49,36 +62,37 @@ enum CoderFile {
 impl<'a> CoderGenerator<'a> {
     pub fn new(
         generator: &'a Generator,
+        model: &'a ProgramModel,
         keep_idl_json: bool,
         skip_init: bool,
         yarn_lock: bool,
-        maybe_idl_path: Option<&PathBuf>,
-    ) -> Self {
-        let idl_path = match maybe_idl_path {
-            Some(path) => path,
-            None => &generator.idl_path,
-        };
-        let idl = fs::read_to_string(idl_path).unwrap();
-        let idl: Idl = serde_json::from_str(&idl).unwrap();
+    ) -> Result<Self, Box<dyn Error>> {
+        let idl_json = model.to_idl();
+        let idl: Idl = serde_json::from_value(idl_json.clone())
+            .map_err(|e| format!("Could not parse the legacy IDL: {e}"))?;
 
         let coder_path = generator
             .generated_project_path
             .join(dirname::SRC)
             .join(dirname::CODER);
 
-        Self {
+        Ok(Self {
             program_info: &generator.program_info,
             native_src_path: &generator.native_src_path,
+            modules: &generator.modules,
             all_content: &generator.all_content,
             idl_path: &generator.idl_path,
+            model,
             idl,
+            idl_json,
+            discriminants: Discriminants::from_model(model),
             coder_path,
             config: CoderConfig {
                 keep_idl_json,
                 skip_init,
                 yarn_lock,
             },
-        }
+        })
     }
 
     pub fn run(self) -> GeneratorResult {
@@ -119,7 +133,6 @@ impl<'a> CoderGenerator<'a> {
     fn create_accounts(&self) -> GeneratorResult {
         // Get IDL for accounts
         let idl = &self.idl;
-        let all_content = self.all_content;
 
         let mut encode_cases = String::new();
         let mut decode_cases = String::new();
@@ -127,6 +140,9 @@ impl<'a> CoderGenerator<'a> {
         let mut size_cases = String::new();
         let mut decode_functions = String::new();
         let mut account_layouts = String::new();
+        let mut decode_any_cases = String::new();
+        // Discriminator values that are already used by the other accounts
+        let mut discriminator_values = vec![];
 
         for acc in &idl.accounts {
             let acc_name = &acc.name;
@@ -135,7 +151,7 @@ impl<'a> CoderGenerator<'a> {
 
             // Get account space
             let (space, maybe_padding, is_variable) =
-                match get_total_space_for_account(acc_name, all_content, &self.idl) {
+                match get_total_space_for_account(acc_name, self.modules, &self.idl) {
                     AccountSpace::Normal(s) => (Some(s), None, false),
                     AccountSpace::PaddingNeeded((s, p)) => (Some(s), Some(p), false),
                     AccountSpace::Variable => (None, None, true),
@@ -154,10 +170,7 @@ impl<'a> CoderGenerator<'a> {
             };
 
             // Encode
-            let encode_space = match space {
-                Some(space) => space,
-                None => 1024 * 1024 * 10,
-            };
+            let encode_space = space.unwrap_or(1024 * 1024 * 10);
             let encode_case = format!(
                 r#"case "{camel_case_name}": {{
     const buffer = Buffer.alloc({encode_space}); {variable_comment}
@@ -177,10 +190,40 @@ impl<'a> CoderGenerator<'a> {
 
             decode_cases.push_str(&decode_case);
 
+            // Accounts with a leading enum tag are filtered and decoded by its first byte
+            let discriminator = self
+                .model
+                .accounts
+                .iter()
+                .find(|account| account.name == *acc_name)
+                .and_then(|account| account.discriminator.as_ref())
+                .map(|discriminator| discriminator.value);
+            if let Some(value) = discriminator {
+                match discriminator_values.contains(&value) {
+                    true => warn(format!(
+                        "Account '{acc_name}' has the same discriminator as another account, it can't be decoded with `decodeAny`."
+                    )),
+                    false => {
+                        discriminator_values.push(value);
+                        decode_any_cases.push_str(&format!(
+                            r#"case {value}: {{
+    return {{ name: "{camel_case_name}" as A, account: decode{acc_name}Account(data) }};
+}}"#
+                        ));
+                    }
+                }
+            }
+
             // Memcmp
+            let discriminator_filter = match discriminator {
+                Some(value) => format!(
+                    "offset: 0,\n        bytes: utils.bytes.bs58.encode([{value}]),\n        "
+                ),
+                None => String::new(),
+            };
             let memcmp_return = match space {
-                Some(s) => format!("dataSize: {s},"),
-                None => variable_comment.into(),
+                Some(s) => format!("{discriminator_filter}dataSize: {s},"),
+                None => format!("{discriminator_filter}{variable_comment}"),
             };
             let memcmp_case = format!(
                 r#"case "{camel_case_name}": {{
@@ -194,10 +237,7 @@ impl<'a> CoderGenerator<'a> {
             memcmp_cases.push_str(&memcmp_case);
 
             // Size
-            let size_return = match space {
-                Some(s) => s,
-                None => 0,
-            };
+            let size_return = space.unwrap_or_default();
             let size_case = format!(
                 r#"case "{camel_case_name}": {{
     return {size_return} {variable_comment};
@@ -223,7 +263,7 @@ impl<'a> CoderGenerator<'a> {
                     for field in fields {
                         // let buffer_type = get_buffer_type(&field.ty, idl);
                         // account_layout.push_str(&format!(r#"{buffer_type}"{}"),"#, field.name));
-                        account_layout.push_str(&get_buffer_type(field, idl));
+                        account_layout.push_str(&get_buffer_type(field, idl, &self.discriminants));
                     }
                 }
                 _ => warn(format!("Account '{acc_name}' is enum.")),
@@ -239,6 +279,14 @@ impl<'a> CoderGenerator<'a> {
             account_layouts.push_str(&account_layout);
         }
 
+        let (decode_any, utils_import) = match decode_any_cases.is_empty() {
+            true => (String::new(), ""),
+            false => (
+                accounts::DECODE_ANY_CONTENT.replace("<DecodeAnyCases>", &decode_any_cases),
+                ", utils",
+            ),
+        };
+
         let (accounts_path, accounts_content) = self.get_filepath_and_content(CoderFile::Accounts);
 
         let accounts_content = accounts_content
@@ -246,6 +294,8 @@ impl<'a> CoderGenerator<'a> {
             .replace("<DecodeCases>", &decode_cases)
             .replace("<MemCmpCases>", &memcmp_cases)
             .replace("<SizeCases>", &size_cases)
+            .replace("<DecodeAny>", &decode_any)
+            .replace("<UtilsImport>", utils_import)
             .replace("<DecodeFunctions>", &decode_functions)
             .replace("<AccountLayouts>", &account_layouts);
 
@@ -275,11 +325,12 @@ impl<'a> CoderGenerator<'a> {
         let mut cases = String::new();
         let mut functions = String::new();
         let mut layouts: String = String::new();
-        let mut layout_index = 0u8;
 
-        for ix in &idl.instructions {
+        let tag = self.discriminants.instruction_tag();
+        for (i, ix) in idl.instructions.iter().enumerate() {
             let fn_name = &ix.name;
-            let encode_fn_name = format!("encode{}", pascal_from_camel(&fn_name));
+            let layout_index = self.discriminants.instruction(fn_name, i);
+            let encode_fn_name = format!("encode{}", pascal_from_camel(fn_name));
 
             // Get case
             let case = format!(r#"case "{fn_name}": {{return {encode_fn_name}(ix);}}"#,);
@@ -288,8 +339,14 @@ impl<'a> CoderGenerator<'a> {
 
             // Get function and layout
             let mut args = String::new();
-            let mut layout = format!(r#"LAYOUT.addVariant({layout_index}, B.struct(["#);
-            let mut max_span = String::from(&format!("1"));
+            let mut layout = match tag {
+                InstructionTag::U64 | InstructionTag::Sighash => format!(
+                    r#"LAYOUTS["{fn_name}"] = [Buffer.from({:?}), B.struct(["#,
+                    layout_index.to_le_bytes()
+                ),
+                _ => format!(r#"LAYOUT.addVariant({layout_index}, B.struct(["#),
+            };
+            let mut max_span = tag.size().to_string();
             for arg in &ix.args {
                 args.push_str(&arg.name);
                 args.push(',');
@@ -297,24 +354,39 @@ impl<'a> CoderGenerator<'a> {
                 // Get type
                 // let buffer_type = get_buffer_type(&arg.ty, idl);
                 // layout.push_str(&format!(r#"{buffer_type}"{}"),"#, arg.name));
-                layout.push_str(&get_buffer_type(arg, idl));
+                layout.push_str(&get_buffer_type(arg, idl, &self.discriminants));
 
                 // Get max span
                 max_span.push_str(&get_max_span(&arg.ty, &arg.name, idl));
             }
 
             // Close layout
-            layout.push_str(&format!(r#"]), "{fn_name}");"#));
+            match tag {
+                InstructionTag::U64 | InstructionTag::Sighash => layout.push_str("])];"),
+                _ => layout.push_str(&format!(r#"]), "{fn_name}");"#)),
+            }
 
             layouts.push_str(&layout);
 
             functions.push_str(&format!(
                 "function {encode_fn_name}({{{args}}}: any): Buffer {{return encodeData({{{fn_name}: {{{args}}}}}, {max_span});}}\n\n"
             ));
-
-            layout_index += 1;
         }
 
+        let union_layouts = |tag_layout: &str| {
+            instructions::UNION_LAYOUTS
+                .replace("<Tag>", tag_layout)
+                .replace("<Layouts>", &layouts)
+        };
+        let layouts = match tag {
+            InstructionTag::U8 => union_layouts("u8"),
+            InstructionTag::U16 => union_layouts("u16"),
+            InstructionTag::U32 => union_layouts("u32"),
+            InstructionTag::U64 | InstructionTag::Sighash => {
+                instructions::DISCRIMINATOR_LAYOUTS.replace("<Layouts>", &layouts)
+            }
+        };
+
         let (ix_path, ix_content) = self.get_filepath_and_content(CoderFile::Instructions);
 
         let ix_content = ix_content
@@ -344,10 +416,16 @@ impl<'a> CoderGenerator<'a> {
     fn create_package(mut self) -> GeneratorResult {
         // Problems occur when accounts are not camelCase.
         // Accounts are PascalCase by default so we change it here
-        for acc in &mut self.idl.accounts {
-            acc.name = camel_from_pascal(&acc.name)
+        let accounts = self
+            .idl_json
+            .get_mut("accounts")
+            .and_then(Value::as_array_mut);
+        for acc in accounts.into_iter().flatten() {
+            if let Some(name) = acc["name"].as_str() {
+                acc["name"] = json!(camel_from_pascal(name));
+            }
         }
-        let idl = to_string_pretty(&self.idl).unwrap();
+        let idl = to_string_pretty(&self.idl_json).unwrap();
 
         let program_name = pascal_from_kebab(&self.program_info.name);
         let program_id_name = format!(
@@ -355,6 +433,136 @@ impl<'a> CoderGenerator<'a> {
             snake_from_kebab(&self.program_info.name).to_uppercase()
         );
 
+        // Optional accounts are left out of the instructions when they are not given, the known
+        // addresses are filled in and the dynamic flags can be set by the callers
+        let ixs = self.idl_json["instructions"].as_array();
+        let accounts = ixs
+            .into_iter()
+            .flatten()
+            .flat_map(|ix| ix["accounts"].as_array().into_iter().flatten());
+        let variadic_accounts = ixs
+            .into_iter()
+            .flatten()
+            .flat_map(|ix| ix["variadicAccounts"].as_array().into_iter().flatten());
+        let is_dynamic =
+            |acc: &Value| acc["isDynamicSigner"] == true || acc["isDynamicMut"] == true;
+        let has_custom_account_metas = accounts
+            .clone()
+            .any(|acc| acc["isOptional"] == true || acc["address"].is_string() || is_dynamic(acc));
+        let has_dynamic_flags = accounts.chain(variadic_accounts).any(is_dynamic);
+        let (program_idl, set_account_metas, account_metas) = match has_custom_account_metas {
+            true => (
+                "withoutResolvedAccounts(IDL)",
+                src::SET_ACCOUNT_METAS,
+                src::ACCOUNT_METAS_CONTENT.replace("<ProgramName>", &program_name),
+            ),
+            false => ("IDL", "", String::new()),
+        };
+        let account_with_flags = match has_dynamic_flags {
+            true => src::ACCOUNT_WITH_FLAGS_CONTENT,
+            false => "",
+        };
+
+        // Optional accounts and known addresses can be left out of `program.methods` calls, the
+        // calls without them are compiled by the generated checks
+        let is_resolved = |acc: &Value| acc["isOptional"] == true || acc["address"].is_string();
+        let account_names = |ix: &Value, resolved: bool| {
+            ix["accounts"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter(|acc| is_resolved(acc) == resolved)
+                .filter_map(|acc| acc["name"].as_str().map(str::to_owned))
+                .collect::<Vec<_>>()
+        };
+        let resolved_ixs = self.idl_json["instructions"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|ix| !account_names(ix, true).is_empty())
+            .filter_map(|ix| Some((ix["name"].as_str()?, ix)))
+            .collect::<Vec<_>>();
+        let resolved_accounts_type = resolved_ixs
+            .iter()
+            .map(|(name, ix)| {
+                let accounts = account_names(ix, true)
+                    .iter()
+                    .map(|name| format!("\"{name}\""))
+                    .collect::<Vec<_>>();
+                format!("  {name}: {};", accounts.join(" | "))
+            })
+            .collect::<Vec<_>>();
+        let resolved_checks = resolved_ixs
+            .iter()
+            .map(|(name, ix)| {
+                let accounts = account_names(ix, false)
+                    .iter()
+                    .map(|name| format!("{name}: PublicKey.default"))
+                    .collect::<Vec<_>>();
+                let accounts = match accounts.is_empty() {
+                    true => "{}".to_owned(),
+                    false => format!("{{ {} }}", accounts.join(", ")),
+                };
+                checks::ACCOUNTS_CHECK_CONTENT
+                    .replace("<ProgramName>", &program_name)
+                    .replace("<IxNamePascal>", &pascal_from_camel(name))
+                    .replace("<IxName>", name)
+                    .replace("<Accounts>", &accounts)
+            })
+            .collect::<String>();
+        let (resolved_accounts, program_type, program_cast) =
+            match resolved_accounts_type.is_empty() {
+                true => (String::new(), format!("Program<{program_name}>"), ""),
+                false => (
+                    src::RESOLVED_ACCOUNTS_CONTENT
+                        .replace("<ProgramName>", &program_name)
+                        .replace("<ResolvedAccountsType>", &resolved_accounts_type.join("\n")),
+                    format!("{program_name}Program"),
+                    " as any",
+                ),
+            };
+
+        // Variadic accounts are given as remaining accounts with a typed helper
+        let variadic_accounts_type = self.idl_json["instructions"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|ix| {
+                let accounts = ix["variadicAccounts"]
+                    .as_array()?
+                    .iter()
+                    .filter_map(|acc| acc["name"].as_str())
+                    .map(|name| format!("{name}: PublicKey[]"))
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                Some(format!("  {}: {{ {accounts} }};", ix["name"].as_str()?))
+            })
+            .collect::<Vec<_>>();
+        let variadic_accounts = match variadic_accounts_type.is_empty() {
+            true => String::new(),
+            false => src::VARIADIC_ACCOUNTS_CONTENT
+                .replace("<ProgramName>", &program_name)
+                .replace("<ProgramNameCamel>", &camel_from_pascal(&program_name))
+                .replace("<VariadicAccountsType>", &variadic_accounts_type.join("\n")),
+        };
+        let get_account_meta = match has_custom_account_metas || !variadic_accounts.is_empty() {
+            true => src::GET_ACCOUNT_META_CONTENT,
+            false => "",
+        };
+
+        // Addresses of the PDAs can be found with typed helpers
+        let (pda_addresses, pda_uses_bn) = self.get_pda_addresses(&program_id_name);
+
+        // Account fields at fixed offsets can be filtered with typed builders
+        let (filters, filters_use_bn) = self.get_filters();
+        let mut anchor_imports = String::new();
+        if pda_uses_bn || filters_use_bn {
+            anchor_imports.push_str(", BN");
+        }
+        if !filters.is_empty() {
+            anchor_imports.push_str(", utils");
+        }
+
         let ty = format!("type {} = {}", program_name, idl);
         let idl = format!("const IDL: {} = {}", program_name, idl);
 
@@ -364,8 +572,20 @@ impl<'a> CoderGenerator<'a> {
         let program_path = src_dir.join(src::PROGRAM_FILENAME);
         let mut program_content = src::PROGRAM_CONTENT
             .replace("<ProgramName>", &program_name)
-            .replace("<ProgramNameCamel>", &camel_from_pascal(program_name))
+            .replace("<ProgramNameCamel>", &camel_from_pascal(&program_name))
             .replace("<ProgramIdName>", &program_id_name)
+            .replace("<ProgramType>", &program_type)
+            .replace("<ProgramCast>", program_cast)
+            .replace("<ProgramIdl>", program_idl)
+            .replace("<SetAccountMetas>", set_account_metas)
+            .replace("<AccountWithFlags>", account_with_flags)
+            .replace("<AccountMetas>", &account_metas)
+            .replace("<ResolvedAccounts>", &resolved_accounts)
+            .replace("<VariadicAccounts>", &variadic_accounts)
+            .replace("<GetAccountMeta>", get_account_meta)
+            .replace("<PdaAddresses>", &pda_addresses)
+            .replace("<Filters>", &filters)
+            .replace("<AnchorImports>", &anchor_imports)
             .replace("<Type>", &ty)
             .replace("<Idl>", &idl);
 
@@ -385,20 +605,12 @@ impl<'a> CoderGenerator<'a> {
                 &format!("const {program_id_name} = PublicKey.default"),
             )
         };
-        match lib_content.find("declare_id!") {
-            Some(program_id_index) => {
-                let start_from_program_id_line = lib_content.get(program_id_index..).unwrap();
-                match get_inside_item_line(start_from_program_id_line, '"') {
-                    Some(program_id) => {
-                        program_content = program_content.replace(
-                            "<ProgramId>",
-                            &format!(
-                                r#"export const {program_id_name} = new PublicKey("{program_id}");"#
-                            ),
-                        )
-                    }
-                    None => warn_no_program_id(),
-                }
+        match get_program_id(lib_content) {
+            Some(program_id) => {
+                program_content = program_content.replace(
+                    "<ProgramId>",
+                    &format!(r#"export const {program_id_name} = new PublicKey("{program_id}");"#),
+                )
             }
             None => warn_no_program_id(),
         }
@@ -411,6 +623,19 @@ impl<'a> CoderGenerator<'a> {
         // Create package
         let package_dir = src_dir.parent().unwrap().canonicalize().unwrap();
 
+        // Create the type checks
+        let checks_dir = package_dir.join(dirname::CHECKS);
+        if checks_dir.exists() {
+            fs::remove_dir_all(&checks_dir)?;
+        }
+        if !resolved_checks.is_empty() {
+            fs::create_dir_all(&checks_dir)?;
+            let checks_content = checks::ACCOUNTS_CONTENT
+                .replace("<ProgramName>", &program_name)
+                .replace("<Checks>", &resolved_checks);
+            fs::write(checks_dir.join(checks::ACCOUNTS_FILENAME), checks_content)?;
+        }
+
         // Create package.json
         let package_name = format!("@native-to-anchor/{}", self.program_info.name);
         let package_json_path = package_dir.join(package::PACKAGE_JSON_FILENAME);
@@ -434,6 +659,13 @@ impl<'a> CoderGenerator<'a> {
         let tsconfig_base_json_path = package_dir.join(package::TSCONFIG_BASE_JSON_FILENAME);
         fs::write(tsconfig_base_json_path, package::TSCONFIG_BASE_JSON_CONTENT)?;
 
+        // Create tsconfig check
+        let tsconfig_check_json_path = package_dir.join(package::TSCONFIG_CHECK_JSON_FILENAME);
+        fs::write(
+            tsconfig_check_json_path,
+            package::TSCONFIG_CHECK_JSON_CONTENT,
+        )?;
+
         // Create tsconfig cjs
         let tsconfig_cjs_json_path = package_dir.join(package::TSCONFIG_CJS_JSON_FILENAME);
         fs::write(tsconfig_cjs_json_path, package::TSCONFIG_CJS_JSON_CONTENT)?;
@@ -507,6 +739,226 @@ impl<'a> CoderGenerator<'a> {
         Ok(())
     }
 
+    /// Create the `find<Account>Address` helpers of the accounts that have `pda` in the IDL,
+    /// returns the helpers and whether they use `BN`.
+    ///
+    /// Accounts with the same name share the helper if their seeds are the same, otherwise the
+    /// helpers are named after the instructions too e.g `findInitVaultAddress`.
+    fn get_pda_addresses(&self, program_id_name: &str) -> (String, bool) {
+        // (account name, seeds, instruction names)
+        let mut pdas: Vec<(&str, &Value, Vec<&str>)> = vec![];
+        let ixs = self.idl_json["instructions"]
+            .as_array()
+            .into_iter()
+            .flatten();
+        for ix in ixs {
+            let ix_name = ix["name"].as_str().unwrap_or_default();
+            let accounts = ix["accounts"].as_array().into_iter().flatten();
+            for (name, pda) in
+                accounts.filter_map(|acc| Some((acc["name"].as_str()?, acc.get("pda")?)))
+            {
+                match pdas.iter_mut().find(|(n, p, _)| *n == name && *p == pda) {
+                    Some((.., ix_names)) => ix_names.push(ix_name),
+                    None => pdas.push((name, pda, vec![ix_name])),
+                }
+            }
+        }
+
+        let accounts = self.idl_json["instructions"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .flat_map(|ix| ix["accounts"].as_array().into_iter().flatten())
+            .collect::<Vec<_>>();
+        let get_address = |path: &str| {
+            let name = camel_from_snake(path);
+            accounts
+                .iter()
+                .find(|acc| acc["name"] == name.as_str())
+                .and_then(|acc| acc["address"].as_str())
+        };
+
+        let mut uses_bn = false;
+        let mut helpers = String::new();
+        for (account_name, pda, ix_names) in &pdas {
+            let fn_name = match pdas.iter().filter(|(n, ..)| n == account_name).count() {
+                1 => format!("find{}Address", pascal_from_camel(account_name)),
+                _ => format!(
+                    "find{}{}Address",
+                    pascal_from_camel(ix_names[0]),
+                    pascal_from_camel(account_name)
+                ),
+            };
+
+            // Seeds that are not constants or known addresses are the parameters
+            let mut params: Vec<(String, &str)> = vec![];
+            let mut get_param = |path: &str, ty: &'static str| {
+                let name = camel_from_snake(path);
+                if !params.iter().any(|(n, _)| *n == name) {
+                    params.push((name.to_owned(), ty));
+                }
+                name
+            };
+            let seeds = pda["seeds"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|seed| {
+                    let path = seed["path"].as_str().unwrap_or_default();
+                    let seed = match (seed["kind"].as_str()?, seed["type"].as_str()?) {
+                        ("const", _) => format!("Buffer.from({})", seed["value"]),
+                        ("account", _) if get_address(path).is_some() => {
+                            format!(r#"new PublicKey("{}").toBuffer()"#, get_address(path)?)
+                        }
+                        (_, "publicKey") => {
+                            format!("{}.toBuffer()", get_param(path, "PublicKey"))
+                        }
+                        (_, "string") => format!("Buffer.from({})", get_param(path, "string")),
+                        (_, ty) if INT_TYPES.contains(&ty) => {
+                            uses_bn = true;
+                            let bits = ty[1..].parse::<usize>().ok()?;
+                            let mut seed = format!("new BN({})", get_param(path, "BN | number"));
+                            if ty.starts_with('i') {
+                                seed.push_str(&format!(".toTwos({bits})"));
+                            }
+                            seed.push_str(&format!(r#".toArrayLike(Buffer, "le", {})"#, bits / 8));
+                            seed
+                        }
+                        _ => return None,
+                    };
+                    Some(seed)
+                })
+                .collect::<Vec<_>>();
+
+            let program_id = match pda.get("programId") {
+                Some(program_id) => {
+                    let path = program_id["path"].as_str().unwrap_or_default();
+                    match get_address(path) {
+                        Some(address) => format!(r#"new PublicKey("{address}")"#),
+                        None => {
+                            warn(format!(
+                                "Could not find the program of `{account_name}` PDA."
+                            ));
+                            continue;
+                        }
+                    }
+                }
+                None => program_id_name.to_owned(),
+            };
+
+            let params = match params.is_empty() {
+                true => String::new(),
+                false => format!(
+                    "{{ {} }}: {{ {} }},\n  ",
+                    params
+                        .iter()
+                        .map(|(name, _)| name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    params
+                        .iter()
+                        .map(|(name, ty)| format!("{name}: {ty}"))
+                        .collect::<Vec<_>>()
+                        .join("; ")
+                ),
+            };
+            let ix_names = ix_names
+                .iter()
+                .map(|name| format!("`{name}`"))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            helpers.push_str(
+                &src::PDA_ADDRESS_CONTENT
+                    .replace("<AccountName>", account_name)
+                    .replace("<Instructions>", &ix_names)
+                    .replace("<FnName>", &fn_name)
+                    .replace("<Params>", &params)
+                    .replace("<ProgramId>", &program_id)
+                    .replace("<Seeds>", &seeds.join(", ")),
+            );
+        }
+
+        (helpers, uses_bn)
+    }
+
+    /// Create the `filters` of the account fields that are at fixed offsets, returns the filters
+    /// and whether they use `BN`.
+    ///
+    /// Offsets of the fields that come after a variable-length field depend on the data, they
+    /// can't be filtered.
+    fn get_filters(&self) -> (String, bool) {
+        let idl = &self.idl;
+        let mut uses_bn = false;
+        let mut account_filters = vec![];
+        let mut first_filter = None;
+        for acc in &idl.accounts {
+            let fields = match &acc.ty {
+                IdlTypeDefinitionTy::Struct { fields } => fields,
+                _ => continue,
+            };
+
+            let mut field_filters = vec![];
+            for (field, offset) in get_field_offsets(fields, idl) {
+                let offset = match offset {
+                    Some(offset) => offset,
+                    None => {
+                        warn(format!(
+                            "Field '{}' of account '{}' comes after a variable-length field or an enum with different sized variants, it can't be filtered.",
+                            field.name, acc.name
+                        ));
+                        continue;
+                    }
+                };
+
+                let (param, bytes) = match &field.ty {
+                    IdlType::PublicKey => ("PublicKey", "value.toBuffer()".to_owned()),
+                    IdlType::Bool => ("boolean", "[value ? 1 : 0]".to_owned()),
+                    ty => match get_int_size(ty) {
+                        Some((size, is_signed)) => {
+                            uses_bn = true;
+                            let twos = match is_signed {
+                                true => format!(".toTwos({})", size * 8),
+                                false => String::new(),
+                            };
+                            (
+                                "BN | number",
+                                format!(r#"new BN(value){twos}.toArrayLike(Buffer, "le", {size})"#),
+                            )
+                        }
+                        // Serialized bytes of the value e.g an enum or a struct
+                        None => ("Buffer | Uint8Array | number[]", "value".to_owned()),
+                    },
+                };
+                first_filter
+                    .get_or_insert_with(|| (camel_from_pascal(&acc.name), field.name.to_owned()));
+                field_filters.push(format!(
+                    "    {}: (value: {param}) => memcmp({offset}, {bytes}),",
+                    field.name
+                ));
+            }
+
+            if !field_filters.is_empty() {
+                account_filters.push(format!(
+                    "  {}: {{\n{}\n  }},",
+                    camel_from_pascal(&acc.name),
+                    field_filters.join("\n")
+                ));
+            }
+        }
+
+        match first_filter {
+            Some((account_name, field_name)) => (
+                src::FILTERS_CONTENT
+                    .replace("<AccountName>", &account_name)
+                    .replace("<FieldName>", &field_name)
+                    .replace("<Filters>", &account_filters.join("\n")),
+                uses_bn,
+            ),
+            None => (String::new(), false),
+        }
+    }
+
     fn get_filepath_and_content(&self, file: CoderFile) -> (PathBuf, String) {
         let defaults = match file {
             CoderFile::Accounts => (accounts::FILENAME, accounts::CODER_TYPE, accounts::CONTENT),
@@ -3,7 +3,7 @@ pub mod accounts {
     pub const CODER_TYPE: &str = "AccountsCoder";
     pub const CONTENT: &str = r#"// @ts-nocheck
 import * as B from "@native-to-anchor/buffer-layout";
-import { AccountsCoder, Idl } from "@project-serum/anchor";
+import { AccountsCoder, Idl<UtilsImport> } from "@project-serum/anchor";
 import { IdlTypeDef } from "@project-serum/anchor/dist/cjs/idl";
 
 export class <ProgramName>AccountsCoder<A extends string = string>
@@ -31,7 +31,7 @@ export class <ProgramName>AccountsCoder<A extends string = string>
         throw new Error(`Invalid account name: ${accountName}`);
       }
     }
-  }
+  }<DecodeAny>
 
   public memcmp(
     accountName: A,
@@ -58,6 +58,21 @@ export class <ProgramName>AccountsCoder<A extends string = string>
 <DecodeFunctions>
 
 <AccountLayouts>"#;
+
+    pub const DECODE_ANY_CONTENT: &str = r#"
+
+  /**
+   * Decode the account by its discriminator, the first byte of the account data, returns the
+   * account name with the decoded account
+   */
+  public decodeAny<T = any>(data: Buffer): { name: A; account: T } {
+    switch (data[0]) {
+      <DecodeAnyCases>
+      default: {
+        throw new Error(`Unknown account discriminator: ${data[0]}`);
+      }
+    }
+  }"#;
 }
 
 pub mod events {
@@ -135,7 +150,10 @@ export class <ProgramName>InstructionCoder implements InstructionCoder {
 
 <Functions>
 
-const LAYOUT = B.union(B.u8("instruction"));
+<Layouts>"#;
+
+    /// Layouts of the instructions with `u8`, `u16` or `u32` tags
+    pub const UNION_LAYOUTS: &str = r#"const LAYOUT = B.union(B.<Tag>("instruction"));
 <Layouts>
 
 function encodeData(ix: any, span: number): Buffer {
@@ -143,6 +161,20 @@ function encodeData(ix: any, span: number): Buffer {
   LAYOUT.encode(ix, b);
   return b;
 }
+"#;
+
+    /// Layouts of the instructions with 8 byte discriminators
+    pub const DISCRIMINATOR_LAYOUTS: &str = r#"const LAYOUTS: { [ixName: string]: [Buffer, any] } = {};
+<Layouts>
+
+function encodeData(ix: any, span: number): Buffer {
+  const ixName = Object.keys(ix)[0];
+  const [discriminator, layout] = LAYOUTS[ixName];
+  const b = Buffer.alloc(span);
+  discriminator.copy(b);
+  layout.encode(ix[ixName], b, discriminator.length);
+  return b;
+}
 "#;
 }
 
@@ -186,7 +218,7 @@ pub mod src {
 
     pub const PROGRAM_FILENAME: &str = "program.ts";
     pub const PROGRAM_CONTENT: &str = r#"import { PublicKey } from "@solana/web3.js";
-import { Program, AnchorProvider } from "@project-serum/anchor";
+import { Program, AnchorProvider<AnchorImports> } from "@project-serum/anchor";
 
 import { <ProgramName>Coder } from "./coder";
 
@@ -199,18 +231,221 @@ interface GetProgramParams {
 
 export function <ProgramNameCamel>Program(
   params?: GetProgramParams
-): Program<<ProgramName>> {
-  return new Program<<ProgramName>>(
-    IDL,
+): <ProgramType> {
+  const program = new Program<<ProgramName>>(
+    <ProgramIdl>,
     params?.programId ?? <ProgramIdName>,
     params?.provider,
     new <ProgramName>Coder(IDL)
-  );
-}
+  );<SetAccountMetas>
 
+  return program<ProgramCast>;
+}
+<AccountWithFlags><AccountMetas><ResolvedAccounts><VariadicAccounts><GetAccountMeta><PdaAddresses><Filters>
 <Type>
 
 <Idl>
+"#;
+
+    pub const SET_ACCOUNT_METAS: &str = "\n  setAccountMetas(program);";
+    pub const ACCOUNT_WITH_FLAGS_CONTENT: &str = r#"
+/**
+ * Set the dynamic signer/writable flags of an account, flags that are not given use their
+ * defaults from the IDL e.g
+ *
+ * ```ts
+ * program.methods
+ *   .ix()
+ *   .accounts({ owner: accountWithFlags(owner, { isSigner: false }) })
+ * ```
+ */
+export function accountWithFlags(
+  pubkey: PublicKey,
+  flags: { isSigner?: boolean; isWritable?: boolean }
+): PublicKey {
+  return Object.assign(new PublicKey(pubkey), flags);
+}
+"#;
+
+    pub const ACCOUNT_METAS_CONTENT: &str = r#"
+/**
+ * Remove the optional accounts and the accounts with known addresses from the instructions so
+ * that the program doesn't require them, they are added back in order when they are given or
+ * their addresses are used.
+ */
+function withoutResolvedAccounts(idl: <ProgramName>): <ProgramName> {
+  return {
+    ...idl,
+    instructions: idl.instructions.map((ix) => ({
+      ...ix,
+      accounts: (ix.accounts as any[]).filter((acc) => !acc.isOptional && !acc.address),
+    })),
+  } as any;
+}
+
+/**
+ * Build the account metas with the optional accounts that are given, the known addresses and the
+ * dynamic flags.
+ *
+ * NOTE: This relies on the internals of Anchor 0.25(`@project-serum/anchor` is pinned to `=0.25.0`
+ * in `package.json`). The functions of `program.instruction` build their keys with their own
+ * `accounts` property, which `program.methods` also uses, so it's replaced here. Check it when
+ * updating Anchor.
+ */
+function setAccountMetas(program: Program<<ProgramName>>) {
+  for (const ix of IDL.instructions) {
+    const accounts: any[] = ix.accounts;
+    const hasCustomMetas = accounts.some(
+      (acc) => acc.isOptional || acc.address || acc.isDynamicSigner || acc.isDynamicMut
+    );
+    if (!hasCustomMetas) continue;
+
+    const ixFn: any = (program.instruction as any)[ix.name];
+    ixFn.accounts = (accs: any = {}) =>
+      accounts
+        .filter((acc) => !acc.isOptional || accs[acc.name])
+        .map((acc) => getAccountMeta(acc, accs[acc.name] ?? acc.address));
+  }
+}
+"#;
+
+    pub const RESOLVED_ACCOUNTS_CONTENT: &str = r#"
+/** Optional accounts and accounts with known addresses of the instructions */
+type <ProgramName>ResolvedAccounts = {
+<ResolvedAccountsType>
+};
+
+type AccountsParam<B> = B extends { accounts(accounts: infer A): any } ? A : never;
+
+/** Methods builder whose `.accounts({ ... })` doesn't require the resolved accounts `R` */
+type ResolvedMethodsBuilder<B, R extends PropertyKey> = {
+  [K in keyof B]: K extends "accounts"
+    ? (
+        accounts: Omit<AccountsParam<B>, R> &
+          Partial<Pick<AccountsParam<B>, R & keyof AccountsParam<B>>>
+      ) => ResolvedMethodsBuilder<B, R>
+    : B[K] extends (...args: infer A) => B
+    ? (...args: A) => ResolvedMethodsBuilder<B, R>
+    : B[K];
+};
+
+type <ProgramName>Methods = Program<<ProgramName>>["methods"];
+
+/**
+ * Program whose `program.methods` can leave out the optional accounts and the accounts with known
+ * addresses e.g
+ *
+ * ```ts
+ * program.methods.ix().accounts({ owner }) // `systemProgram` is filled in
+ * ```
+ */
+export type <ProgramName>Program = Omit<Program<<ProgramName>>, "methods"> & {
+  methods: {
+    [N in keyof <ProgramName>Methods]: <ProgramName>Methods[N] extends (
+      ...args: infer A
+    ) => infer B
+      ? (
+          ...args: A
+        ) => ResolvedMethodsBuilder<
+          B,
+          N extends keyof <ProgramName>ResolvedAccounts ? <ProgramName>ResolvedAccounts[N] : never
+        >
+      : <ProgramName>Methods[N];
+  };
+};
+"#;
+
+    pub const VARIADIC_ACCOUNTS_CONTENT: &str = r#"
+/** Accounts of the instructions that can be given any number of times after the other accounts */
+export type <ProgramName>VariadicAccounts = {
+<VariadicAccountsType>
+};
+
+/**
+ * Get the account metas of the variadic accounts in order, they should be given to the
+ * instruction as the remaining accounts e.g
+ *
+ * ```ts
+ * program.methods
+ *   .ix()
+ *   .accounts({ ... })
+ *   .remainingAccounts(<ProgramNameCamel>VariadicAccounts("ix", { signers: [signer] }))
+ * ```
+ */
+export function <ProgramNameCamel>VariadicAccounts<
+  N extends keyof <ProgramName>VariadicAccounts
+>(ixName: N, accounts: <ProgramName>VariadicAccounts[N]) {
+  const ix: any = IDL.instructions.find((ix) => ix.name === ixName);
+  return (ix.variadicAccounts as any[]).flatMap((acc) =>
+    ((accounts as any)[acc.name] as PublicKey[]).map((pubkey) =>
+      getAccountMeta(acc, pubkey)
+    )
+  );
+}
+"#;
+
+    pub const PDA_ADDRESS_CONTENT: &str = r#"
+/** Find the address and the bump of `<AccountName>` in <Instructions> */
+export function <FnName>(
+  <Params>programId: PublicKey = <ProgramId>
+): [PublicKey, number] {
+  return PublicKey.findProgramAddressSync(
+    [<Seeds>],
+    programId
+  );
+}
+"#;
+
+    pub const FILTERS_CONTENT: &str = r#"
+/**
+ * `getProgramAccounts` filters of the account fields that are at fixed offsets e.g
+ *
+ * ```ts
+ * program.account.<AccountName>.all([filters.<AccountName>.<FieldName>(value)])
+ * ```
+ */
+export const filters = {
+<Filters>
+};
+
+/** Create a `memcmp` filter of the bytes at the offset */
+function memcmp(offset: number, bytes: Buffer | Uint8Array | number[]) {
+  return { memcmp: { offset, bytes: utils.bytes.bs58.encode(Buffer.from(bytes)) } };
+}
+"#;
+
+    pub const GET_ACCOUNT_META_CONTENT: &str = r#"
+/** Create the account meta of the IDL account with its dynamic flags if they are given */
+function getAccountMeta(acc: any, pubkey: any) {
+  return {
+    pubkey: new PublicKey(pubkey),
+    isWritable: (acc.isDynamicMut ? pubkey.isWritable ?? acc.isMut : acc.isMut) as boolean,
+    isSigner: (acc.isDynamicSigner ? pubkey.isSigner ?? acc.isSigner : acc.isSigner) as boolean,
+  };
+}
+"#;
+}
+
+pub mod checks {
+    pub const ACCOUNTS_FILENAME: &str = "accounts.ts";
+    pub const ACCOUNTS_CONTENT: &str = r#"// Type checks of the generated package, `check:types` compiles them without running them.
+//
+// Optional accounts and accounts with known addresses can be left out of `.accounts({ ... })`.
+import { PublicKey } from "@solana/web3.js";
+
+import { <ProgramName>Program } from "../src";
+<Checks>"#;
+
+    pub const ACCOUNTS_CHECK_CONTENT: &str = r#"
+export function check<IxNamePascal>(
+  program: <ProgramName>Program,
+  ...args: Parameters<<ProgramName>Program["methods"]["<IxName>"]>
+) {
+  return program.methods
+    .<IxName>(...args)
+    .accounts(<Accounts>)
+    .instruction();
+}
 "#;
 }
 
@@ -234,12 +469,13 @@ pub mod package {
   "browser": "./dist/browser/index.js",
   "types": "./dist/cjs/index.d.ts",
   "scripts": {
-    "init:yarn": "yarn && yarn lint:fix && yarn build:yarn",
-    "init:npm": "npm i && npm run lint:fix && npm run build:npm",
+    "init:yarn": "yarn && yarn lint:fix && yarn check:types && yarn build:yarn",
+    "init:npm": "npm i && npm run lint:fix && npm run check:types && npm run build:npm",
     "build:yarn": "rimraf dist/ && yarn build:node && yarn build:browser",
     "build:npm": "rimraf dist/ && npm run build:node && npm run build:browser",
     "build:node": "tsc && tsc -p tsconfig.cjs.json",
     "build:browser": "rollup --config",
+    "check:types": "tsc -p tsconfig.check.json",
     "lint:fix": "prettier src/** -w",
     "lint": "prettier src/** --check",
     "watch": "tsc -p tsconfig.cjs.json --watch"
@@ -300,6 +536,22 @@ pub mod package {
     ],
   }
 }
+"#;
+
+    pub const TSCONFIG_CHECK_JSON_FILENAME: &str = "tsconfig.check.json";
+    pub const TSCONFIG_CHECK_JSON_CONTENT: &str = r#"{
+  "extends": "./tsconfig.base.json",
+  "include": [
+    "./src/**/*",
+    "./checks/**/*"
+  ],
+  "compilerOptions": {
+    "noEmit": true,
+    "composite": false,
+    "declaration": false,
+    "declarationMap": false
+  }
+}
 "#;
 
     pub const TSCONFIG_CJS_JSON_FILENAME: &str = "tsconfig.cjs.json";
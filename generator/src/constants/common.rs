@@ -1,11 +1,13 @@
 // Files
 pub mod filename {
     pub const CARGO_TOML: &str = "Cargo.toml";
+    pub const CARGO_LOCK: &str = "Cargo.lock";
     pub const LIB: &str = "lib.rs";
-    pub const INSTRUCTION: &str = "instruction.rs";
-    pub const STATE: &str = "state.rs";
-    pub const ERROR: &str = "error.rs";
+    pub const MAIN: &str = "main.rs";
+    pub const MOD: &str = "mod.rs";
     pub const IDL: &str = "idl.json";
+    pub const LEGACY_IDL: &str = "idl.legacy.json";
+    pub const MODEL: &str = "model.json";
 }
 
 // Directories
@@ -13,8 +15,9 @@ pub mod dirname {
     pub const NATIVE: &str = "native";
     pub const GENERATED: &str = "generated";
     pub const SRC: &str = "src";
-    pub const STATE: &str = "state";
     pub const PROGRAM: &str = "program";
     pub const CODER: &str = "coder";
+    pub const CHECKS: &str = "checks";
     pub const NODE_MODULES: &str = "node_modules";
+    pub const VENDOR: &str = "vendor";
 }
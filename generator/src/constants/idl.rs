@@ -5,23 +5,19 @@ use anchor_lang::prelude::*;
 
 declare_id!("11111111111111111111111111111111");
 
-#[program]
+<ProgramDocs>#[program]
 pub mod <ProgramName> {
 use super::*;
 
 "#;
-pub const SKIP_LINE: [&str; 9] = [
-    "///",
-    "#[allow(dead_code)]",
-    "#[deprecated",
-    "#[serde(",
-    "#[cfg",
-    "since =",
-    "note =",
-    ")]",
-    "Reserved",
-];
-pub const DEFAULT_ARGS: [&str; 5] = ["u16", "u32", "u64", "bool", "[u64; 8]"];
+/// Version of the Anchor IDL spec that is written
+pub const IDL_SPEC: &str = "0.1.0";
+/// Anchor adds it to the codes of the custom errors
+pub const ERROR_CODE_OFFSET: u32 = 6000;
+pub const OPTIONAL_PREFIX: &str = "optional_";
+pub const VARIADIC_PREFIX: &str = "variadic_";
+pub const DYNAMIC_SIGNER_PREFIX: &str = "dynamic_signer_";
+pub const DYNAMIC_MUT_PREFIX: &str = "dynamic_mut_";
 pub const ACCOUNT_SPLIT: [&str; 8] = [
     "_pubkey",
     "_key",
@@ -33,3 +29,15 @@ pub const ACCOUNT_SPLIT: [&str; 8] = [
     "::default",
 ];
 pub const ACCOUNT_TRAITS: [&str; 4] = ["Pack", "Sealed", "IsInitialized", "AccountMaxSize"];
+pub const ERROR_TRAITS: [&str; 2] = ["PrintProgramError", "DecodeError"];
+pub const DESERIALIZE_FNS: [&str; 5] = [
+    "try_from_slice",
+    "unpack",
+    "unpack_unchecked",
+    "unpack_from_slice",
+    "deserialize",
+];
+pub const INT_TYPES: [&str; 10] = [
+    "u8", "u16", "u32", "u64", "u128", "i8", "i16", "i32", "i64", "i128",
+];
+pub const SERIALIZE_FNS: [&str; 3] = ["pack", "pack_into_slice", "serialize"];
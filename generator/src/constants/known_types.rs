@@ -4,3 +4,217 @@ pub const REPLECABLE_TYPES: [[&str; 2]; 3] =
 
 // Types that we can append to the anchor file
 pub const APPENDABLE_TYPES: [[&str; 2]; 0] = [];
+
+// Constants of the Solana crates that are commonly used in sizes
+pub const KNOWN_CONSTS: [(&str, u128); 6] = [
+    ("PUBKEY_BYTES", 32),
+    ("HASH_BYTES", 32),
+    ("SIGNATURE_BYTES", 64),
+    ("MAX_SEED_LEN", 32),
+    ("MAX_SEEDS", 16),
+    ("MAX_PERMITTED_DATA_INCREASE", 10240),
+];
+
+// Sizes and alignments of the types for `size_of::<T>()`, public keys and hashes are byte arrays
+pub const KNOWN_TYPE_SIZES: [(&str, u128, u128); 20] = [
+    ("bool", 1, 1),
+    ("u8", 1, 1),
+    ("i8", 1, 1),
+    ("u16", 2, 2),
+    ("i16", 2, 2),
+    ("u32", 4, 4),
+    ("i32", 4, 4),
+    ("f32", 4, 4),
+    ("u64", 8, 8),
+    ("i64", 8, 8),
+    ("f64", 8, 8),
+    ("usize", 8, 8),
+    ("isize", 8, 8),
+    ("u128", 16, 16),
+    ("i128", 16, 16),
+    ("Pubkey", 32, 1),
+    ("Hash", 32, 1),
+    ("Slot", 8, 8),
+    ("Epoch", 8, 8),
+    ("UnixTimestamp", 8, 8),
+];
+
+// `cfg` options that are set when compiling for the Solana target
+pub const TARGET_CFGS: [(&str, &str); 6] = [
+    ("target_os", "solana"),
+    ("target_arch", "bpf"),
+    ("target_arch", "sbf"),
+    ("target_pointer_width", "64"),
+    ("target_endian", "little"),
+    ("target_vendor", "solana"),
+];
+
+// Sysvars that can be read with `from_account_info`
+pub const SYSVARS: [&str; 10] = [
+    "Clock",
+    "EpochRewards",
+    "EpochSchedule",
+    "Fees",
+    "Instructions",
+    "LastRestartSlot",
+    "RecentBlockhashes",
+    "Rent",
+    "SlotHashes",
+    "StakeHistory",
+];
+
+// Sysvars that can be `Sysvar<'info, T>` with the Anchor 0.25 prelude, the others are read from
+// unchecked accounts in the dummy program
+pub const PRELUDE_SYSVARS: [&str; 6] = [
+    "Clock",
+    "EpochSchedule",
+    "RecentBlockhashes",
+    "Rent",
+    "SlotHashes",
+    "StakeHistory",
+];
+
+// Well-known programs and sysvars: (name, address, Anchor type, patterns).
+//
+// Accounts without an Anchor type are `UncheckedAccount`s with an address constraint, the types
+// that are not in the Anchor 0.25 prelude are left out so that the dummy program compiles.
+//
+// Account names that contain one of the patterns are renamed to the known name, the first
+// matching entry is used so the more specific patterns come first.
+pub const KNOWN_ACCOUNTS: [(&str, &str, Option<&str>, &[&str]); 22] = [
+    (
+        "associated_token_program",
+        "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL",
+        Some("Program<'info, AssociatedToken>"),
+        &["associated_token_program", "spl_associated_token_account"],
+    ),
+    (
+        "token_2022_program",
+        "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb",
+        None,
+        &["token_2022_program", "spl_token_2022"],
+    ),
+    (
+        "token_program",
+        "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+        Some("Program<'info, Token>"),
+        &["token_program", "spl_token"],
+    ),
+    (
+        "system_program",
+        "11111111111111111111111111111111",
+        Some("Program<'info, System>"),
+        &["system_program"],
+    ),
+    (
+        "bpf_loader_upgradeable",
+        "BPFLoaderUpgradeab1e11111111111111111111111",
+        None,
+        &["bpf_loader_upgradeable"],
+    ),
+    (
+        "stake_program",
+        "Stake11111111111111111111111111111111111111",
+        None,
+        &["stake_program", "stake::program"],
+    ),
+    (
+        "vote_program",
+        "Vote111111111111111111111111111111111111111",
+        None,
+        &["vote_program", "vote::program"],
+    ),
+    (
+        "compute_budget_program",
+        "ComputeBudget111111111111111111111111111111",
+        None,
+        &["compute_budget_program", "::compute_budget"],
+    ),
+    (
+        "address_lookup_table_program",
+        "AddressLookupTab1e1111111111111111111111111",
+        None,
+        &[
+            "address_lookup_table_program",
+            "address_lookup_table::program",
+        ],
+    ),
+    (
+        "metadata_program",
+        "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s",
+        None,
+        &["metadata_program", "mpl_token_metadata"],
+    ),
+    (
+        "memo_program",
+        "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr",
+        None,
+        &["memo_program", "spl_memo"],
+    ),
+    (
+        "instructions_sysvar",
+        "Sysvar1nstructions1111111111111111111111111",
+        None,
+        &["instructions_sysvar", "sysvar::instructions"],
+    ),
+    (
+        "slot_hashes",
+        "SysvarS1otHashes111111111111111111111111111",
+        Some("Sysvar<'info, SlotHashes>"),
+        &["slot_hashes"],
+    ),
+    (
+        "slot_history",
+        "SysvarS1otHistory11111111111111111111111111",
+        None,
+        &["slot_history"],
+    ),
+    (
+        "stake_history",
+        "SysvarStakeHistory1111111111111111111111111",
+        Some("Sysvar<'info, StakeHistory>"),
+        &["stake_history"],
+    ),
+    (
+        "epoch_schedule",
+        "SysvarEpochSchedu1e111111111111111111111111",
+        Some("Sysvar<'info, EpochSchedule>"),
+        &["epoch_schedule"],
+    ),
+    (
+        "epoch_rewards",
+        "SysvarEpochRewards1111111111111111111111111",
+        None,
+        &["epoch_rewards"],
+    ),
+    (
+        "recent_blockhashes",
+        "SysvarRecentB1ockHashes11111111111111111111",
+        Some("Sysvar<'info, RecentBlockhashes>"),
+        &["recent_blockhashes"],
+    ),
+    (
+        "last_restart_slot",
+        "SysvarLastRestartS1ot1111111111111111111111",
+        None,
+        &["last_restart_slot"],
+    ),
+    (
+        "fees",
+        "SysvarFees111111111111111111111111111111111",
+        None,
+        &["sysvar::fees"],
+    ),
+    (
+        "rent",
+        "SysvarRent111111111111111111111111111111111",
+        Some("Sysvar<'info, Rent>"),
+        &["::rent"],
+    ),
+    (
+        "clock",
+        "SysvarC1ock11111111111111111111111111111111",
+        Some("Sysvar<'info, Clock>"),
+        &["clock"],
+    ),
+];
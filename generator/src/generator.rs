@@ -6,12 +6,15 @@ use std::{
 };
 
 use crate::{
-    cli::{Commands, IdlArgs, PackageArgs, ProgramArgs},
+    analyzer::{finish_analysis, AccountAnalyzer, ProgramReport},
+    cli::{AnalyzeArgs, Commands, IdlArgs, PackageArgs, ProgramArgs},
     coder::CoderGenerator,
     constants::common::*,
-    idl::ProgramAndIdlGenerator,
+    idl::{IdlFormat, ProgramAndIdlGenerator},
+    model::ProgramModel,
+    parser::{cfg::CfgContext, module::ModuleGraph},
     utils::{
-        common::{get_absolute_path, get_all_content_from_folder, open_files},
+        common::{error, get_absolute_path, open_files},
         generator::{get_program_info, ProgramInfo},
     },
 };
@@ -27,14 +30,18 @@ pub struct Generator {
     pub generated_project_path: PathBuf,
     /// Generated IDL Path
     pub idl_path: PathBuf,
-    /// All crate files are dumped into this
+    /// Modules of the crate starting from lib.rs
+    pub modules: ModuleGraph,
+    /// All module files of the crate are dumped into this
     pub all_content: String,
 }
 
 impl Generator {
-    fn new(cargo_toml_path: &Path, generated_path: &Path) -> Self {
-        let program_info = get_program_info(cargo_toml_path);
-
+    fn new(
+        program_info: ProgramInfo,
+        cargo_toml_path: &Path,
+        generated_path: &Path,
+    ) -> Result<Self, Box<dyn Error>> {
         let generated_project_path = match generated_path.is_absolute() {
             true => generated_path.join(&program_info.name),
             false => {
@@ -45,18 +52,26 @@ impl Generator {
         let idl_path = generated_project_path.join(filename::IDL);
 
         let native_src_path = cargo_toml_path.parent().unwrap().join(dirname::SRC);
-        let all_content = get_all_content_from_folder(&native_src_path).unwrap();
+        let cfg = CfgContext::new(
+            cargo_toml_path,
+            &program_info.features,
+            program_info.default_features,
+        );
+        let modules = ModuleGraph::new(&native_src_path, cfg)?;
+        let all_content = modules.all_content();
 
-        Self {
+        Ok(Self {
             program_info,
             native_src_path,
             generated_project_path,
             idl_path,
+            modules,
             all_content,
-        }
+        })
     }
 
-    fn run(&self, command: &Commands) -> GeneratorResult {
+    /// Run the command for the program, returns the report of the program with `analyze`
+    fn run(&self, command: &Commands) -> Result<Option<ProgramReport>, Box<dyn Error>> {
         match command {
             Commands::Program(_) => {
                 ProgramAndIdlGenerator::new_program(self).run()?;
@@ -66,31 +81,42 @@ impl Generator {
                     self,
                     args.keep_dummy_program,
                     &args.dummy_program_path,
+                    IdlFormat::Spec {
+                        legacy: args.legacy,
+                    },
                 )
                 .run()?;
             }
             Commands::Package(args) => {
-                if args.idl_path.is_none() {
-                    ProgramAndIdlGenerator::new_idl(
+                let model = match &args.idl_path {
+                    Some(idl_path) => ProgramModel::from_legacy_idl(&serde_json::from_str(
+                        &fs::read_to_string(idl_path)?,
+                    )?)?,
+                    // Generated packages use the legacy IDL
+                    None => ProgramAndIdlGenerator::new_idl(
                         self,
                         args.keep_dummy_program,
                         &args.dummy_program_path,
+                        IdlFormat::Legacy,
                     )
-                    .run()?;
-                }
+                    .run()?,
+                };
 
                 CoderGenerator::new(
                     self,
+                    &model,
                     args.keep_idl_json,
                     args.skip_init,
                     args.yarn_lock,
-                    args.idl_path.as_ref(),
-                )
+                )?
                 .run()?;
             }
+            Commands::Analyze(args) => {
+                return Ok(Some(AccountAnalyzer::new(self, args.json).run()));
+            }
         }
 
-        Ok(())
+        Ok(None)
     }
 }
 
@@ -98,6 +124,9 @@ pub struct GeneratorConfig {
     command: Commands,
     generated_path: PathBuf,
     maybe_path: Option<PathBuf>,
+    /// Features to enable in addition to the features from the program config
+    features: Vec<String>,
+    no_default_features: bool,
 }
 
 impl GeneratorConfig {
@@ -107,6 +136,8 @@ impl GeneratorConfig {
             command: Commands::Program(args),
             generated_path: PathBuf::from(generated_path_string),
             maybe_path,
+            features: vec![],
+            no_default_features: false,
         }
     }
 
@@ -123,6 +154,8 @@ impl GeneratorConfig {
             }),
             generated_path: PathBuf::from(generated_path_string),
             maybe_path,
+            features: vec![],
+            no_default_features: false,
         }
     }
 
@@ -145,8 +178,28 @@ impl GeneratorConfig {
             }),
             generated_path: PathBuf::from(generated_path_string),
             maybe_path,
+            features: vec![],
+            no_default_features: false,
+        }
+    }
+
+    pub fn analyze(args: AnalyzeArgs, generated_path_string: String) -> GeneratorConfig {
+        let maybe_path = args.path.clone();
+        GeneratorConfig {
+            command: Commands::Analyze(args),
+            generated_path: PathBuf::from(generated_path_string),
+            maybe_path,
+            features: vec![],
+            no_default_features: false,
         }
     }
+
+    /// Set the features that the programs are compiled with
+    pub fn features(mut self, features: Vec<String>, no_default_features: bool) -> GeneratorConfig {
+        self.features = features;
+        self.no_default_features = no_default_features;
+        self
+    }
 }
 
 pub fn generate(config: GeneratorConfig) -> GeneratorResult {
@@ -156,6 +209,8 @@ pub fn generate(config: GeneratorConfig) -> GeneratorResult {
     };
     let native_path = native_path.canonicalize()?;
 
+    let mut failed_programs = vec![];
+    let mut reports = vec![];
     let mut f = |dir_entry: fs::DirEntry| -> GeneratorResult {
         let file_path = dir_entry.path();
         let file_name = file_path.file_name().unwrap().to_str().unwrap();
@@ -164,8 +219,42 @@ pub fn generate(config: GeneratorConfig) -> GeneratorResult {
         }
         let cargo_toml_path = file_path;
 
-        Generator::new(&cargo_toml_path, &config.generated_path).run(&config.command)
+        // Workspace manifests and crates that are not programs are skipped
+        let mut program_info = match get_program_info(&cargo_toml_path) {
+            Some(program_info) => program_info,
+            None => return Ok(()),
+        };
+        program_info
+            .features
+            .extend(config.features.iter().cloned());
+        if config.no_default_features {
+            program_info.default_features = false;
+        }
+
+        // A program that fails doesn't stop the generation of the other programs
+        let name = program_info.name.to_owned();
+        let result = Generator::new(program_info, &cargo_toml_path, &config.generated_path)
+            .and_then(|generator| generator.run(&config.command));
+        match result {
+            Ok(report) => reports.extend(report),
+            Err(e) => {
+                error(format!("Could not generate {name}: {e}"));
+                failed_programs.push(name);
+            }
+        }
+
+        Ok(())
+    };
+    open_files(&native_path, &mut f)?;
+
+    // Reports of the programs that could be analyzed are printed even if some programs failed
+    let analysis_result = match &config.command {
+        Commands::Analyze(args) => finish_analysis(&reports, args.json, args.deny),
+        _ => Ok(()),
     };
+    if !failed_programs.is_empty() {
+        return Err(format!("Failed programs: {}", failed_programs.join(", ")).into());
+    }
 
-    open_files(&native_path, &mut f)
+    analysis_result
 }
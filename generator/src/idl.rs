@@ -5,62 +5,114 @@
 //
 // You can fix it by adjusting the program based on the requirements.
 //
-// Remaining accounts will be commented out in the dummy Anchor program.
+// Optional, variadic and dynamic accounts are prefixed in the dummy Anchor program(e.g
+// `optional_rent`) and written to the IDL with their flags. Variadic accounts that are not the last
+// accounts of the instruction are commented out.
 
 use std::{
     cell::RefCell,
+    collections::HashMap,
+    error::Error,
     fs,
     path::{Path, PathBuf},
-    usize,
+    rc::Rc,
 };
 
-use anchor_syn::idl::{self, Idl, IdlType};
+use anchor_syn::idl::{self, IdlType};
 use colored::Colorize;
-use log::info;
+use log::{info, log_enabled, Level};
 use serde_json::to_string_pretty;
 
 use crate::{
     constants::{
         common::*,
-        idl::{ACCOUNT_TRAITS, DEFAULT_ARGS, PROGRAM_PRETEXT, SKIP_LINE},
         known_types::{APPENDABLE_TYPES, REPLECABLE_TYPES},
     },
     generator::{Generator, GeneratorResult},
-    push_or_continue,
+    model::{ErrorsModel, FieldModel, InstructionModel, ProgramModel, TypeKind, TypeModel},
+    parser::{
+        account::{
+            find_account_names, find_account_structs, find_referenced_type_names,
+            get_account_discriminator,
+        },
+        common::{get_discriminants, type_to_string},
+        error::ErrorEnum,
+        eval::eval_array_lens,
+        instruction::{InstructionEnum, InstructionTag},
+        module::{ModuleGraph, Symbol},
+    },
     utils::{
         common::{
-            debug, error, get_absolute_path, get_all_content_from_folder, get_const_value,
-            get_file_without_tests, get_inside_defined_type_name_from_str, get_inside_item,
-            get_inside_type, get_item, get_item_indices, get_item_name_from_full_item,
-            get_item_type_from_full_item, get_local_type, info, rustfmt, snake_from_kebab,
-            snake_from_pascal, success, warn,
+            debug, error, get_absolute_path, get_inside_defined_type_name, get_inside_type, info,
+            rustfmt, snake_from_kebab, success, warn,
         },
         generator::ProgramInfo,
-        idl::{create_context, is_type_defined},
+        idl::{
+            create_context, get_idl_type, get_instruction_discriminant, get_item_docs,
+            get_program_id, is_positional, is_type_defined, remove_module_paths,
+        },
     },
 };
 
+/// Model of the program that the dummy program and the IDL are rendered from
+pub type ModelResult = Result<ProgramModel, Box<dyn Error>>;
+
 pub struct ProgramAndIdlGenerator<'a> {
-    program_generator: Option<ProgramGenerator<'a>>,
+    program_generator: Option<ProgramGenerator>,
     idl_generator: Option<IdlGenerator<'a>>,
     program_info: &'a ProgramInfo,
+    modules: &'a ModuleGraph,
     all_content: &'a str,
-    /// Anchor lib.rs path
+    /// Anchor lib.rs path, the IDL is parsed from it if it's given by the user
     anchor_path: PathBuf,
-    /// All content that will be written to program/lib.rs
-    anchor_content: RefCell<String>,
-    replecable_types: RefCell<Vec<&'static [&'static str; 2]>>,
+    /// Path of the program model JSON that is written with `--debug`
+    model_path: PathBuf,
     irreplecable_types: RefCell<Vec<String>>,
+    /// Type names used in the dummy program -> paths of the types inside the crate
+    type_paths: RefCell<HashMap<String, String>>,
+    /// Instruction enum of the program once it's searched, it can't be an account
+    instruction_enum: RefCell<Option<Option<Rc<InstructionEnum>>>>,
 }
 
-pub struct ProgramGenerator<'a> {
-    pub native_src_path: &'a Path,
-}
+pub struct ProgramGenerator;
 
 pub struct IdlGenerator<'a> {
     pub idl_path: &'a Path,
     /// Whether to keep generated anchor program folder after IDL creation
     pub keep_dummy_program: bool,
+    pub format: IdlFormat,
+}
+
+/// Format of the generated IDL
+#[derive(Clone, Copy)]
+pub enum IdlFormat {
+    /// Legacy IDL(before Anchor 0.30) with the extra fields of the generated packages e.g
+    /// `isOptional`
+    Legacy,
+    /// Current Anchor IDL spec, the legacy IDL is also written as `idl.legacy.json` if `legacy`
+    Spec { legacy: bool },
+}
+
+/// Structs and enums that the accounts and the types of the model are created from, in the order
+/// they are found
+#[derive(Default)]
+struct TypeItems {
+    items: Vec<TypeItem>,
+    /// Types whose fields are being searched
+    visiting: Vec<String>,
+}
+
+struct TypeItem {
+    name: String,
+    item: syn::Item,
+    /// Module the item is defined in, array lengths of the fields are evaluated inside it
+    module: String,
+}
+
+impl TypeItems {
+    fn contains(&self, name: &str) -> bool {
+        self.items.iter().any(|type_item| type_item.name == name)
+    }
 }
 
 impl<'a> ProgramAndIdlGenerator<'a> {
@@ -70,22 +122,17 @@ impl<'a> ProgramAndIdlGenerator<'a> {
             .join(dirname::PROGRAM)
             .join(filename::LIB);
 
-        let anchor_content = PROGRAM_PRETEXT.replace(
-            "<ProgramName>",
-            &snake_from_kebab(&generator.program_info.name),
-        );
-
         Self {
-            program_generator: Some(ProgramGenerator {
-                native_src_path: &generator.native_src_path,
-            }),
+            program_generator: Some(ProgramGenerator),
             idl_generator: None,
             program_info: &generator.program_info,
+            modules: &generator.modules,
             all_content: &generator.all_content,
             anchor_path,
-            anchor_content: RefCell::new(anchor_content),
-            replecable_types: RefCell::new(vec![]),
+            model_path: generator.generated_project_path.join(filename::MODEL),
             irreplecable_types: RefCell::new(vec![]),
+            type_paths: RefCell::new(HashMap::new()),
+            instruction_enum: RefCell::new(None),
         }
     }
 
@@ -93,29 +140,16 @@ impl<'a> ProgramAndIdlGenerator<'a> {
         generator: &'a Generator,
         keep_dummy_program: bool,
         maybe_dummy_program_path: &Option<PathBuf>,
+        format: IdlFormat,
     ) -> Self {
-        let (anchor_path, anchor_content, program_generator) = match maybe_dummy_program_path {
-            Some(path) => {
-                let path = get_absolute_path(path);
-                let content = fs::read_to_string(&path).unwrap();
-                (path, content, None)
-            }
+        let (anchor_path, program_generator) = match maybe_dummy_program_path {
+            Some(path) => (get_absolute_path(path), None),
             None => {
                 let path = generator
                     .generated_project_path
                     .join(dirname::PROGRAM)
                     .join(filename::LIB);
-                let content = PROGRAM_PRETEXT.replace(
-                    "<ProgramName>",
-                    &snake_from_kebab(&generator.program_info.name),
-                );
-                (
-                    path,
-                    content,
-                    Some(ProgramGenerator {
-                        native_src_path: &generator.native_src_path,
-                    }),
-                )
+                (path, Some(ProgramGenerator))
             }
         };
 
@@ -124,668 +158,477 @@ impl<'a> ProgramAndIdlGenerator<'a> {
             idl_generator: Some(IdlGenerator {
                 keep_dummy_program,
                 idl_path: &generator.idl_path,
+                format,
             }),
             program_info: &generator.program_info,
+            modules: &generator.modules,
             all_content: &generator.all_content,
             anchor_path,
-            anchor_content: RefCell::new(anchor_content),
-            replecable_types: RefCell::new(vec![]),
+            model_path: generator.generated_project_path.join(filename::MODEL),
             irreplecable_types: RefCell::new(vec![]),
+            type_paths: RefCell::new(HashMap::new()),
+            instruction_enum: RefCell::new(None),
         }
     }
 
-    pub fn run(self) -> GeneratorResult {
-        // Create dummy program
-        if self.program_generator.is_some() {
-            info(format!(
-                "Creating Anchor program for {}...",
-                self.program_info.name
-            ));
-
-            // Create Anchor dir(s) recursively
-            let anchor_dir = &self.anchor_path.parent().unwrap();
-            if !Path::new(anchor_dir).is_dir() {
-                fs::create_dir_all(anchor_dir)?;
+    /// Create the dummy program and/or the IDL, returns the model that they are created from
+    pub fn run(self) -> ModelResult {
+        let model = match self.program_generator {
+            Some(_) => {
+                info(format!(
+                    "Creating Anchor program for {}...",
+                    self.program_info.name
+                ));
+                let model = self.create_model();
+
+                let keep_dummy_program = self
+                    .idl_generator
+                    .as_ref()
+                    .map_or(true, |idl_generator| idl_generator.keep_dummy_program);
+                if keep_dummy_program {
+                    self.write_anchor_dummy(&model)?;
+                }
+                success("Success.");
+                model
             }
-
-            // Format the code
-            rustfmt(&self.anchor_path)?;
-
-            // Create instructions
-            self.create_instructions()?;
-
-            // Create accounts
-            self.create_accounts()?;
-
-            // Create errors
-            self.create_errors()?;
-
-            // Write Anchor file
-            self.write_anchor_dummy()?;
-            success("Success.");
+            // The model is parsed from the dummy program given by the user
+            None => self.parse_dummy_program()?,
+        };
+        if log_enabled!(Level::Debug) {
+            self.write_model(&model)?;
         }
 
         // Create IDL
         if self.idl_generator.is_some() {
-            self.create_idl()?;
-            success("Success.")
+            self.create_idl(&model)?;
+            success("Success.");
         }
 
-        Ok(())
-    }
-
-    fn native_src_path(&self) -> &Path {
-        self.program_generator.as_ref().unwrap().native_src_path
+        Ok(model)
     }
 
-    fn idl_generator(&self) -> &IdlGenerator {
+    fn idl_generator(&self) -> &IdlGenerator<'_> {
         self.idl_generator.as_ref().unwrap()
     }
 
-    fn create_instructions(&self) -> GeneratorResult {
-        info!("{}", "Contexts".purple().bold());
-        // Read instruction file
-        let instruction_path = self.native_src_path().join(filename::INSTRUCTION);
-        let maybe_instruction_content = fs::read_to_string(instruction_path);
-        let content = match &maybe_instruction_content {
-            Ok(c) => c,
-            Err(_) => {
-                warn("Could not find instruction file.");
-                self.all_content
-            }
+    fn create_model(&self) -> ProgramModel {
+        let mut model = ProgramModel {
+            name: snake_from_kebab(&self.program_info.name),
+            version: self.program_info.version.to_owned(),
+            docs: self
+                .modules
+                .modules()
+                .first()
+                .map(|root| root.docs.to_owned())
+                .unwrap_or_default(),
+            instruction_tag: self.instruction_tag(),
+            instructions: vec![],
+            accounts: vec![],
+            types: vec![],
+            errors: None,
         };
 
-        let mut anchor_content = self.anchor_content.borrow_mut();
-        let mut contexts = String::new();
+        self.create_instructions(&mut model);
+        self.create_accounts(&mut model);
+        self.add_missing_types(&mut model);
+        self.create_errors(&mut model);
+        self.set_discriminants(&mut model);
 
-        // Compare curly braces count
-        let mut open_count = 0u8;
-        let mut close_count = 0u8;
+        // Known types that are not defined in the program e.g `UnixTimestamp` -> `i64`
+        let defined_types = model
+            .accounts
+            .iter()
+            .chain(&model.types)
+            .map(|type_def| type_def.name.to_owned())
+            .collect::<Vec<_>>();
+        model.replace_types(|name| {
+            REPLECABLE_TYPES
+                .iter()
+                .find(|ty| ty[0] == name && !defined_types.iter().any(|defined| defined == name))
+                .map(|ty| get_idl_type(ty[1]))
+        });
+
+        model
+    }
 
-        'line_loop: for line in content.lines() {
-            for skip_str in SKIP_LINE {
-                if line.contains(skip_str) {
-                    continue 'line_loop;
-                }
+    fn create_instructions(&self, model: &mut ProgramModel) {
+        info!("{}", "Contexts".purple().bold());
+        let modules = self.modules;
+        let instruction_enum = match self.instruction_enum() {
+            Some(instruction_enum) => instruction_enum,
+            None => {
+                warn("Could not find instruction enum.");
+                return;
             }
+        };
+        debug(format!(
+            "Found instruction enum '{}::{}' with {} variants.",
+            instruction_enum.module,
+            instruction_enum.name,
+            instruction_enum.variants.len()
+        ));
 
-            if open_count > 0 {
-                if line.contains("{") {
-                    open_count += 1;
-                }
-                if line.contains('}') {
-                    close_count += 1;
-
-                    if close_count == open_count {
-                        break;
-                    }
-                }
+        let mut type_paths = self.type_paths.borrow_mut();
+        for variant in &instruction_enum.variants {
+            if variant.is_reserved() {
+                continue;
+            }
 
-                let mut line = line.to_string();
-                // Remove in line comments
-                if let Some(start_index) = line.find("//") {
-                    line.replace_range(start_index..line.len(), "");
-                }
+            let accounts = match create_context(
+                modules,
+                &instruction_enum,
+                variant,
+                &self.program_info.known_accounts,
+            ) {
+                Some(accounts) => accounts,
+                None => continue,
+            };
 
-                line = line.trim().to_string();
-
-                let mut replaced = false;
-
-                // Contexts
-                if line.contains('(') && line.contains("),") {
-                    // One liner with argument defined somewhere else
-                    // Example: Trade(TradeArgs),
-                    // Args defined in a seperate struct
-                    let struct_name = line.get(..line.find('(').unwrap()).unwrap().to_owned();
-                    let function_name = snake_from_pascal(&struct_name);
-
-                    // Create #[Accounts] for the function
-                    push_or_continue!(
-                        contexts,
-                        create_context(&content, &self.all_content, &function_name, &struct_name)
-                    );
-
-                    // Get arguments from struct;
-                    let arg_struct_name = line
-                        .get((line.find('(').unwrap() + 1)..line.find(')').unwrap())
-                        .unwrap();
-
-                    // Get args
-                    // Check if struct name is among the defaults
-                    let args = if DEFAULT_ARGS.contains(&arg_struct_name) {
-                        format!("arg: {arg_struct_name}")
-                    } else {
-                        match content.find(&format!("pub struct {arg_struct_name}")) {
-                            Some(start_index) => {
-                                let start_from_struct = content.split_at(start_index).1;
-                                let struct_end_index = start_from_struct.find('}').unwrap();
-                                let full_struct =
-                                    start_from_struct.get(..struct_end_index + 1).unwrap();
-
-                                full_struct
-                                    .lines()
-                                    .filter(|line| {
-                                        !line.contains("struct ")
-                                            && !line.contains('}')
-                                            && !line.contains("//")
-                                    })
-                                    .map(|arg| arg.trim().replace("pub ", ""))
-                                    .reduce(|acc, arg| format!("{acc}{arg}"))
-                                    .unwrap_or_default()
-                            }
-                            // Could be Enum Type
-                            None => format!("arg: {arg_struct_name}"),
-                        }
-                    };
-
-                    // Rename function to snake_case
-                    line = format!("pub fn {}", snake_from_pascal(&line));
-
-                    line.replace_range(
-                        line.find('(').unwrap()..(line.find(',').unwrap() + 1),
-                        &format!(
-                            "(ctx: Context<{struct_name}>, {args}) -> Result<()> {{ Ok(()) }}\n"
-                        ),
-                    );
-                    replaced = true;
-                } else if line.contains("{}") {
-                    // One line no argument
-                    // Example: FinalizeVote {},
-                    let struct_name = line.split_whitespace().next().unwrap();
-                    let function_name = snake_from_pascal(&struct_name);
-
-                    // Create #[Accounts] for the function
-                    push_or_continue!(
-                        contexts,
-                        create_context(&content, &self.all_content, &function_name, &struct_name)
-                    );
-
-                    line = format!("pub fn {function_name}(ctx: Context<{struct_name}>}},");
-                } else if line.contains('{') && line.contains('}') {
-                    // One line with argument(s)
-                    // Example: Transfer { new_owner: Pubkey },
-                    let struct_name = line.split_whitespace().next().unwrap();
-                    let function_name = snake_from_pascal(&struct_name);
-
-                    // Create #[Accounts] for the function
-                    push_or_continue!(
-                        contexts,
-                        create_context(&content, &self.all_content, &function_name, &struct_name)
-                    );
-
-                    line = line
-                        .get(line.find(" {").unwrap()..)
-                        .unwrap()
-                        .replace(" {", &format!("(ctx: Context<{struct_name}>,"));
-
-                    line = format!("pub fn {function_name}{line}");
-                } else if line.len() > 1 && !line.contains(':') && !line.contains('}') {
-                    // Remaining types
-                    // Example 1: DepositReserveLiquidity {
-                    //     liquidity_amount: u64,
-                    // },
-                    // Example 2: Settle,
-
-                    // Get struct name
-                    let struct_name = if line.contains(" {") {
-                        // '{' specified
-                        line.get(..line.find(" {").unwrap()).unwrap().to_owned()
-                    } else {
-                        // No arg with ,
-                        line.replace(',', "")
-                    };
-
-                    let function_name = snake_from_pascal(&struct_name);
-
-                    // Create #[Accounts] for the function
-                    push_or_continue!(
-                        contexts,
-                        create_context(&content, &self.all_content, &function_name, &struct_name)
-                    );
-
-                    // Rename function to snake_case
-                    line = format!("pub fn {}", snake_from_pascal(&line));
-
-                    if line.ends_with(',') {
-                        // One liner without argument
-                        line = line.replace(
-                            ',',
-                            &format!("(ctx: Context<{struct_name}>) -> Result<()> {{ Ok(()) }}\n"),
-                        );
-                        replaced = true;
-                    } else {
-                        line = line.replace(" {", &format!("(ctx: Context<{struct_name}>,"))
+            // Module paths are removed from the argument types, remember where the types are
+            for arg in &variant.args {
+                for path in &arg.type_paths {
+                    let symbol = modules
+                        .resolve(path, &instruction_enum.module)
+                        .or_else(|| modules.resolve_external(path, &instruction_enum.module));
+                    if let Some(symbol) = symbol {
+                        type_paths.insert(symbol.name.to_owned(), symbol.path.to_owned());
                     }
                 }
-
-                if !replaced {
-                    line = line.replace("},", ") -> Result<()> { Ok(()) }\n");
-                }
-
-                anchor_content.push_str(&line);
-
-                if line.len() > 1 {
-                    anchor_content.push('\n');
-                }
             }
 
-            if line.contains("pub enum") && line.contains("Instruction") && line.contains("{") {
-                open_count += 1;
-            }
+            let args = variant
+                .args
+                .iter()
+                .map(|arg| FieldModel {
+                    name: arg.name.to_owned(),
+                    docs: arg.docs.to_owned(),
+                    ty: get_idl_type(eval_array_lens(&arg.ty, &instruction_enum.module, modules)),
+                })
+                .collect();
+
+            model.instructions.push(InstructionModel {
+                name: variant.name.to_owned(),
+                docs: variant.docs.to_owned(),
+                deprecated: variant
+                    .attrs
+                    .iter()
+                    .find(|attr| attr.starts_with("deprecated"))
+                    .cloned(),
+                discriminant: get_instruction_discriminant(
+                    model.instruction_tag,
+                    &variant.name,
+                    variant.discriminant,
+                ),
+                args,
+                accounts,
+            });
         }
-
-        anchor_content.pop(); // Remove ',' at the end
-        anchor_content.push_str("}\n\n"); // space for Contexts
-
-        // Add contexts
-        anchor_content.push_str(&contexts);
-
-        Ok(())
     }
 
-    fn create_accounts(&self) -> GeneratorResult {
-        let state_path = self.native_src_path().join(filename::STATE);
-        let content = if state_path.exists() {
-            get_file_without_tests(state_path)?
-        } else {
-            // State file doesn't exist, look for state folder
-            let state_path = self.native_src_path().join(dirname::STATE);
-            if !state_path.exists() {
-                warn("Could not find state file or folder.");
-                return Ok(());
-            }
-
-            // Get all state folder content
-            get_all_content_from_folder(state_path)?
+    fn create_accounts(&self, model: &mut ProgramModel) {
+        let structs = match &self.program_info.layout.state {
+            Some(state_path) => self
+                .modules
+                .modules_in(state_path)
+                .iter()
+                .flat_map(|module| self.modules.symbols_in(&module.path))
+                .filter(|symbol| matches!(symbol.item, syn::Item::Struct(_)))
+                .collect::<Vec<_>>(),
+            None => find_account_structs(self.modules),
         };
+        if structs.is_empty() {
+            warn("Could not find account structs.");
+        }
 
-        self.create_accounts_from_content(content)
+        self.create_accounts_from_structs(model, structs)
     }
 
-    fn create_accounts_from_content(&self, content: String) -> GeneratorResult {
-        let all_content = self.all_content;
-
-        // Get all structs and types, remaining ones are accounts
-        let all_types = RefCell::new(vec![]);
-        let all_type_names = RefCell::new(vec![]);
-        let types = RefCell::new(vec![]);
-        let type_names = RefCell::new(vec![]);
-
-        let mut push_all_types = |full_type: String| {
-            let name = get_item_name_from_full_item(&full_type).to_owned();
-            let mut all_type_names = all_type_names.borrow_mut();
-            if !all_type_names.contains(&name) {
-                all_types.borrow_mut().push(full_type);
-                all_type_names.push(name);
-            }
-        };
-
-        let mut push_types = |full_type: String| {
-            let type_name = get_item_name_from_full_item(&full_type).to_owned();
-            let mut type_names = type_names.borrow_mut();
-            if !type_names.contains(&type_name) {
-                types.borrow_mut().push(full_type);
-                type_names.push(type_name);
-            }
-        };
-
-        // Get all structs(from state file/folder)
-        let indices = get_item_indices(&content, "pub struct");
-        for (_, struct_indices) in indices {
-            let full_struct = get_item(content.get(struct_indices..).unwrap(), '{');
-            self.get_property_types(
-                full_struct,
-                &mut Box::new(&mut push_all_types),
-                &mut Box::new(&mut push_types),
-            );
-        }
-
-        // Add extra types that are not defined as a property(e.g fn params)
-        for ty in all_types.borrow().iter() {
-            let item_name = get_item_name_from_full_item(ty);
-            if all_content.find(&format!("-> {item_name}")).is_some()
-                || all_content.find(&format!(": {item_name},")).is_some()
-                || all_content.find(&format!(": {item_name})")).is_some()
-                || all_content.find(&format!("<{item_name}, ")).is_some()
-            {
-                push_types((*ty).to_owned());
-            }
+    fn create_accounts_from_structs(&self, model: &mut ProgramModel, structs: Vec<&Symbol>) {
+        // Structs and the types of their fields
+        let mut type_items = TypeItems::default();
+        for symbol in structs {
+            self.collect_type_item(symbol.item.to_owned(), &symbol.module, &mut type_items);
         }
 
-        // Add accounts and fix mistyped accounts if any
+        // Add the accounts that are not one of the structs e.g only deserialized accounts
         let account_names = self.get_best_guess_account_names();
-        for &account_name in &account_names {
-            // Check if the account has been added to types
-            {
-                let mut type_names = type_names.borrow_mut();
-                if let Some(type_index) = type_names.iter().position(|name| name == account_name) {
-                    type_names.swap_remove(type_index);
-                    types.borrow_mut().swap_remove(type_index);
-                }
+        for account_name in &account_names {
+            if type_items.contains(account_name) {
+                continue;
             }
 
-            // Add the account to all_types if it doesn't exist
-            let all_type_names = all_type_names.borrow();
-            if let None = all_type_names.iter().position(|name| name == account_name) {
-                // Drop the borrow to not get runtime errors
-                drop(all_type_names);
-
-                match get_local_type(account_name, all_content) {
-                    Some(full_account) => {
-                        // We can just push all types because all properties are guaranteed to exist
-                        push_all_types(full_account)
-                    }
-                    None => match APPENDABLE_TYPES.iter().find(|el| el[0] == account_name) {
-                        Some(el) => {
-                            let ty = el[1];
-                            // We need to check the properties of the imported type because it will
-                            // not exist inside the crate
-                            self.get_property_types(
-                                ty.into(),
-                                &mut Box::new(&mut push_all_types),
-                                &mut Box::new(&mut push_types),
-                            );
-                        }
-                        None => error(format!(
-                            "Account: '{account_name}' not found inside the crate."
-                        )),
-                    },
+            match self.find_type(account_name) {
+                Some(symbol) => {
+                    self.collect_type_item(symbol.item.to_owned(), &symbol.module, &mut type_items)
                 }
+                None => match APPENDABLE_TYPES.iter().find(|el| el[0] == account_name) {
+                    Some(el) => match syn::parse_str(el[1]) {
+                        Ok(item) => self.collect_type_item(item, "crate", &mut type_items),
+                        Err(e) => warn(format!("Could not parse type '{account_name}': {e}")),
+                    },
+                    None => error(format!(
+                        "Account: '{account_name}' not found inside the crate."
+                    )),
+                },
             }
         }
 
-        // Types that are not in all_types are accounts
-        let all_types = all_types.borrow();
-        let accounts = all_types
-            .iter()
-            .filter(|t| account_names.contains(&get_item_name_from_full_item(t)))
-            .collect::<Vec<&String>>();
-
-        let mut anchor_content = self.anchor_content.borrow_mut();
-
         info!("{}", "Accounts".purple().bold());
-        for account in &accounts {
-            let account_name = get_item_name_from_full_item(account);
-            info!("Creating account: {}", account_name.bold());
-            let account = format!("#[account]\n{account}");
-            anchor_content.push_str(&format!("{account}\n"));
+        let (accounts, types) = type_items
+            .items
+            .iter()
+            .partition::<Vec<_>, _>(|type_item| account_names.contains(&type_item.name));
+        for account in accounts {
+            info!("Creating account: {}", account.name.bold());
+            model
+                .accounts
+                .extend(self.create_type(&account.item, &account.module));
         }
 
         info!("{}", "Types".purple().bold());
-        let types = all_types
-            .iter()
-            .filter(|t| !accounts.contains(&t))
-            .collect::<Vec<&String>>();
         for ty in types {
-            let type_name = get_item_name_from_full_item(&ty);
-            info!("Creating type: {}", type_name.bold());
-            let ty = format!("#[derive(AnchorSerialize, AnchorDeserialize)]\n{ty}\n");
-            anchor_content.push_str(&ty);
+            info!("Creating type: {}", ty.name.bold());
+            model.types.extend(self.create_type(&ty.item, &ty.module));
         }
 
-        // Remove unused types
-        // Recursion is used here because the types we remove might potentially make other types useless
+        // Types that the fields and the functions of the crate use are removed if the accounts and
+        // the instructions don't use them. Removing a type can make other types unused.
+        let referenced_type_names = find_referenced_type_names(self.modules);
         loop {
-            let mut used_once_count = 0usize;
-            for type_name in type_names.borrow().iter() {
-                let indices = get_item_indices(anchor_content.as_str(), type_name);
-                if indices.len() == 1 {
-                    debug(format!("Type '{type_name}' is only used once. Removing..."));
-
-                    let item = get_local_type(type_name, anchor_content.as_str()).unwrap();
-                    let anchor_serde_len =
-                        "#[derive(AnchorSerialize, AnchorDeserialize)]".len() + 1;
-                    let start_index = match get_item_type_from_full_item(&item) {
-                        "struct" => indices[0].1 - (("pub struct".len() + 1) + anchor_serde_len),
-                        "enum" => indices[0].1 - (("pub enum".len() + 1) + anchor_serde_len),
-                        _ => unreachable!(),
-                    };
-                    anchor_content.replace_range(
-                        start_index..start_index + item.len() + anchor_serde_len,
-                        "",
-                    );
-                    used_once_count += 1;
-                }
+            let unused_type_names = model
+                .types
+                .iter()
+                .map(|ty| ty.name.to_owned())
+                .filter(|name| referenced_type_names.contains(name))
+                .filter(|name| !model.is_type_used(name))
+                .collect::<Vec<_>>();
+            if unused_type_names.is_empty() {
+                break;
             }
 
-            if used_once_count == 0 {
-                break;
+            for type_name in unused_type_names {
+                debug(format!("Type '{type_name}' is not used. Removing..."));
+                model.types.retain(|ty| ty.name != type_name);
             }
         }
+    }
 
-        Ok(())
+    /// Create the type model from the struct or enum, array lengths of the fields are evaluated
+    /// inside the module of the type and the module paths are removed e.g
+    /// `[state::Slot; MAX_SLOTS]` -> `[Slot; 16]`
+    fn create_type(&self, item: &syn::Item, module: &str) -> Option<TypeModel> {
+        TypeModel::new(item, get_item_docs(item), |ty| {
+            let ty = eval_array_lens(&type_to_string(ty), module, self.modules);
+            get_idl_type(remove_module_paths(ty))
+        })
     }
 
     // Returned names from this function is not guaranteed to be accounts. Works for most programs.
-    fn get_best_guess_account_names(&self) -> Vec<&str> {
-        let all_content = self.all_content;
-        // Get guaranteed accounts
+    fn get_best_guess_account_names(&self) -> Vec<String> {
+        let instruction_enum = self.instruction_enum();
         let mut account_names = vec![];
-
-        let mut push_account_name = |acc_name: &'a str| {
-            if !account_names.contains(&acc_name)
-                && !acc_name.contains("Instruction")
-                && is_type_defined(acc_name)
-                && acc_name != "Self"
+        for name in find_account_names(self.modules) {
+            if !account_names.contains(&name)
+                && !name.contains("Instruction")
+                && instruction_enum
+                    .as_ref()
+                    .map(|ix_enum| ix_enum.name.as_str())
+                    != Some(name.as_str())
+                && is_type_defined(&name)
+                && name != "Self"
             {
-                account_names.push(acc_name)
+                account_names.push(name);
             }
-        };
+        }
 
-        // Accounts from traits
-        for account_trait in ACCOUNT_TRAITS {
-            let indices = get_item_indices(all_content, &format!("impl {account_trait} for"));
+        account_names
+    }
 
-            for (_, i) in indices {
-                let line = all_content.get(i..).unwrap().lines().next().unwrap();
-                let account_name = line.split_whitespace().collect::<Vec<&str>>()[3];
-                push_account_name(account_name);
-            }
+    /// Add the struct or enum to the type items after the types of its fields, field types are
+    /// searched inside the crate, the known types and the dependencies
+    fn collect_type_item(&self, item: syn::Item, module: &str, type_items: &mut TypeItems) {
+        let (name, fields) = match &item {
+            syn::Item::Struct(item_struct) => (
+                item_struct.ident.to_string(),
+                item_struct.fields.iter().collect::<Vec<_>>(),
+            ),
+            syn::Item::Enum(item_enum) => (
+                item_enum.ident.to_string(),
+                item_enum
+                    .variants
+                    .iter()
+                    .flat_map(|variant| &variant.fields)
+                    .collect(),
+            ),
+            _ => return,
+        };
+        // Types can refer to themselves e.g `children: Vec<Node>`
+        if type_items.contains(&name) || type_items.visiting.contains(&name) {
+            return;
         }
 
-        // Accounts from `try_from_slice`
-        for (_, i) in get_item_indices(all_content, &format!("::try_from_slice(")) {
-            // This is the last \n index + 1 .. i
-            if let Some(account_name_start_index) = all_content.get(..i).unwrap().rfind('\n') {
-                let account_name = all_content
-                    .get(account_name_start_index..i)
-                    .unwrap()
-                    .split_whitespace()
-                    .last()
-                    .unwrap();
-                push_account_name(account_name);
+        type_items.visiting.push(name.to_owned());
+        for field in fields {
+            let ty = eval_array_lens(&type_to_string(&field.ty), module, self.modules);
+            if let Some(defined) = get_inside_defined_type_name(get_idl_type(ty)) {
+                self.collect_field_type(&defined, module, type_items);
             }
         }
+        type_items.visiting.pop();
 
-        // Accounts from `try_from_slice_unchecked`
-        for (_, i) in get_item_indices(all_content, &format!("try_from_slice_unchecked::")) {
-            let account_name_line = all_content.get(i..).unwrap().lines().next().unwrap();
-            if let Some(account_name) = get_inside_item(account_name_line, '<') {
-                if is_type_defined(account_name) && account_name != "Self" {
-                    push_account_name(account_name.split(':').last().unwrap());
-                }
+        type_items.items.push(TypeItem {
+            name,
+            item,
+            module: module.to_owned(),
+        });
+    }
+
+    /// Find the type of the field and add it to the type items
+    fn collect_field_type(&self, type_name: &str, module: &str, type_items: &mut TypeItems) {
+        let is_type =
+            |symbol: &&Symbol| matches!(symbol.item, syn::Item::Struct(_) | syn::Item::Enum(_));
+
+        // Types that are defined inside the crate
+        if let Some(symbol) = self.modules.resolve(type_name, module).filter(is_type) {
+            return self.collect_type_item(symbol.item.to_owned(), &symbol.module, type_items);
+        }
+
+        // Known types
+        if REPLECABLE_TYPES.iter().any(|el| el[0] == type_name) {
+            return;
+        }
+        if let Some(ty) = APPENDABLE_TYPES.iter().find(|el| el[0] == type_name) {
+            match syn::parse_str(ty[1]) {
+                Ok(item) => self.collect_type_item(item, module, type_items),
+                Err(e) => warn(format!("Could not parse type '{type_name}': {e}")),
             }
+            return;
         }
 
-        account_names
-    }
+        // Types that are defined inside the dependencies e.g `spl_token::state::AccountState`
+        if let Some(symbol) = self
+            .modules
+            .resolve_external(type_name, module)
+            .filter(is_type)
+        {
+            return self.collect_type_item(symbol.item.to_owned(), &symbol.module, type_items);
+        }
 
-    fn get_property_types(
-        &self,
-        full_type: String,
-        push_all_types: &mut Box<&mut dyn FnMut(String)>,
-        push_types: &mut Box<&mut dyn FnMut(String)>,
-    ) {
-        let first_line = full_type.lines().next().unwrap();
-        let is_wrapper_struct = first_line.ends_with(");");
-
-        if is_wrapper_struct {
-            // e.g: struct Wrapper(Foo);
-            // Get inside type
-            let inside = get_inside_item(first_line, '(');
-            if let Some(inside_struct_name) = inside {
-                // Get whether the inside struct is defined in the crate
-                self.get_and_run_local_type(&inside_struct_name, push_all_types, push_types);
-            };
+        let type_name = type_name.to_owned();
+        if is_type_defined(&type_name) && !self.irreplecable_types.borrow().contains(&type_name) {
+            warn(format!("Type '{type_name}' is not found."));
+            self.irreplecable_types.borrow_mut().push(type_name);
+        }
+    }
 
-            (*push_all_types)(format!("{first_line}\n"));
-        } else {
-            'line_loop: for line in full_type.lines() {
-                for skip_str in SKIP_LINE {
-                    if line.contains(skip_str) {
-                        continue 'line_loop;
+    /// Add the instruction argument types that are not found from the accounts
+    fn add_missing_types(&self, model: &mut ProgramModel) {
+        let arg_types = model
+            .instructions
+            .iter()
+            .flat_map(|ix| ix.args.iter().map(move |arg| (ix, arg)))
+            .filter_map(|(ix, arg)| {
+                let arg_type = get_inside_type(&arg.ty).unwrap_or(&arg.ty);
+                match arg_type {
+                    IdlType::Defined(name) => {
+                        Some((ix.name.to_owned(), arg.name.to_owned(), name.to_owned()))
                     }
+                    _ => None,
                 }
+            })
+            .collect::<Vec<_>>();
 
-                // For enums
-                if get_item_type_from_full_item(&full_type) == "enum" {
-                    let maybe_wrapped = get_inside_item(line, '(');
-                    if let Some(wrapped) = maybe_wrapped {
-                        match get_inside_item(&wrapped, '<') {
-                            Some(wrapped) => {
-                                self.get_and_run_local_type(&wrapped, push_all_types, push_types);
-                            }
-                            None => {
-                                self.get_and_run_local_type(&wrapped, push_all_types, push_types);
-                            }
-                        }
-                    }
-                }
+        for (ix_name, arg_name, name) in arg_types {
+            // Exclude some of the known types like COption
+            if name.contains("COption") || name.contains("&'") || model.is_type_defined(&name) {
+                continue;
+            }
 
-                // Only get properties from the type
-                if !line.contains(':') || (line.contains("//") && line.contains(':')) {
-                    continue;
+            // Try to find it from all content
+            let symbol = self
+                .find_type(&name)
+                .filter(|symbol| matches!(symbol.item, syn::Item::Struct(_) | syn::Item::Enum(_)));
+            match symbol {
+                Some(symbol) => {
+                    info!("{}", format!("Adding missing type '{name}'").purple());
+                    model
+                        .types
+                        .extend(self.create_type(&symbol.item, &symbol.module));
                 }
-
-                // Get property types and find the types in the file
-                let property_type = line
-                    .split_once(':')
-                    .unwrap()
-                    .1
-                    .trim()
-                    .strip_suffix(',')
-                    .unwrap();
-
-                // Check for arrays for constant length
-                if property_type.starts_with('[') {
-                    let arr_len = property_type
-                        .split(';')
-                        .last()
-                        .unwrap()
-                        .strip_suffix(']')
-                        .unwrap()
-                        .trim_start();
-
-                    if arr_len.parse::<usize>().is_err() {
-                        if let Some(const_str) = get_const_value(arr_len, self.all_content) {
-                            if const_str.parse::<usize>().is_ok() {
-                                (*push_all_types)(full_type.replace(arr_len, const_str));
-                                break;
-                            }
-                        }
+                None => {
+                    if !REPLECABLE_TYPES.iter().any(|el| el[0] == name) {
+                        error(format!(
+                            "Instruction: '{ix_name}' arg: '{arg_name}' type: '{name}' is not defined inside the crate."
+                        ))
                     }
                 }
-
-                if let Some(defined) = get_inside_defined_type_name_from_str(property_type) {
-                    // Get whether the defined type is defined inside the crate
-                    self.get_and_run_local_type(&defined, push_all_types, push_types);
-                };
             }
-
-            // Pushing at last because of const values, [Pubkey; MAX_SIGNERS]
-            (*push_all_types)(full_type);
         }
     }
 
-    fn get_and_run_local_type(
-        &self,
-        item_name: &str,
-        push_all_types: &mut Box<&mut dyn FnMut(String)>,
-        push_types: &mut Box<&mut dyn FnMut(String)>,
-    ) {
-        match get_local_type(item_name, self.all_content) {
-            Some(local_type) => {
-                self.get_property_types(local_type, push_all_types, push_types);
-            }
-            None => match REPLECABLE_TYPES.iter().find(|el| el[0] == item_name) {
-                Some(ty) => self.push_replecable_types(ty),
-                None => match APPENDABLE_TYPES.iter().find(|el| el[0] == item_name) {
-                    Some(ty) => {
-                        self.get_property_types(ty[1].into(), push_all_types, push_types);
-                    }
-                    None => {
-                        let mut irreplecable_types = self.irreplecable_types.borrow_mut();
-                        let item_name = item_name.to_owned();
-                        if is_type_defined(&item_name) && !irreplecable_types.contains(&item_name) {
-                            // Check if it's a tuple type e.g 'Meta, Stake'
-                            if item_name.contains(',') {
-                                for item_name in item_name.split(',') {
-                                    self.get_and_run_local_type(
-                                        item_name.trim(),
-                                        push_all_types,
-                                        push_types,
-                                    );
-                                }
-                            } else {
-                                warn(format!("Type '{item_name}' is not found."));
-                                irreplecable_types.push(item_name);
-                            }
-                        }
-                    }
-                },
-            },
-        }
-    }
-
-    fn create_errors(&self) -> GeneratorResult {
+    fn create_errors(&self, model: &mut ProgramModel) {
         info!("{}", "Errors".purple().bold());
 
-        let error_path = self.native_src_path().join(filename::ERROR);
-        let content = match fs::read_to_string(error_path) {
-            Ok(c) => c,
-            Err(_) => {
-                warn("Could not find error file.");
-                return Ok(());
+        let error_path = self.program_info.layout.error.as_deref();
+        let error_enum = match ErrorEnum::find(self.modules, error_path) {
+            Some(error_enum) => error_enum,
+            None => {
+                warn("Could not find error enum.");
+                return;
             }
         };
 
-        if let Some(error_index) = content.find(&format!("pub enum")) {
-            let error = get_item(content.get(error_index..).unwrap(), '{');
-            let error = error.replace("#[error", "#[msg");
-            let error_name = get_item_name_from_full_item(&error);
-            info!("Creating error: {}", error_name.bold());
-
-            let mut anchor_content = self.anchor_content.borrow_mut();
-            anchor_content.push_str(&format!("#[error_code]{error}"));
-        }
-
-        Ok(())
+        info!("Creating error: {}", error_enum.name.bold());
+        model.errors = Some(ErrorsModel::new(&error_enum));
     }
 
-    fn write_anchor_dummy(&self) -> GeneratorResult {
-        let mut anchor_content = self.anchor_content.borrow_mut();
-
-        for replecable_type in self.replecable_types.borrow().iter() {
-            (*anchor_content) = (*anchor_content)
-                .replace(
-                    &format!(": {},", replecable_type[0]),
-                    &format!(": {},", replecable_type[1]),
-                )
-                .replace(
-                    &format!("<{}", replecable_type[0]),
-                    &format!("<{}", replecable_type[1]),
-                )
-                .replace(
-                    &format!(", {}", replecable_type[0]),
-                    &format!(", {}", replecable_type[1]),
-                )
-                .replace(
-                    &format!("{}>", replecable_type[0]),
-                    &format!("{}>", replecable_type[1]),
-                );
+    /// Set the discriminants of the enums and the leading enum tags of the accounts
+    fn set_discriminants(&self, model: &mut ProgramModel) {
+        for type_def in model.type_defs_mut() {
+            let symbol = match self.find_type(&type_def.name) {
+                Some(symbol) => symbol,
+                None => continue,
+            };
+
+            match &mut type_def.kind {
+                TypeKind::Enum(variants) => {
+                    if let syn::Item::Enum(item_enum) = &symbol.item {
+                        let discriminants = item_enum
+                            .variants
+                            .iter()
+                            .map(|variant| variant.ident.to_string())
+                            .zip(get_discriminants(item_enum, self.modules, &symbol.module))
+                            .collect::<Vec<_>>();
+                        if !is_positional(&discriminants) {
+                            for (variant, (_, discriminant)) in
+                                variants.iter_mut().zip(discriminants)
+                            {
+                                variant.discriminant = Some(discriminant);
+                            }
+                        }
+                    }
+                }
+                _ => {
+                    let discriminator = get_account_discriminator(symbol, self.modules);
+                    match discriminator {
+                        // Tags are Borsh enums, they take a single byte
+                        Some(discriminator) if discriminator.value <= u8::MAX as u64 => {
+                            debug(format!(
+                                "Account '{}' has discriminator '{}::{}'.",
+                                type_def.name, discriminator.enum_name, discriminator.variant
+                            ));
+                            type_def.discriminator = Some(discriminator);
+                        }
+                        _ => {}
+                    }
+                }
+            }
         }
+    }
 
-        fs::write(&self.anchor_path, anchor_content.as_bytes())?;
+    fn write_anchor_dummy(&self, model: &ProgramModel) -> GeneratorResult {
+        fs::create_dir_all(self.anchor_path.parent().unwrap())?;
+        fs::write(&self.anchor_path, model.to_dummy_program())?;
 
         // Format the code
         rustfmt(&self.anchor_path)?;
@@ -793,104 +636,156 @@ impl<'a> ProgramAndIdlGenerator<'a> {
         Ok(())
     }
 
-    fn create_idl(&self) -> GeneratorResult {
+    fn write_model(&self, model: &ProgramModel) -> GeneratorResult {
+        fs::create_dir_all(self.model_path.parent().unwrap())?;
+        fs::write(&self.model_path, to_string_pretty(model)?)?;
+        debug(format!(
+            "Program model is written to '{}'.",
+            self.model_path.display()
+        ));
+
+        Ok(())
+    }
+
+    fn create_idl(&self, model: &ProgramModel) -> GeneratorResult {
         info(format!(
             "Creating Anchor IDL for {}...",
             self.program_info.name
         ));
 
-        let mut idl = self.parse_idl();
-        let mut new_idl = false;
-
-        // Check to see if all instruction arguments exist if they are defined
-        for ix in &idl.instructions {
-            for arg in &ix.args {
-                let mut arg_type = &arg.ty;
-                if let Some(inside_type) = get_inside_type(&arg.ty) {
-                    arg_type = inside_type;
-                }
-
-                if let IdlType::Defined(name) = arg_type {
-                    // Exclude some of the known types like COption
-                    if name.contains("COption") || name.contains("&'") {
-                        continue;
-                    }
-
-                    let mut anchor_content = self.anchor_content.borrow_mut();
-                    if let None = get_local_type(name, anchor_content.as_str()) {
-                        // Defined type doesn't exist in anchor file
-                        // Try to find it from all content
-                        match get_local_type(name, self.all_content) {
-                            Some(defined_type) => {
-                                info!("{}", format!("Adding missing type '{name}'").purple());
-
-                                // Add the type to anchor content
-                                anchor_content.push_str(&format!("\n#[derive(AnchorSerialize, AnchorDeserialize)]\n{defined_type}\n"));
-                                new_idl = true;
-                            }
-                            None => match REPLECABLE_TYPES.iter().find(|el| el[0] == name) {
-                                Some(ty) => {
-                                    self.push_replecable_types(ty);
-                                    new_idl = true;
-                                }
-                                None => {
-                                    error(format!("Instruction: '{}' arg: '{}' type: '{}' is not defined inside the crate.",
-                                    ix.name,
-                                    arg.name,
-                                    name
-                               ))
-                                }
-                            },
-                        }
-                    }
+        let idl_json = model.to_idl();
+        let idl_path = self.idl_generator().idl_path;
+        fs::create_dir_all(idl_path.parent().unwrap())?;
+        match self.idl_generator().format {
+            IdlFormat::Legacy => fs::write(idl_path, to_string_pretty(&idl_json)?)?,
+            IdlFormat::Spec { legacy } => {
+                let address = get_program_id(self.all_content).unwrap_or_else(|| {
+                    warn(format!(
+                        "Could not find program id for {}.",
+                        self.program_info.name
+                    ));
+                    // Default pubkey like the generated packages
+                    "11111111111111111111111111111111"
+                });
+                let spec_idl = model.to_spec_idl(address, self.program_info);
+                fs::write(idl_path, to_string_pretty(&spec_idl)?)?;
+                if legacy {
+                    let legacy_idl_path = idl_path.with_file_name(filename::LEGACY_IDL);
+                    fs::write(legacy_idl_path, to_string_pretty(&idl_json)?)?;
                 }
             }
         }
 
-        if new_idl {
-            // Rewrite the anchor file
-            self.write_anchor_dummy()?;
+        Ok(())
+    }
 
-            // Re-create the idl
-            idl = self.parse_idl();
-        }
+    /// Parse the IDL from the dummy program given by the user and create the model from it, the
+    /// discriminants and the known addresses are set from the native program
+    fn parse_dummy_program(&self) -> Result<ProgramModel, Box<dyn Error>> {
+        info(format!(
+            "Parsing Anchor program for {}...",
+            self.program_info.name
+        ));
+        let idl = idl::file::parse(
+            &self.anchor_path,
+            self.program_info.version.to_owned(),
+            true,
+            false,
+            false,
+        )?
+        .ok_or("Could not find the program module of the dummy program.")?;
+
+        // Anchor doesn't keep the name of the error enum in the IDL
+        let error_name = match idl.errors {
+            Some(_) => get_error_enum_name(&fs::read_to_string(&self.anchor_path)?)
+                .ok_or("Could not find the `#[error_code]` enum of the dummy program.")?,
+            None => String::new(),
+        };
+        let mut model = ProgramModel::from_idl(idl, self.instruction_tag(), error_name);
+
+        // IDL names are camelCase, variant and field names are PascalCase and snake_case
+        let normalize = |name: &str| name.replace('_', "").to_lowercase();
+        let instruction_enum = self.instruction_enum();
+        for (i, ix) in model.instructions.iter_mut().enumerate() {
+            let variant = instruction_enum
+                .iter()
+                .flat_map(|ix_enum| &ix_enum.variants)
+                .find(|variant| normalize(&variant.name) == normalize(&ix.name));
+            let discriminant = variant.map_or(i as u64, |variant| variant.discriminant);
+            ix.discriminant =
+                get_instruction_discriminant(model.instruction_tag, &ix.name, discriminant);
+
+            // Doc comments are not allowed on function parameters, add the argument docs here
+            for arg in &mut ix.args {
+                let field = variant
+                    .iter()
+                    .flat_map(|variant| &variant.args)
+                    .find(|field| normalize(&field.name) == normalize(&arg.name));
+                if let Some(field) = field {
+                    arg.docs = field.docs.to_owned();
+                }
+            }
 
-        // Normalize error codes from 6000 to 0
-        for errors in &mut idl.errors {
-            for error in errors {
-                error.code -= 6000
+            // Signers are given by the callers even if they have a known address
+            for account in &mut ix.accounts {
+                if !account.is_signer && !account.is_variadic {
+                    account.address = self
+                        .program_info
+                        .known_accounts
+                        .find(&account.name)
+                        .map(|known_account| known_account.address.to_owned());
+                }
             }
         }
+        self.set_discriminants(&mut model);
 
-        fs::write(&self.idl_generator().idl_path, to_string_pretty(&idl)?)?;
+        Ok(model)
+    }
 
-        // Delete anchor dummy if configured
-        if !self.idl_generator().keep_dummy_program {
-            // Dangerous O_o
-            // fs::remove_dir_all(self.anchor_path.parent().unwrap())?;
-            fs::remove_file(&self.anchor_path)?;
-            fs::remove_dir(self.anchor_path.parent().unwrap())?;
-        }
+    /// Find the instruction enum once, it's used for both the program and the IDL
+    fn instruction_enum(&self) -> Option<Rc<InstructionEnum>> {
+        self.instruction_enum
+            .borrow_mut()
+            .get_or_insert_with(|| {
+                let instruction_path = self.program_info.layout.instruction.as_deref();
+                InstructionEnum::find(self.modules, instruction_path).map(Rc::new)
+            })
+            .clone()
+    }
 
-        Ok(())
+    /// Find the type by its name, paths of the instruction argument types are used if they exist
+    fn find_type(&self, name: &str) -> Option<&Symbol> {
+        match self.type_paths.borrow().get(name) {
+            Some(path) => self
+                .modules
+                .resolve(path, "crate")
+                .or_else(|| self.modules.resolve_external(path, "crate")),
+            None => self.modules.find_type(name),
+        }
     }
 
-    fn parse_idl(&self) -> Idl {
-        idl::file::parse(
-            &self.anchor_path,
-            self.program_info.version.to_owned(),
-            false,
-            true,
-            false,
-        )
-        .unwrap()
-        .unwrap()
+    /// Instruction tag from the program's `Cargo.toml`, or the one that is inferred from the
+    /// instruction enum
+    fn instruction_tag(&self) -> InstructionTag {
+        self.program_info
+            .instruction_tag
+            .or_else(|| self.instruction_enum().map(|ix_enum| ix_enum.tag))
+            .unwrap_or_default()
     }
+}
 
-    fn push_replecable_types(&self, ty: &'static [&str; 2]) {
-        let mut replecable_types = self.replecable_types.borrow_mut();
-        if !replecable_types.contains(&ty) {
-            replecable_types.push(ty);
+/// Get the name of the `#[error_code]` enum of the Anchor program
+fn get_error_enum_name(content: &str) -> Option<String> {
+    let file = syn::parse_file(content).ok()?;
+    file.items.iter().find_map(|item| match item {
+        syn::Item::Enum(item_enum)
+            if item_enum
+                .attrs
+                .iter()
+                .any(|attr| attr.path.is_ident("error_code")) =>
+        {
+            Some(item_enum.ident.to_string())
         }
-    }
+        _ => None,
+    })
 }
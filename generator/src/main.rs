@@ -1,8 +1,11 @@
+mod analyzer;
 mod cli;
 mod coder;
 mod constants;
 mod generator;
 mod idl;
+mod model;
+mod parser;
 mod utils;
 
 fn main() -> generator::GeneratorResult {
@@ -0,0 +1,1464 @@
+use std::error::Error;
+
+use anchor_syn::idl::{
+    EnumFields, Idl, IdlAccount, IdlAccountItem, IdlEnumVariant, IdlErrorCode, IdlField,
+    IdlInstruction, IdlPda, IdlSeed, IdlSeedAccount, IdlSeedArg, IdlSeedConst, IdlType,
+    IdlTypeDefinition, IdlTypeDefinitionTy,
+};
+use heck::{MixedCase, SnakeCase};
+use serde_derive::Serialize;
+use serde_json::{json, Map, Value};
+use syn::{Fields, Item, Type};
+
+use crate::{
+    constants::idl::{
+        DYNAMIC_MUT_PREFIX, DYNAMIC_SIGNER_PREFIX, ERROR_CODE_OFFSET, IDL_SPEC, INT_TYPES,
+        OPTIONAL_PREFIX, PROGRAM_PRETEXT, VARIADIC_PREFIX,
+    },
+    parser::{
+        account::AccountDiscriminator, common::get_docs, error::ErrorEnum,
+        instruction::InstructionTag,
+    },
+    utils::{
+        common::{pascal_from_camel, snake_from_pascal, warn},
+        generator::ProgramInfo,
+        idl::get_rust_type,
+    },
+};
+
+/// Everything that is known about the program.
+///
+/// The model is created from the source of the native program, or parsed from the user's dummy
+/// program or the legacy IDL given with `--idl-path`. The dummy Anchor program, the IDLs and the
+/// coder of the generated package are created from it. It's written as `model.json` with
+/// `--debug`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgramModel {
+    /// Name of the program module in snake_case
+    pub name: String,
+    pub version: String,
+    /// Crate docs(`//!`)
+    pub docs: Vec<String>,
+    /// Encoding of the instruction discriminants
+    pub instruction_tag: InstructionTag,
+    pub instructions: Vec<InstructionModel>,
+    pub accounts: Vec<TypeModel>,
+    pub types: Vec<TypeModel>,
+    pub errors: Option<ErrorsModel>,
+}
+
+/// Instruction and the accounts of its context
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstructionModel {
+    /// Name of the instruction variant in PascalCase
+    pub name: String,
+    pub docs: Vec<String>,
+    /// `deprecated` attribute of the variant e.g `deprecated(since = "1.0.0")`
+    pub deprecated: Option<String>,
+    pub discriminant: u64,
+    pub args: Vec<FieldModel>,
+    pub accounts: Vec<ContextAccount>,
+}
+
+/// Account of the instruction context
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextAccount {
+    /// Name of the account in snake_case
+    pub name: String,
+    pub docs: Vec<String>,
+    /// Anchor type of the account e.g `Signer<'info>` or `Sysvar<'info, Rent>`
+    pub ty: String,
+    pub is_mut: bool,
+    pub is_signer: bool,
+    /// Only added in some cases
+    pub is_optional: bool,
+    /// Repeated at the end of the accounts
+    pub is_variadic: bool,
+    /// Signer flag depends on the arguments of the builder function
+    pub is_dynamic_signer: bool,
+    /// Writable flag depends on the arguments of the builder function
+    pub is_dynamic_mut: bool,
+    /// Owner from the owner checks of the processor e.g `spl_token::id()`
+    pub owner: Option<String>,
+    /// Fixed address of the known programs and sysvars
+    pub address: Option<String>,
+    pub pda: Option<PdaModel>,
+    /// Variadic accounts that are not the last accounts can't be in the IDL
+    pub is_commented_out: bool,
+}
+
+/// Seeds of a program derived address
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PdaModel {
+    pub seeds: Vec<SeedModel>,
+    /// Program that the address is derived from, `None` for the program itself
+    pub program: Option<SeedModel>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SeedModel {
+    /// Printable constant e.g `vault`
+    Const(String),
+    /// Name of the instruction argument
+    Arg(String),
+    /// Name of the account of the instruction
+    Account(String),
+    /// Constant of any other type, only parsed from the user's dummy program e.g `&[1, 2]`
+    Value { ty: IdlType, value: Value },
+}
+
+/// Struct or enum that is defined in the program
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypeModel {
+    pub name: String,
+    pub docs: Vec<String>,
+    pub kind: TypeKind,
+    /// Leading enum tag of the account
+    pub discriminator: Option<AccountDiscriminator>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TypeKind {
+    Struct(Vec<FieldModel>),
+    /// Tuple structs e.g `pub struct Wrapper(Foo);`, Anchor leaves them out of the IDL
+    TupleStruct(Vec<IdlType>),
+    Enum(Vec<VariantModel>),
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldModel {
+    /// Name of the field in snake_case
+    pub name: String,
+    pub docs: Vec<String>,
+    pub ty: IdlType,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VariantModel {
+    pub name: String,
+    pub docs: Vec<String>,
+    pub fields: VariantFields,
+    /// Discriminant of the variant, only set if the discriminants are not the variant indices
+    pub discriminant: Option<u64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum VariantFields {
+    Unit,
+    Named(Vec<FieldModel>),
+    Tuple(Vec<IdlType>),
+}
+
+/// Custom error enum of the program
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorsModel {
+    pub name: String,
+    pub errors: Vec<ErrorModel>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorModel {
+    pub name: String,
+    pub docs: Vec<String>,
+    pub msg: Option<String>,
+    /// Error code without the Anchor offset(6000)
+    pub code: u32,
+}
+
+impl TypeModel {
+    /// Create the type from the struct or enum item, types of the fields are converted to the IDL
+    /// types with `get_type` e.g `Box<state::Config>` -> `Config`
+    pub fn new(
+        item: &Item,
+        docs: Vec<String>,
+        get_type: impl Fn(&Type) -> IdlType,
+    ) -> Option<Self> {
+        let get_named_fields = |fields: &Fields| {
+            fields
+                .iter()
+                .map(|field| FieldModel {
+                    name: field.ident.as_ref().unwrap().to_string(),
+                    docs: get_docs(&field.attrs),
+                    ty: get_type(&field.ty),
+                })
+                .collect::<Vec<_>>()
+        };
+        let get_unnamed_fields = |fields: &Fields| {
+            fields
+                .iter()
+                .map(|field| get_type(&field.ty))
+                .collect::<Vec<_>>()
+        };
+
+        let (name, kind) = match item {
+            Item::Struct(item_struct) => {
+                let kind = match &item_struct.fields {
+                    Fields::Unnamed(_) => {
+                        TypeKind::TupleStruct(get_unnamed_fields(&item_struct.fields))
+                    }
+                    fields => TypeKind::Struct(get_named_fields(fields)),
+                };
+                (&item_struct.ident, kind)
+            }
+            Item::Enum(item_enum) => {
+                let variants = item_enum
+                    .variants
+                    .iter()
+                    .map(|variant| VariantModel {
+                        name: variant.ident.to_string(),
+                        docs: get_docs(&variant.attrs),
+                        fields: match &variant.fields {
+                            Fields::Named(_) => {
+                                VariantFields::Named(get_named_fields(&variant.fields))
+                            }
+                            Fields::Unnamed(_) => {
+                                VariantFields::Tuple(get_unnamed_fields(&variant.fields))
+                            }
+                            Fields::Unit => VariantFields::Unit,
+                        },
+                        discriminant: None,
+                    })
+                    .collect();
+                (&item_enum.ident, TypeKind::Enum(variants))
+            }
+            _ => return None,
+        };
+
+        Some(Self {
+            name: name.to_string(),
+            docs,
+            kind,
+            discriminator: None,
+        })
+    }
+
+    /// Create the type from the IDL type definition of the user's dummy program
+    fn from_idl(type_def: IdlTypeDefinition) -> Self {
+        let kind = match type_def.ty {
+            IdlTypeDefinitionTy::Struct { fields } => {
+                TypeKind::Struct(fields.into_iter().map(FieldModel::from_idl).collect())
+            }
+            IdlTypeDefinitionTy::Enum { variants } => TypeKind::Enum(
+                variants
+                    .into_iter()
+                    .map(|variant| VariantModel {
+                        name: variant.name,
+                        docs: vec![],
+                        fields: match variant.fields {
+                            None => VariantFields::Unit,
+                            // Anchor doesn't change the case of the variant fields
+                            Some(EnumFields::Named(fields)) => VariantFields::Named(
+                                fields
+                                    .into_iter()
+                                    .map(|field| FieldModel {
+                                        name: field.name,
+                                        docs: field.docs.unwrap_or_default(),
+                                        ty: field.ty,
+                                    })
+                                    .collect(),
+                            ),
+                            Some(EnumFields::Tuple(types)) => VariantFields::Tuple(types),
+                        },
+                        discriminant: None,
+                    })
+                    .collect(),
+            ),
+        };
+
+        Self {
+            name: type_def.name,
+            docs: type_def.docs.unwrap_or_default(),
+            kind,
+            discriminator: None,
+        }
+    }
+
+    /// IDL types that the type refers to
+    fn idl_types(&self) -> Vec<&IdlType> {
+        match &self.kind {
+            TypeKind::Struct(fields) => fields.iter().map(|field| &field.ty).collect(),
+            TypeKind::TupleStruct(types) => types.iter().collect(),
+            TypeKind::Enum(variants) => variants
+                .iter()
+                .flat_map(|variant| match &variant.fields {
+                    VariantFields::Unit => vec![],
+                    VariantFields::Named(fields) => fields.iter().map(|field| &field.ty).collect(),
+                    VariantFields::Tuple(types) => types.iter().collect(),
+                })
+                .collect(),
+        }
+    }
+
+    fn idl_types_mut(&mut self) -> Vec<&mut IdlType> {
+        match &mut self.kind {
+            TypeKind::Struct(fields) => fields.iter_mut().map(|field| &mut field.ty).collect(),
+            TypeKind::TupleStruct(types) => types.iter_mut().collect(),
+            TypeKind::Enum(variants) => variants
+                .iter_mut()
+                .flat_map(|variant| match &mut variant.fields {
+                    VariantFields::Unit => vec![],
+                    VariantFields::Named(fields) => {
+                        fields.iter_mut().map(|field| &mut field.ty).collect()
+                    }
+                    VariantFields::Tuple(types) => types.iter_mut().collect(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Get the struct or enum the way it's written to the dummy program e.g `pub struct Foo {..}`
+    fn to_dummy_item(&self) -> String {
+        let get_fields = |fields: &[FieldModel], indent: &str, vis: &str| {
+            fields
+                .iter()
+                .map(|field| {
+                    format!(
+                        "{}{indent}{vis}{}: {},\n",
+                        get_doc_lines(&field.docs, indent),
+                        field.name,
+                        get_rust_type(&field.ty)
+                    )
+                })
+                .collect::<String>()
+        };
+        let get_types = |types: &[IdlType]| {
+            types
+                .iter()
+                .map(get_rust_type)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        let name = &self.name;
+        match &self.kind {
+            TypeKind::Struct(fields) => {
+                format!(
+                    "pub struct {name} {{\n{}}}\n",
+                    get_fields(fields, "    ", "pub ")
+                )
+            }
+            TypeKind::TupleStruct(types) => format!("pub struct {name}({});\n", get_types(types)),
+            TypeKind::Enum(variants) => {
+                let variants = variants
+                    .iter()
+                    .map(|variant| {
+                        let fields = match &variant.fields {
+                            VariantFields::Unit => String::new(),
+                            VariantFields::Named(fields) => {
+                                format!(" {{\n{}    }}", get_fields(fields, "        ", ""))
+                            }
+                            VariantFields::Tuple(types) => format!("({})", get_types(types)),
+                        };
+                        let discriminant = variant
+                            .discriminant
+                            .map(|discriminant| format!(" = {discriminant}"))
+                            .unwrap_or_default();
+                        format!(
+                            "{}    {}{fields}{discriminant},\n",
+                            get_doc_lines(&variant.docs, "    "),
+                            variant.name
+                        )
+                    })
+                    .collect::<String>();
+                format!("pub enum {name} {{\n{variants}}}\n")
+            }
+        }
+    }
+
+    fn to_idl(&self) -> Option<IdlTypeDefinition> {
+        let ty = match &self.kind {
+            TypeKind::Struct(fields) => IdlTypeDefinitionTy::Struct {
+                fields: fields.iter().map(FieldModel::to_idl).collect(),
+            },
+            TypeKind::TupleStruct(_) => return None,
+            TypeKind::Enum(variants) => IdlTypeDefinitionTy::Enum {
+                variants: variants
+                    .iter()
+                    .map(|variant| IdlEnumVariant {
+                        name: variant.name.to_owned(),
+                        fields: match &variant.fields {
+                            VariantFields::Unit => None,
+                            // Anchor doesn't change the case of the variant fields
+                            VariantFields::Named(fields) => Some(EnumFields::Named(
+                                fields
+                                    .iter()
+                                    .map(|field| IdlField {
+                                        name: field.name.to_owned(),
+                                        ..field.to_idl()
+                                    })
+                                    .collect(),
+                            )),
+                            VariantFields::Tuple(types) => {
+                                Some(EnumFields::Tuple(types.to_owned()))
+                            }
+                        },
+                    })
+                    .collect(),
+            },
+        };
+
+        Some(IdlTypeDefinition {
+            name: self.name.to_owned(),
+            docs: get_idl_docs(&self.docs),
+            ty,
+        })
+    }
+
+    /// Add the variant discriminants and the account discriminator to the IDL type definition,
+    /// Anchor IDLs don't have them
+    fn set_idl_discriminants(&self, type_def: &mut Value) {
+        if let TypeKind::Enum(variants) = &self.kind {
+            let idl_variants = type_def
+                .pointer_mut("/type/variants")
+                .and_then(Value::as_array_mut);
+            for (variant, idl_variant) in variants.iter().zip(idl_variants.into_iter().flatten()) {
+                if let Some(discriminant) = variant.discriminant {
+                    idl_variant["discriminant"] = json!(discriminant);
+                }
+            }
+        }
+        if let Some(discriminator) = &self.discriminator {
+            type_def["discriminator"] = json!({
+                "field": discriminator.field.to_mixed_case(),
+                "enum": discriminator.enum_name,
+                "variant": discriminator.variant,
+                "value": discriminator.value,
+            });
+        }
+    }
+
+    /// Set the discriminants that `set_idl_discriminants` writes to the IDL type definition
+    fn set_discriminants_from_idl(&mut self, type_def: &Value) {
+        if let TypeKind::Enum(variants) = &mut self.kind {
+            let idl_variants = type_def.pointer("/type/variants").and_then(Value::as_array);
+            for (variant, idl_variant) in
+                variants.iter_mut().zip(idl_variants.into_iter().flatten())
+            {
+                variant.discriminant = idl_variant["discriminant"].as_u64();
+            }
+        }
+        let discriminator = &type_def["discriminator"];
+        if let (Some(field), Some(enum_name), Some(variant), Some(value)) = (
+            discriminator["field"].as_str(),
+            discriminator["enum"].as_str(),
+            discriminator["variant"].as_str(),
+            discriminator["value"].as_u64(),
+        ) {
+            self.discriminator = Some(AccountDiscriminator {
+                field: field.to_snake_case(),
+                enum_name: enum_name.to_owned(),
+                variant: variant.to_owned(),
+                value,
+            });
+        }
+    }
+
+    /// Get the type definition in the spec. The tag field of the accounts with a discriminator is
+    /// left out because Anchor removes the discriminator before it decodes the account.
+    fn to_spec(&self, is_account: bool) -> Value {
+        let ty = match &self.kind {
+            TypeKind::Struct(fields) => {
+                let discriminator_field = self
+                    .discriminator
+                    .as_ref()
+                    .filter(|_| is_account)
+                    .map(|discriminator| discriminator.field.as_str());
+                let fields = fields
+                    .iter()
+                    .filter(|field| Some(field.name.as_str()) != discriminator_field)
+                    .map(FieldModel::to_spec)
+                    .collect::<Vec<_>>();
+                json!({ "kind": "struct", "fields": fields })
+            }
+            TypeKind::TupleStruct(types) => json!({
+                "kind": "struct",
+                "fields": types.iter().map(get_spec_type).collect::<Vec<_>>(),
+            }),
+            TypeKind::Enum(variants) => {
+                if variants
+                    .iter()
+                    .any(|variant| variant.discriminant.is_some())
+                {
+                    warn(format!(
+                        "Enum '{}' has explicit discriminants, they can't be written to the spec IDL and the variants are encoded with their indices. Use the legacy IDL for it.",
+                        self.name
+                    ));
+                }
+                let variants = variants
+                    .iter()
+                    .map(|variant| {
+                        let mut spec_variant = json!({ "name": variant.name });
+                        match &variant.fields {
+                            VariantFields::Unit => {}
+                            VariantFields::Named(fields) => {
+                                spec_variant["fields"] =
+                                    fields.iter().map(FieldModel::to_spec).collect();
+                            }
+                            VariantFields::Tuple(types) => {
+                                spec_variant["fields"] = types.iter().map(get_spec_type).collect();
+                            }
+                        }
+                        spec_variant
+                    })
+                    .collect::<Vec<_>>();
+                json!({ "kind": "enum", "variants": variants })
+            }
+        };
+
+        let mut type_def = Map::new();
+        type_def.insert("name".into(), json!(self.name));
+        insert_spec_docs(&mut type_def, &self.docs);
+        type_def.insert("type".into(), ty);
+
+        Value::Object(type_def)
+    }
+
+    fn is_struct(&self) -> bool {
+        matches!(self.kind, TypeKind::Struct(_))
+    }
+}
+
+impl FieldModel {
+    /// Create the field from the camelCase IDL field
+    fn from_idl(field: IdlField) -> Self {
+        Self {
+            name: field.name.to_snake_case(),
+            docs: field.docs.unwrap_or_default(),
+            ty: field.ty,
+        }
+    }
+
+    fn to_idl(&self) -> IdlField {
+        IdlField {
+            name: self.name.to_mixed_case(),
+            docs: get_idl_docs(&self.docs),
+            ty: self.ty.to_owned(),
+        }
+    }
+
+    fn to_spec(&self) -> Value {
+        let mut field = Map::new();
+        field.insert("name".into(), json!(self.name));
+        insert_spec_docs(&mut field, &self.docs);
+        field.insert("type".into(), get_spec_type(&self.ty));
+
+        Value::Object(field)
+    }
+}
+
+impl ErrorsModel {
+    /// Create the errors from the error enum, Anchor only accepts integer literals as error
+    /// discriminants and the following errors continue from the last discriminant
+    pub fn new(error_enum: &ErrorEnum) -> Self {
+        let mut next_code = 0;
+        let errors = error_enum
+            .variants
+            .iter()
+            .map(|variant| {
+                let code = match &variant.discriminant {
+                    Some(discriminant) => match discriminant.parse::<u32>() {
+                        Ok(code) => code,
+                        Err(_) => {
+                            warn(format!(
+                                "Discriminant '{discriminant}' of error '{}' is not a number, skipping.",
+                                variant.name
+                            ));
+                            next_code
+                        }
+                    },
+                    None => next_code,
+                };
+                next_code = code + 1;
+                ErrorModel {
+                    name: variant.name.to_owned(),
+                    docs: variant.docs.to_owned(),
+                    msg: variant.msg.to_owned(),
+                    code,
+                }
+            })
+            .collect();
+
+        Self {
+            name: error_enum.name.to_owned(),
+            errors,
+        }
+    }
+
+    /// Get the error enum the way it's written to the dummy program, messages are written as
+    /// `#[msg("")]` attributes
+    fn to_dummy_item(&self) -> String {
+        let mut next_code = 0;
+        let errors = self
+            .errors
+            .iter()
+            .map(|error| {
+                let msg = match &error.msg {
+                    Some(msg) => format!("    #[msg({msg:?})]\n"),
+                    None => String::new(),
+                };
+                let code = match error.code == next_code {
+                    true => String::new(),
+                    false => format!(" = {}", error.code),
+                };
+                next_code = error.code + 1;
+                format!(
+                    "{}{msg}    {}{code},\n",
+                    get_doc_lines(&error.docs, "    "),
+                    error.name
+                )
+            })
+            .collect::<String>();
+
+        format!("pub enum {} {{\n{errors}}}\n", self.name)
+    }
+}
+
+impl ContextAccount {
+    /// Name of the field in the dummy program. Anchor doesn't have the optional, variadic and
+    /// dynamic accounts so they are prefixed e.g `optional_rent`, and the prefixes are converted
+    /// back to the flags when the IDL is parsed from the dummy program.
+    pub fn field_name(&self) -> String {
+        let mut prefix = String::new();
+        if self.is_optional {
+            prefix.push_str(OPTIONAL_PREFIX);
+        }
+        if self.is_variadic {
+            prefix.push_str(VARIADIC_PREFIX);
+        }
+        if self.is_dynamic_signer {
+            prefix.push_str(DYNAMIC_SIGNER_PREFIX);
+        }
+        if self.is_dynamic_mut {
+            prefix.push_str(DYNAMIC_MUT_PREFIX);
+        }
+        format!("{prefix}{}", self.name)
+    }
+
+    /// Create the account from the IDL account of the user's dummy program, the prefixes of the
+    /// field name are converted back to the flags
+    fn from_idl(account: IdlAccount) -> Self {
+        let mut name = account.name.to_snake_case();
+        let mut strip_prefix = |prefix: &str| match name.strip_prefix(prefix) {
+            Some(stripped) => {
+                name = stripped.to_owned();
+                true
+            }
+            None => false,
+        };
+        let is_optional = strip_prefix(OPTIONAL_PREFIX);
+        let is_variadic = strip_prefix(VARIADIC_PREFIX);
+        let is_dynamic_signer = strip_prefix(DYNAMIC_SIGNER_PREFIX);
+        let is_dynamic_mut = strip_prefix(DYNAMIC_MUT_PREFIX);
+
+        let pda = account.pda.map(|pda| PdaModel {
+            seeds: pda.seeds.into_iter().map(SeedModel::from_idl).collect(),
+            program: pda.program_id.map(SeedModel::from_idl),
+        });
+
+        Self {
+            name,
+            docs: account.docs.unwrap_or_default(),
+            ty: match account.is_signer {
+                true => "Signer<'info>".into(),
+                false => "AccountInfo<'info>".into(),
+            },
+            is_mut: account.is_mut,
+            is_signer: account.is_signer,
+            is_optional,
+            is_variadic,
+            is_dynamic_signer,
+            is_dynamic_mut,
+            owner: None,
+            address: None,
+            pda,
+            is_commented_out: false,
+        }
+    }
+
+    fn to_idl(&self, args: &[FieldModel]) -> Value {
+        let pda = self.pda.as_ref().map(|pda| {
+            let get_seed = |seed: &SeedModel| match seed {
+                SeedModel::Const(value) => IdlSeed::Const(IdlSeedConst {
+                    ty: IdlType::String,
+                    value: json!(value),
+                }),
+                SeedModel::Value { ty, value } => IdlSeed::Const(IdlSeedConst {
+                    ty: ty.to_owned(),
+                    value: value.to_owned(),
+                }),
+                SeedModel::Arg(name) => IdlSeed::Arg(IdlSeedArg {
+                    ty: args
+                        .iter()
+                        .find(|arg| arg.name == *name)
+                        .map(|arg| arg.ty.to_owned())
+                        .unwrap_or(IdlType::PublicKey),
+                    path: name.to_owned(),
+                }),
+                SeedModel::Account(name) => get_account_seed(name),
+            };
+            IdlPda {
+                seeds: pda.seeds.iter().map(get_seed).collect(),
+                program_id: pda.program.as_ref().map(get_seed),
+            }
+        });
+
+        let mut account = json!(IdlAccount {
+            name: self.name.to_mixed_case(),
+            is_mut: self.is_mut,
+            is_signer: self.is_signer,
+            docs: get_idl_docs(&self.docs),
+            pda,
+        });
+        if self.is_optional {
+            account["isOptional"] = json!(true);
+        }
+        if self.is_dynamic_signer {
+            account["isDynamicSigner"] = json!(true);
+        }
+        if self.is_dynamic_mut {
+            account["isDynamicMut"] = json!(true);
+        }
+        if let Some(address) = &self.address {
+            account["address"] = json!(address);
+        }
+
+        account
+    }
+
+    fn to_spec(&self) -> Value {
+        let mut account = Map::new();
+        account.insert("name".into(), json!(self.name));
+        insert_spec_docs(&mut account, &self.docs);
+        for (flag, key) in [
+            (self.is_mut, "writable"),
+            (self.is_signer, "signer"),
+            (self.is_optional, "optional"),
+        ] {
+            if flag {
+                account.insert(key.into(), json!(true));
+            }
+        }
+        if let Some(address) = &self.address {
+            account.insert("address".into(), json!(address));
+        }
+        if let Some(pda) = self.pda.as_ref().and_then(PdaModel::to_spec) {
+            account.insert("pda".into(), pda);
+        }
+
+        Value::Object(account)
+    }
+}
+
+impl PdaModel {
+    /// Get the PDA in the spec, constant seeds are written as bytes. `None` if a constant can't
+    /// be converted to bytes.
+    fn to_spec(&self) -> Option<Value> {
+        let get_seed = |seed: &SeedModel| match seed {
+            SeedModel::Const(value) => Some(json!({ "kind": "const", "value": value.as_bytes() })),
+            SeedModel::Value { value, .. } => {
+                let value = value
+                    .as_array()?
+                    .iter()
+                    .map(|value| value.as_u64().and_then(|value| u8::try_from(value).ok()))
+                    .collect::<Option<Vec<_>>>()?;
+                Some(json!({ "kind": "const", "value": value }))
+            }
+            SeedModel::Arg(name) => Some(json!({ "kind": "arg", "path": name })),
+            SeedModel::Account(name) => Some(json!({ "kind": "account", "path": name })),
+        };
+
+        let seeds = self
+            .seeds
+            .iter()
+            .map(get_seed)
+            .collect::<Option<Vec<_>>>()?;
+        let mut pda = json!({ "seeds": seeds });
+        if let Some(program) = &self.program {
+            pda["program"] = get_seed(program)?;
+        }
+
+        Some(pda)
+    }
+}
+
+impl InstructionModel {
+    /// Get the instruction function of the dummy program, argument docs are written as comments
+    /// because doc comments are not allowed on function parameters
+    fn to_dummy_fn(&self) -> String {
+        let docs = get_doc_lines(&self.docs, "");
+        let deprecated = self
+            .deprecated
+            .as_ref()
+            .map(|attr| format!("#[{attr}]\n"))
+            .unwrap_or_default();
+        let args = self
+            .args
+            .iter()
+            .map(|arg| {
+                let comments = arg
+                    .docs
+                    .iter()
+                    .map(|doc| format!("//{doc}\n"))
+                    .collect::<String>();
+                format!(",\n{comments}{}: {}", arg.name, get_rust_type(&arg.ty))
+            })
+            .collect::<String>();
+
+        format!(
+            "{docs}{deprecated}pub fn {}(ctx: Context<{}>{args}) -> Result<()> {{ Ok(()) }}\n\n",
+            snake_from_pascal(&self.name),
+            self.name
+        )
+    }
+
+    /// Get the `#[derive(Accounts)]` struct of the instruction, the flags and the seeds of the
+    /// accounts are written as constraints
+    fn to_dummy_context(&self) -> String {
+        let field_name = |name: &str| {
+            self.accounts
+                .iter()
+                .find(|acc| acc.name == name)
+                .map(ContextAccount::field_name)
+                .unwrap_or_else(|| name.to_owned())
+        };
+
+        let mut uses_args = false;
+        let mut get_seed = |seed: &SeedModel| match seed {
+            SeedModel::Const(value) => format!("b\"{value}\".as_ref()"),
+            SeedModel::Value { value, .. } => format!("&{value}"),
+            SeedModel::Arg(name) => {
+                uses_args = true;
+                let ty = self.args.iter().find(|arg| arg.name == *name);
+                match ty.map(|arg| get_rust_type(&arg.ty)).as_deref() {
+                    Some("String") => format!("{name}.as_bytes()"),
+                    Some(ty) if INT_TYPES.contains(&ty) => {
+                        format!("{name}.to_le_bytes().as_ref()")
+                    }
+                    _ => format!("{name}.as_ref()"),
+                }
+            }
+            SeedModel::Account(name) => format!("{}.key().as_ref()", field_name(name)),
+        };
+
+        let mut context = String::new();
+        for account in &self.accounts {
+            for doc in &account.docs {
+                context.push_str(&format!("/// {doc}\n"));
+            }
+
+            let mut constraints = [
+                account.is_mut.then(|| "mut".to_owned()),
+                account
+                    .owner
+                    .as_ref()
+                    .map(|owner| format!("owner = {owner}")),
+            ]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+            // Known accounts without an Anchor type e.g the stake program
+            if let (true, Some(address)) =
+                (account.ty.starts_with("UncheckedAccount"), &account.address)
+            {
+                constraints.push(format!(
+                    "address = anchor_lang::solana_program::pubkey!(\"{address}\")"
+                ));
+            }
+            if let Some(pda) = &account.pda {
+                let seeds = pda.seeds.iter().map(&mut get_seed).collect::<Vec<_>>();
+                let mut seeds = format!("seeds = [{}], bump", seeds.join(", "));
+                if let Some(program) = &pda.program {
+                    let program = match program {
+                        SeedModel::Account(name) => format!("{}.key()", field_name(name)),
+                        seed => get_seed(seed),
+                    };
+                    seeds.push_str(&format!(", seeds::program = {program}"));
+                }
+                constraints.push(seeds);
+            }
+
+            // Add comment
+            let comment = if account.is_commented_out { "// " } else { "" };
+            if !constraints.is_empty() {
+                context.push_str(&format!(
+                    "{comment}#[account({})]\n",
+                    constraints.join(", ")
+                ));
+            }
+            context.push_str(&format!(
+                "{comment}{}: {},\n",
+                account.field_name(),
+                account.ty
+            ));
+        }
+
+        // Arguments are only visible to the seeds if they are declared
+        let instruction = match uses_args {
+            true => {
+                let args = self
+                    .args
+                    .iter()
+                    .map(|arg| format!("{}: {}", arg.name, get_rust_type(&arg.ty)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("#[instruction({args})]\n")
+            }
+            false => String::new(),
+        };
+
+        format!(
+            "#[derive(Accounts)]\n{instruction}pub struct {}<'info> {{\n{context}}}\n\n",
+            self.name
+        )
+    }
+
+    fn to_idl(&self, is_positional: bool) -> Value {
+        let args = self.args.iter().map(FieldModel::to_idl).collect();
+        let mut ix = json!(IdlInstruction {
+            name: snake_from_pascal(&self.name).to_mixed_case(),
+            docs: get_idl_docs(&self.docs),
+            accounts: vec![],
+            args,
+            returns: None,
+        });
+
+        let (variadic_accounts, accounts) = self
+            .accounts
+            .iter()
+            .filter(|acc| !acc.is_commented_out)
+            .partition::<Vec<_>, _>(|acc| acc.is_variadic);
+        ix["accounts"] = accounts.iter().map(|acc| acc.to_idl(&self.args)).collect();
+        // Discriminants are only written if they are not the same as the positions
+        if !is_positional {
+            ix["discriminant"] = json!(self.discriminant);
+        }
+        if !variadic_accounts.is_empty() {
+            ix["variadicAccounts"] = variadic_accounts
+                .iter()
+                .map(|acc| acc.to_idl(&self.args))
+                .collect();
+        }
+
+        ix
+    }
+
+    fn to_spec(&self, instruction_tag: InstructionTag) -> Value {
+        let mut ix = Map::new();
+        ix.insert("name".into(), json!(snake_from_pascal(&self.name)));
+        insert_spec_docs(&mut ix, &self.docs);
+        ix.insert(
+            "discriminator".into(),
+            json!(get_instruction_discriminator(
+                self.discriminant,
+                instruction_tag
+            )),
+        );
+        ix.insert(
+            "accounts".into(),
+            self.accounts
+                .iter()
+                .filter(|acc| !acc.is_commented_out && !acc.is_variadic)
+                .map(ContextAccount::to_spec)
+                .collect(),
+        );
+        ix.insert(
+            "args".into(),
+            self.args.iter().map(FieldModel::to_spec).collect(),
+        );
+
+        Value::Object(ix)
+    }
+}
+
+impl ProgramModel {
+    /// Create the model from the IDL that Anchor parses from the user's dummy program.
+    ///
+    /// Anchor IDLs don't have the discriminants, they are set from the native program after.
+    pub fn from_idl(idl: Idl, instruction_tag: InstructionTag, error_name: String) -> Self {
+        let instructions = idl
+            .instructions
+            .into_iter()
+            .map(|ix| InstructionModel {
+                name: pascal_from_camel(&ix.name),
+                docs: ix.docs.unwrap_or_default(),
+                deprecated: None,
+                discriminant: 0,
+                args: ix.args.into_iter().map(FieldModel::from_idl).collect(),
+                accounts: get_context_accounts(ix.accounts),
+            })
+            .collect();
+
+        let errors = idl.errors.map(|errors| ErrorsModel {
+            name: error_name,
+            errors: errors
+                .into_iter()
+                .map(|error| ErrorModel {
+                    code: error
+                        .code
+                        .checked_sub(ERROR_CODE_OFFSET)
+                        .unwrap_or_else(|| {
+                            warn(format!(
+                            "Code of error '{}' is below the Anchor offset({ERROR_CODE_OFFSET}).",
+                            error.name
+                        ));
+                            error.code
+                        }),
+                    name: error.name,
+                    docs: vec![],
+                    msg: error.msg,
+                })
+                .collect(),
+        });
+
+        Self {
+            name: idl.name,
+            version: idl.version,
+            docs: idl.docs.unwrap_or_default(),
+            instruction_tag,
+            instructions,
+            accounts: idl.accounts.into_iter().map(TypeModel::from_idl).collect(),
+            types: idl.types.into_iter().map(TypeModel::from_idl).collect(),
+            errors,
+        }
+    }
+
+    /// Create the model from the legacy IDL that is given with `--idl-path`, the extra fields of
+    /// the generated packages e.g `isOptional` and the discriminants are read from the JSON.
+    pub fn from_legacy_idl(idl_json: &Value) -> Result<Self, Box<dyn Error>> {
+        if idl_json.pointer("/metadata/spec").is_some() {
+            return Err("Packages are generated from the legacy IDL, create it with `idl --legacy` and use idl.legacy.json.".into());
+        }
+        let idl: Idl = serde_json::from_value(idl_json.to_owned())
+            .map_err(|e| format!("Could not parse the legacy IDL: {e}"))?;
+        let instruction_tag = idl_json
+            .get("instructionTag")
+            .map(|tag| serde_json::from_value(tag.to_owned()))
+            .transpose()
+            .map_err(|e| format!("Could not parse the instruction tag of the legacy IDL: {e}"))?
+            .unwrap_or_default();
+
+        // The legacy IDL doesn't have the name of the error enum, it's only used by the dummy
+        // program which isn't created from the IDL
+        let mut model = Self::from_idl(idl, instruction_tag, String::new());
+
+        let get_flag = |account: &Value, key: &str| account[key] == true;
+        let idl_ixs = idl_json["instructions"].as_array().into_iter().flatten();
+        for (i, (ix, idl_ix)) in model.instructions.iter_mut().zip(idl_ixs).enumerate() {
+            ix.discriminant = idl_ix["discriminant"].as_u64().unwrap_or(i as u64);
+
+            let idl_accounts = idl_ix["accounts"].as_array().into_iter().flatten();
+            for (account, idl_account) in ix.accounts.iter_mut().zip(idl_accounts) {
+                account.is_optional = get_flag(idl_account, "isOptional");
+                account.is_dynamic_signer = get_flag(idl_account, "isDynamicSigner");
+                account.is_dynamic_mut = get_flag(idl_account, "isDynamicMut");
+                account.address = idl_account["address"].as_str().map(str::to_owned);
+            }
+            for idl_account in idl_ix["variadicAccounts"].as_array().into_iter().flatten() {
+                let account: IdlAccount = serde_json::from_value(idl_account.to_owned())
+                    .map_err(|e| format!("Could not parse the variadic account: {e}"))?;
+                ix.accounts.push(ContextAccount {
+                    is_variadic: true,
+                    is_dynamic_signer: get_flag(idl_account, "isDynamicSigner"),
+                    is_dynamic_mut: get_flag(idl_account, "isDynamicMut"),
+                    ..ContextAccount::from_idl(account)
+                });
+            }
+        }
+
+        for (key, type_defs) in [
+            ("accounts", &mut model.accounts),
+            ("types", &mut model.types),
+        ] {
+            let idl_type_defs = idl_json[key].as_array().into_iter().flatten();
+            for (type_def, idl_type_def) in type_defs.iter_mut().zip(idl_type_defs) {
+                type_def.set_discriminants_from_idl(idl_type_def);
+            }
+        }
+
+        Ok(model)
+    }
+
+    /// Whether the type is used by the instructions or the other types
+    pub fn is_type_used(&self, name: &str) -> bool {
+        let type_defs = self.accounts.iter().chain(&self.types);
+        let is_used = |ty: &IdlType| get_type_names(ty).contains(&name);
+        self.instructions.iter().any(|ix| {
+            ix.args.iter().any(|arg| is_used(&arg.ty))
+                || ix.accounts.iter().any(|acc| {
+                    acc.ty
+                        .split(|c: char| !c.is_alphanumeric() && c != '_')
+                        .any(|ty_name| ty_name == name)
+                })
+        }) || type_defs.flat_map(TypeModel::idl_types).any(is_used)
+    }
+
+    /// Whether the struct or the enum is defined in the program
+    pub fn is_type_defined(&self, name: &str) -> bool {
+        self.accounts
+            .iter()
+            .chain(&self.types)
+            .any(|type_def| type_def.name == name)
+    }
+
+    pub fn type_defs_mut(&mut self) -> impl Iterator<Item = &mut TypeModel> {
+        self.accounts.iter_mut().chain(&mut self.types)
+    }
+
+    /// Replace the defined types of the arguments and the fields e.g `UnixTimestamp` -> `i64`
+    pub fn replace_types(&mut self, replace: impl Fn(&str) -> Option<IdlType>) {
+        let types = self
+            .instructions
+            .iter_mut()
+            .flat_map(|ix| ix.args.iter_mut().map(|arg| &mut arg.ty))
+            .chain(
+                self.accounts
+                    .iter_mut()
+                    .chain(&mut self.types)
+                    .flat_map(TypeModel::idl_types_mut),
+            );
+        for ty in types {
+            replace_type(ty, &replace);
+        }
+    }
+
+    /// Get the content of the dummy Anchor program
+    pub fn to_dummy_program(&self) -> String {
+        let mut content = PROGRAM_PRETEXT
+            .replace("<ProgramName>", &self.name)
+            .replace("<ProgramDocs>", &get_doc_lines(&self.docs, ""));
+
+        let mut contexts = String::new();
+        for ix in &self.instructions {
+            content.push_str(&ix.to_dummy_fn());
+            contexts.push_str(&ix.to_dummy_context());
+        }
+        content.push_str("}\n\n");
+        content.push_str(&contexts);
+
+        for account in &self.accounts {
+            content.push_str(&format!(
+                "{}#[account]\n{}\n",
+                get_doc_lines(&account.docs, ""),
+                account.to_dummy_item()
+            ));
+        }
+        for ty in &self.types {
+            content.push_str(&format!(
+                "{}#[derive(AnchorSerialize, AnchorDeserialize)]\n{}\n",
+                get_doc_lines(&ty.docs, ""),
+                ty.to_dummy_item()
+            ));
+        }
+        if let Some(errors) = &self.errors {
+            content.push_str(&format!("#[error_code]\n{}", errors.to_dummy_item()));
+        }
+
+        content
+    }
+
+    /// Get the account and the type definitions of the IDL, the error enum is left out
+    fn idl_type_defs(&self) -> (Vec<&TypeModel>, Vec<&TypeModel>) {
+        let error_name = self.errors.as_ref().map(|errors| errors.name.as_str());
+        let type_defs = || {
+            self.accounts
+                .iter()
+                .chain(&self.types)
+                .filter(move |type_def| Some(type_def.name.as_str()) != error_name)
+        };
+        type_defs()
+            .filter(|type_def| type_def.is_struct())
+            .chain(type_defs().filter(|type_def| !type_def.is_struct()))
+            .partition(|type_def| {
+                type_def.is_struct() && self.accounts.iter().any(|acc| acc.name == type_def.name)
+            })
+    }
+
+    /// Get the legacy IDL(before Anchor 0.30) with the extra fields of the generated packages e.g
+    /// `isOptional`.
+    ///
+    /// Type definitions are in the same order as Anchor parses them from the dummy program, structs
+    /// come before enums and enum accounts are types.
+    pub fn to_idl(&self) -> Value {
+        let (accounts, mut types) = self.idl_type_defs();
+        types.retain(|type_def| type_def.to_idl().is_some());
+
+        let errors = self.errors.as_ref().map(|errors| {
+            errors
+                .errors
+                .iter()
+                .map(|error| IdlErrorCode {
+                    code: error.code,
+                    name: error.name.to_owned(),
+                    msg: error.msg.to_owned(),
+                })
+                .collect()
+        });
+
+        let mut idl = json!(Idl {
+            version: self.version.to_owned(),
+            name: self.name.to_owned(),
+            docs: get_idl_docs(&self.docs),
+            constants: vec![],
+            instructions: vec![],
+            state: None,
+            accounts: accounts.iter().filter_map(|acc| acc.to_idl()).collect(),
+            types: types.iter().filter_map(|ty| ty.to_idl()).collect(),
+            events: None,
+            errors,
+            metadata: None,
+        });
+
+        let is_positional = self
+            .instructions
+            .iter()
+            .enumerate()
+            .all(|(i, ix)| i as u64 == ix.discriminant);
+        idl["instructions"] = self
+            .instructions
+            .iter()
+            .map(|ix| ix.to_idl(is_positional))
+            .collect();
+        for (key, type_defs) in [("accounts", accounts), ("types", types)] {
+            let idl_type_defs = idl.get_mut(key).and_then(Value::as_array_mut);
+            for (type_def, idl_type_def) in
+                type_defs.iter().zip(idl_type_defs.into_iter().flatten())
+            {
+                type_def.set_idl_discriminants(idl_type_def);
+            }
+        }
+        if self.instruction_tag != InstructionTag::U8 {
+            idl["instructionTag"] = json!(self.instruction_tag);
+        }
+
+        idl
+    }
+
+    /// Get the IDL in the current Anchor IDL spec.
+    ///
+    /// Names are snake_case, instructions and accounts have their discriminator bytes e.g the `u8`
+    /// tag of the instruction or the leading enum tag of the account, and the account type
+    /// definitions are in the `types`. Variadic accounts and the dynamic flags are not in the
+    /// spec, native programs don't have events.
+    pub fn to_spec_idl(&self, address: &str, program_info: &ProgramInfo) -> Value {
+        let mut metadata = json!({
+            "name": self.name,
+            "version": self.version,
+            "spec": IDL_SPEC,
+        });
+        if !program_info.description.is_empty() {
+            metadata["description"] = json!(program_info.description);
+        }
+        if !program_info.repository.is_empty() {
+            metadata["repository"] = json!(program_info.repository);
+        }
+
+        let (accounts, types) = self.idl_type_defs();
+        // Accounts only have their discriminators, their types are defined in `types`. Accounts
+        // without a tag can't be told apart from the other accounts, they are only types.
+        let spec_accounts = accounts
+            .iter()
+            .filter_map(|acc| {
+                let discriminator = acc.discriminator.as_ref()?;
+                Some(json!({ "name": acc.name, "discriminator": [discriminator.value] }))
+            })
+            .collect::<Vec<_>>();
+        let spec_types = accounts
+            .iter()
+            .map(|acc| acc.to_spec(true))
+            .chain(types.iter().map(|ty| ty.to_spec(false)))
+            .collect::<Vec<_>>();
+
+        let mut spec = Map::new();
+        spec.insert("address".into(), json!(address));
+        spec.insert("metadata".into(), metadata);
+        insert_spec_docs(&mut spec, &self.docs);
+        spec.insert(
+            "instructions".into(),
+            self.instructions
+                .iter()
+                .map(|ix| ix.to_spec(self.instruction_tag))
+                .collect(),
+        );
+        if !spec_accounts.is_empty() {
+            spec.insert("accounts".into(), json!(spec_accounts));
+        }
+        if let Some(errors) = &self.errors {
+            let errors = errors
+                .errors
+                .iter()
+                .map(|error| IdlErrorCode {
+                    code: error.code,
+                    name: error.name.to_owned(),
+                    msg: error.msg.to_owned(),
+                })
+                .collect::<Vec<_>>();
+            spec.insert("errors".into(), json!(errors));
+        }
+        if !spec_types.is_empty() {
+            spec.insert("types".into(), json!(spec_types));
+        }
+
+        Value::Object(spec)
+    }
+}
+
+impl SeedModel {
+    /// Create the seed from the IDL seed of the user's dummy program, account seeds refer to the
+    /// prefixed field names e.g `optional_mint` -> `mint`
+    fn from_idl(seed: IdlSeed) -> Self {
+        match seed {
+            IdlSeed::Const(IdlSeedConst {
+                ty: IdlType::String,
+                value: Value::String(value),
+            }) => Self::Const(value),
+            IdlSeed::Const(IdlSeedConst { ty, value }) => Self::Value { ty, value },
+            IdlSeed::Arg(arg) => Self::Arg(arg.path),
+            IdlSeed::Account(account) => {
+                let path = [
+                    OPTIONAL_PREFIX,
+                    VARIADIC_PREFIX,
+                    DYNAMIC_SIGNER_PREFIX,
+                    DYNAMIC_MUT_PREFIX,
+                ]
+                .iter()
+                .fold(account.path.as_str(), |path, prefix| {
+                    path.strip_prefix(prefix).unwrap_or(path)
+                });
+                Self::Account(path.to_owned())
+            }
+        }
+    }
+}
+
+/// Flatten the accounts of the instruction, composite accounts are not supported
+fn get_context_accounts(items: Vec<IdlAccountItem>) -> Vec<ContextAccount> {
+    items
+        .into_iter()
+        .flat_map(|item| match item {
+            IdlAccountItem::IdlAccount(account) => vec![ContextAccount::from_idl(account)],
+            IdlAccountItem::IdlAccounts(accounts) => {
+                warn(format!(
+                    "Composite accounts '{}' are not supported, flattening.",
+                    accounts.name
+                ));
+                get_context_accounts(accounts.accounts)
+            }
+        })
+        .collect()
+}
+
+fn get_account_seed(name: &str) -> IdlSeed {
+    IdlSeed::Account(IdlSeedAccount {
+        ty: IdlType::PublicKey,
+        account: None,
+        path: name.to_owned(),
+    })
+}
+
+/// Get the names of the defined types inside the type e.g `Option<COption<Config>>` -> `COption`
+/// and `Config`
+fn get_type_names(ty: &IdlType) -> Vec<&str> {
+    match ty {
+        IdlType::Defined(name) => name
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .filter(|name| !name.is_empty())
+            .collect(),
+        IdlType::Option(inside) | IdlType::Vec(inside) | IdlType::Array(inside, _) => {
+            get_type_names(inside)
+        }
+        _ => vec![],
+    }
+}
+
+fn replace_type(ty: &mut IdlType, replace: &impl Fn(&str) -> Option<IdlType>) {
+    match ty {
+        IdlType::Defined(name) => {
+            // `COption<T>` is defined by the generated packages
+            let replaced = match name
+                .strip_prefix("COption<")
+                .and_then(|inside| inside.strip_suffix('>'))
+            {
+                Some(inside) => replace(inside)
+                    .map(|inside| IdlType::Defined(format!("COption<{}>", get_rust_type(&inside)))),
+                None => replace(name),
+            };
+            if let Some(replaced) = replaced {
+                *ty = replaced;
+            }
+        }
+        IdlType::Option(inside) | IdlType::Vec(inside) | IdlType::Array(inside, _) => {
+            replace_type(inside, replace)
+        }
+        _ => {}
+    }
+}
+
+fn get_doc_lines(docs: &[String], indent: &str) -> String {
+    docs.iter()
+        .map(|doc| format!("{indent}///{doc}\n"))
+        .collect()
+}
+
+/// Get the docs the way Anchor writes them to the IDL, lines are trimmed and `CHECK:` lines are
+/// left out
+fn get_idl_docs(docs: &[String]) -> Option<Vec<String>> {
+    let docs = docs
+        .iter()
+        .map(|doc| doc.trim().to_owned())
+        .filter(|doc| !doc.starts_with("CHECK:"))
+        .collect::<Vec<_>>();
+    (!docs.is_empty()).then_some(docs)
+}
+
+/// Insert the docs to the spec object if there are any
+fn insert_spec_docs(object: &mut Map<String, Value>, docs: &[String]) {
+    if let Some(docs) = get_idl_docs(docs) {
+        object.insert("docs".into(), json!(docs));
+    }
+}
+
+/// Get the bytes of the instruction tag, sighash discriminants are stored as little endian
+fn get_instruction_discriminator(discriminant: u64, tag: InstructionTag) -> Vec<u8> {
+    let size = match tag {
+        InstructionTag::U8 => 1,
+        InstructionTag::U16 => 2,
+        InstructionTag::U32 => 4,
+        InstructionTag::U64 | InstructionTag::Sighash => 8,
+    };
+    discriminant.to_le_bytes()[..size].to_vec()
+}
+
+/// Get the type in the spec e.g `publicKey` -> `"pubkey"` and `Config` ->
+/// `{ "defined": { "name": "Config" } }`
+fn get_spec_type(ty: &IdlType) -> Value {
+    match ty {
+        IdlType::PublicKey => json!("pubkey"),
+        IdlType::Defined(name) => {
+            // `COption<T>` is a built-in type of the spec
+            let inside = name
+                .strip_prefix("COption<")
+                .and_then(|inside| inside.strip_suffix('>'))
+                .and_then(|inside| inside.parse::<IdlType>().ok());
+            match inside {
+                Some(inside) => json!({ "coption": get_spec_type(&inside) }),
+                None => json!({ "defined": { "name": name } }),
+            }
+        }
+        IdlType::Option(inside) => json!({ "option": get_spec_type(inside) }),
+        IdlType::Vec(inside) => json!({ "vec": get_spec_type(inside) }),
+        IdlType::Array(inside, len) => json!({ "array": [get_spec_type(inside), len] }),
+        _ => json!(ty),
+    }
+}
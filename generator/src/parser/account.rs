@@ -0,0 +1,309 @@
+use quote::ToTokens;
+use serde_derive::Serialize;
+use syn::{
+    visit::{self, Visit},
+    BinOp, Expr, ExprBinary, ExprPath, ExprStruct, Field, Fields, GenericArgument, Item, ItemImpl,
+    PathArguments, Signature, Type, TypePath,
+};
+
+use super::{
+    common::{get_discriminants, get_last_segment, get_trait_name, tokens_to_string},
+    module::{ModuleGraph, Symbol},
+};
+use crate::constants::idl::ACCOUNT_TRAITS;
+
+/// Find the structs that are likely to be accounts or types of the accounts.
+///
+/// These are the structs that implement the account traits(e.g `Pack`) and the structs inside the
+/// `state` modules, in the order they are defined.
+pub fn find_account_structs(modules: &ModuleGraph) -> Vec<&Symbol> {
+    let account_paths = modules
+        .impls()
+        .filter(|(_, item_impl, _)| {
+            get_trait_name(item_impl).is_some_and(|name| ACCOUNT_TRAITS.contains(&name.as_str()))
+        })
+        .filter_map(|(_, _, symbol)| symbol.map(|symbol| symbol.path.as_str()))
+        .collect::<Vec<_>>();
+
+    modules
+        .symbols()
+        .filter(|symbol| matches!(symbol.item, Item::Struct(_)))
+        .filter(|symbol| {
+            account_paths.contains(&symbol.path.as_str()) || is_state_module(&symbol.module)
+        })
+        .collect()
+}
+
+/// Find the names of the types that are likely to be accounts, in this order:
+///
+/// - Types that implement the account traits e.g `impl Pack for Mint`
+/// - Types that are deserialized with `T::try_from_slice(..)`
+/// - Types that are deserialized with `try_from_slice_unchecked::<T>(..)`
+pub fn find_account_names(modules: &ModuleGraph) -> Vec<String> {
+    let mut names = modules
+        .impls()
+        .filter(|(_, item_impl, _)| {
+            get_trait_name(item_impl).is_some_and(|name| ACCOUNT_TRAITS.contains(&name.as_str()))
+        })
+        .filter_map(|(_, item_impl, _)| match &*item_impl.self_ty {
+            Type::Path(type_path) => get_last_segment(&type_path.path),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let mut visitor = DeserializeVisitor::default();
+    for item in modules.modules().iter().flat_map(|module| &module.items) {
+        visitor.visit_item(item);
+    }
+    names.extend(visitor.checked);
+    names.extend(visitor.unchecked);
+
+    names
+}
+
+/// Get the names of the types that the fields and the function signatures of the crate use e.g
+/// `Config` for `pub config: Option<Config>` or `fn process(config: Config) -> Result<Config, E>`
+pub fn find_referenced_type_names(modules: &ModuleGraph) -> Vec<String> {
+    let mut visitor = ReferencedTypesVisitor::default();
+    for item in modules.modules().iter().flat_map(|module| &module.items) {
+        visitor.visit_item(item);
+    }
+
+    visitor.0 .0
+}
+
+/// Types that are deserialized from the account data
+#[derive(Default)]
+struct DeserializeVisitor {
+    /// e.g `Mint` for `Mint::try_from_slice(&data)`
+    checked: Vec<String>,
+    /// e.g `Mint` for `try_from_slice_unchecked::<Mint>(&data)`
+    unchecked: Vec<String>,
+}
+
+impl<'ast> Visit<'ast> for DeserializeVisitor {
+    fn visit_expr_path(&mut self, expr_path: &'ast ExprPath) {
+        let segments = &expr_path.path.segments;
+        let mut rev_segments = segments.iter().rev();
+        if let Some(last) = rev_segments.next() {
+            if last.ident == "try_from_slice" {
+                self.checked
+                    .extend(rev_segments.next().map(|segment| segment.ident.to_string()));
+            } else if last.ident == "try_from_slice_unchecked" {
+                if let PathArguments::AngleBracketed(args) = &last.arguments {
+                    if let Some(GenericArgument::Type(Type::Path(type_path))) = args.args.first() {
+                        self.unchecked.extend(get_last_segment(&type_path.path));
+                    }
+                }
+            }
+        }
+        visit::visit_expr_path(self, expr_path);
+    }
+}
+
+/// Types that are used by the fields and the function signatures
+#[derive(Default)]
+struct ReferencedTypesVisitor(TypeNamesVisitor);
+
+impl<'ast> Visit<'ast> for ReferencedTypesVisitor {
+    fn visit_field(&mut self, field: &'ast Field) {
+        self.0.visit_type(&field.ty);
+    }
+
+    fn visit_signature(&mut self, sig: &'ast Signature) {
+        self.0.visit_signature(sig);
+    }
+}
+
+/// Names of all types inside the visited node e.g `Option` and `Config` for `Option<Config>`
+#[derive(Default)]
+struct TypeNamesVisitor(Vec<String>);
+
+impl<'ast> Visit<'ast> for TypeNamesVisitor {
+    fn visit_type_path(&mut self, type_path: &'ast TypePath) {
+        if let Some(name) = get_last_segment(&type_path.path) {
+            if !self.0.contains(&name) {
+                self.0.push(name);
+            }
+        }
+        visit::visit_type_path(self, type_path);
+    }
+}
+
+/// Leading enum field of an account that tells the kind of the account e.g
+/// `pub account_type: GovernanceAccountType` of `Realm` with `GovernanceAccountType::RealmV2`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountDiscriminator {
+    /// Name of the tag field e.g `account_type`
+    pub field: String,
+    /// Name of the tag enum e.g `GovernanceAccountType`
+    pub enum_name: String,
+    /// Variant of the enum that the account uses e.g `RealmV2`
+    pub variant: String,
+    /// Discriminant of the variant, it's the first byte of the account data
+    pub value: u64,
+}
+
+/// Get the discriminator of the account struct if its first field is a fieldless enum of the
+/// crate.
+///
+/// The variant of the account is taken from the code that creates or checks the account e.g
+/// `Realm { account_type: GovernanceAccountType::RealmV2, .. }` or
+/// `self.key == Key::MetadataV1` inside the `impl` blocks of the account. If the variant can't be
+/// found from the code, the variant with the same name as the account is used.
+pub fn get_account_discriminator(
+    symbol: &Symbol,
+    modules: &ModuleGraph,
+) -> Option<AccountDiscriminator> {
+    let item_struct = match &symbol.item {
+        Item::Struct(item_struct) => item_struct,
+        _ => return None,
+    };
+    let field = match &item_struct.fields {
+        Fields::Named(fields) => fields.named.first()?,
+        _ => return None,
+    };
+    let field_name = field.ident.as_ref()?.to_string();
+    let ty = tokens_to_string(field.ty.to_token_stream().to_string());
+    let enum_symbol = modules.resolve(&ty, &symbol.module)?;
+    let item_enum = match &enum_symbol.item {
+        Item::Enum(item_enum) => item_enum,
+        _ => return None,
+    };
+    if item_enum
+        .variants
+        .iter()
+        .any(|variant| !matches!(variant.fields, Fields::Unit))
+    {
+        return None;
+    }
+
+    let enum_name = enum_symbol.name.to_owned();
+    let variants = item_enum
+        .variants
+        .iter()
+        .map(|variant| variant.ident.to_string())
+        .collect::<Vec<_>>();
+
+    let mut finder = VariantFinder {
+        struct_name: &symbol.name,
+        field: &field_name,
+        enum_name: &enum_name,
+        is_account_impl: false,
+        found: vec![],
+    };
+    for module in modules.modules() {
+        for item in &module.items {
+            finder.visit_item(item);
+        }
+    }
+    finder.found.retain(|variant| variants.contains(variant));
+    finder.found.sort();
+    finder.found.dedup();
+
+    let variant = match finder.found.as_slice() {
+        [variant] => Some(variant.to_owned()),
+        found => {
+            // Prefer the variant with the same name when the account uses more than one variant
+            let candidates = match found.is_empty() {
+                true => &variants,
+                false => found,
+            };
+            candidates
+                .iter()
+                .find(|variant| **variant == symbol.name)
+                .cloned()
+        }
+    }?;
+    let index = variants.iter().position(|name| *name == variant)?;
+    let value = get_discriminants(item_enum, modules, &enum_symbol.module)[index];
+
+    Some(AccountDiscriminator {
+        field: field_name,
+        enum_name,
+        variant,
+        value,
+    })
+}
+
+/// Find the variants of the tag enum that the account is created with or compared against
+struct VariantFinder<'a> {
+    struct_name: &'a str,
+    field: &'a str,
+    enum_name: &'a str,
+    /// Whether the visitor is inside an `impl` block of the account
+    is_account_impl: bool,
+    found: Vec<String>,
+}
+
+impl VariantFinder<'_> {
+    /// Get the variant name if the expression is a variant of the tag enum e.g `Key::MetadataV1`
+    fn get_variant(&self, expr: &Expr) -> Option<String> {
+        let path = match expr {
+            Expr::Path(expr_path) => &expr_path.path,
+            _ => return None,
+        };
+        let mut segments = path.segments.iter().rev();
+        let variant = segments.next()?.ident.to_string();
+        (segments.next()?.ident == self.enum_name).then_some(variant)
+    }
+
+    /// Whether the expression is the tag field of the account e.g `self.account_type`
+    fn is_tag_field(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Field(field) => {
+                self.is_account_impl
+                    && field.member.to_token_stream().to_string() == self.field
+                    && tokens_to_string(field.base.to_token_stream().to_string()) == "self"
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for VariantFinder<'_> {
+    fn visit_item_impl(&mut self, item_impl: &'ast ItemImpl) {
+        let self_ty = tokens_to_string(item_impl.self_ty.to_token_stream().to_string());
+        let is_account_impl = self.is_account_impl;
+        self.is_account_impl = self_ty.rsplit("::").next() == Some(self.struct_name);
+        visit::visit_item_impl(self, item_impl);
+        self.is_account_impl = is_account_impl;
+    }
+
+    fn visit_expr_struct(&mut self, expr_struct: &'ast ExprStruct) {
+        let name = get_last_segment(&expr_struct.path).unwrap_or_default();
+        let is_account = name == self.struct_name || (name == "Self" && self.is_account_impl);
+        if is_account {
+            let variant = expr_struct
+                .fields
+                .iter()
+                .find(|field| field.member.to_token_stream().to_string() == self.field)
+                .and_then(|field| self.get_variant(&field.expr));
+            self.found.extend(variant);
+        }
+        visit::visit_expr_struct(self, expr_struct);
+    }
+
+    fn visit_expr_binary(&mut self, expr_binary: &'ast ExprBinary) {
+        if matches!(expr_binary.op, BinOp::Eq(_)) {
+            let variant = match (
+                self.is_tag_field(&expr_binary.left),
+                self.is_tag_field(&expr_binary.right),
+            ) {
+                (true, _) => self.get_variant(&expr_binary.right),
+                (_, true) => self.get_variant(&expr_binary.left),
+                _ => None,
+            };
+            self.found.extend(variant);
+        }
+        visit::visit_expr_binary(self, expr_binary);
+    }
+}
+
+/// Whether the module is a state module or inside one e.g `crate::state::mint`
+fn is_state_module(module: &str) -> bool {
+    module
+        .split("::")
+        .any(|segment| segment == "state" || segment == "states")
+}
@@ -0,0 +1,498 @@
+use std::collections::HashMap;
+
+use quote::ToTokens;
+use syn::{
+    parse::ParseStream,
+    punctuated::Punctuated,
+    visit::{self, Visit},
+    Block, Expr, ExprCall, ExprForLoop, ExprIf, ExprLoop, ExprMatch, ExprMethodCall, ExprStruct,
+    ExprWhile, FnArg, GenericArgument, ImplItem, Item, Local, Macro, Member, Pat, PathArguments,
+    ReturnType, Signature, Token, Type, Visibility,
+};
+
+use super::{
+    common::tokens_to_string,
+    module::ModuleGraph,
+    pda::{get_pda, get_pda_binding, Pda, PdaSeed},
+};
+
+/// How an account meta is added inside the instruction builder function
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum AccountMetaKind {
+    /// Always added e.g `vec![AccountMeta::new(*mint, false)]`
+    Unconditional,
+    /// Added inside an `if` or `match` e.g `if let Some(fee) = maybe_fee { .. }`
+    Conditional,
+    /// Added inside a loop or an iterator e.g `for signer in signers { .. }`
+    Repeated,
+}
+
+/// `AccountMeta` construction found inside an instruction builder function, accounts from the
+/// docs and the processor are also represented with it
+pub struct BuilderAccountMeta {
+    /// Pubkey expression e.g `*mint_pubkey`, repeated accounts use the iterated collection if
+    /// it's known e.g `signer_pubkeys`
+    pub pubkey: String,
+    /// Whether the account is writable, `true` if it depends on an expression
+    pub is_writable: bool,
+    /// Whether the account is a signer, `true` if it depends on an expression
+    pub is_signer: bool,
+    /// Writable flag depends on an expression e.g `AccountMeta { is_writable, .. }`
+    pub is_dynamic_writable: bool,
+    /// Signer flag depends on an expression e.g `AccountMeta::new(*owner, signers.is_empty())`
+    pub is_dynamic_signer: bool,
+    pub kind: AccountMetaKind,
+    /// Full source expression e.g `AccountMeta::new(*mint_pubkey, false)`
+    pub source: String,
+    /// Description of the account from the doc comments of the instruction
+    pub description: Option<String>,
+    /// Expected owner of the account from the owner checks of the processor e.g `crate::ID`
+    pub owner: Option<String>,
+    /// Sysvar that the processor reads from the account e.g `Rent`
+    pub sysvar: Option<String>,
+    /// Seeds of the account if it's a program derived address
+    pub pda: Option<Pda>,
+}
+
+/// Find the builder function, starting from the given module, and get all of its account metas in
+/// order.
+///
+/// Returns `None` if the function doesn't exist.
+pub fn get_builder_account_metas(
+    modules: &ModuleGraph,
+    module: &str,
+    function_name: &str,
+) -> Option<Vec<BuilderAccountMeta>> {
+    let (module, block) = find_builder_fn(modules, module, function_name)?;
+    let mut visitor = AccountMetaVisitor {
+        modules,
+        module: module.to_owned(),
+        metas: vec![],
+        conditional_depth: 0,
+        repeated_depth: 0,
+        collection: None,
+        call_stack: vec![function_name.to_owned()],
+        substitutions: HashMap::new(),
+        pdas: HashMap::new(),
+    };
+    visitor.visit_block(block);
+
+    Some(visitor.metas)
+}
+
+/// Find a free function or an associated function with the given name, functions inside the given
+/// module are preferred
+pub fn find_fn<'a>(
+    modules: &'a ModuleGraph,
+    module: &str,
+    name: &str,
+) -> Option<(&'a str, &'a Signature, &'a Block)> {
+    modules
+        .module(module)
+        .into_iter()
+        .chain(modules.modules())
+        .find_map(|module| {
+            find_fn_in_items(&module.items, name)
+                .map(|(sig, block)| (module.path.as_str(), sig, block))
+        })
+}
+
+/// Find the instruction builder function with the given name, only public free functions that
+/// return an `Instruction` or a `Result<Instruction, _>` are builders, functions inside the module
+/// of the instruction enum are preferred
+fn find_builder_fn<'a>(
+    modules: &'a ModuleGraph,
+    module: &str,
+    name: &str,
+) -> Option<(&'a str, &'a Block)> {
+    modules
+        .module(module)
+        .into_iter()
+        .chain(modules.modules())
+        .find_map(|module| {
+            module.items.iter().find_map(|item| match item {
+                Item::Fn(item_fn)
+                    if item_fn.sig.ident == name
+                        && matches!(item_fn.vis, Visibility::Public(_))
+                        && returns_instruction(&item_fn.sig.output) =>
+                {
+                    Some((module.path.as_str(), &*item_fn.block))
+                }
+                _ => None,
+            })
+        })
+}
+
+/// Whether the return type is `Instruction` or `Result<Instruction, _>`
+fn returns_instruction(output: &ReturnType) -> bool {
+    fn is_instruction(ty: &Type) -> bool {
+        let segment = match ty {
+            Type::Path(type_path) => type_path.path.segments.last(),
+            _ => None,
+        };
+        let segment = match segment {
+            Some(segment) => segment,
+            None => return false,
+        };
+        if segment.ident == "Instruction" {
+            return true;
+        }
+        match &segment.arguments {
+            PathArguments::AngleBracketed(args) if segment.ident == "Result" => {
+                matches!(args.args.first(), Some(GenericArgument::Type(ty)) if is_instruction(ty))
+            }
+            _ => false,
+        }
+    }
+
+    match output {
+        ReturnType::Type(_, ty) => is_instruction(ty),
+        ReturnType::Default => false,
+    }
+}
+
+fn find_fn_in_items<'a>(items: &'a [Item], name: &str) -> Option<(&'a Signature, &'a Block)> {
+    items.iter().find_map(|item| match item {
+        Item::Fn(item_fn) if item_fn.sig.ident == name => Some((&item_fn.sig, &*item_fn.block)),
+        Item::Impl(item_impl) => item_impl
+            .items
+            .iter()
+            .find_map(|impl_item| match impl_item {
+                ImplItem::Method(method) if method.sig.ident == name => {
+                    Some((&method.sig, &method.block))
+                }
+                _ => None,
+            }),
+        _ => None,
+    })
+}
+
+struct AccountMetaVisitor<'a> {
+    modules: &'a ModuleGraph,
+    /// Module of the function that is currently being visited
+    module: String,
+    metas: Vec<BuilderAccountMeta>,
+    conditional_depth: usize,
+    repeated_depth: usize,
+    /// Collection that the innermost loop or iterator goes over e.g `signer_pubkeys`
+    collection: Option<String>,
+    /// Functions that are currently being visited, prevents infinite recursion
+    call_stack: Vec<String>,
+    /// Helper function parameter name -> argument expression
+    substitutions: HashMap<String, String>,
+    /// Variable name -> program derived address that is bound to it
+    pdas: HashMap<String, Pda>,
+}
+
+impl AccountMetaVisitor<'_> {
+    fn kind(&self) -> AccountMetaKind {
+        if self.repeated_depth > 0 {
+            AccountMetaKind::Repeated
+        } else if self.conditional_depth > 0 {
+            AccountMetaKind::Conditional
+        } else {
+            AccountMetaKind::Unconditional
+        }
+    }
+
+    /// Add the account meta with the given pubkey expression and `(flag, is_dynamic)` flags
+    fn push_meta(
+        &mut self,
+        pubkey: &Expr,
+        (is_writable, is_dynamic_writable): (bool, bool),
+        (is_signer, is_dynamic_signer): (bool, bool),
+        source: &impl ToTokens,
+    ) {
+        let pda = get_pda(pubkey, self.modules, &self.module).map(|pda| self.substitute_pda(pda));
+        let mut pubkey = tokens_to_string(pubkey.to_token_stream().to_string());
+        // Use the argument expression if the pubkey is a helper function parameter
+        let pubkey_name = pubkey.trim_start_matches(['*', '&']).trim();
+        if let Some(arg) = self.substitutions.get(pubkey_name) {
+            pubkey = arg.to_owned();
+        }
+        let pda = pda.or_else(|| {
+            let pubkey_name = pubkey.trim_start_matches(['*', '&']).trim();
+            self.pdas.get(pubkey_name).cloned()
+        });
+        // Name repeated accounts after what they are iterated from
+        if let (AccountMetaKind::Repeated, Some(collection)) = (self.kind(), &self.collection) {
+            pubkey = collection.to_owned();
+        }
+
+        self.metas.push(BuilderAccountMeta {
+            pubkey,
+            is_writable,
+            is_signer,
+            is_dynamic_writable,
+            is_dynamic_signer,
+            kind: self.kind(),
+            source: tokens_to_string(source.to_token_stream().to_string()),
+            description: None,
+            owner: None,
+            sysvar: None,
+            pda,
+        });
+    }
+
+    /// Replace the helper function parameters of the seeds with the arguments
+    fn substitute_pda(&self, pda: Pda) -> Pda {
+        pda.map_vars(|var| {
+            let arg = self.substitutions.get(var)?;
+            Some(PdaSeed::Var(
+                arg.trim_start_matches(['*', '&']).trim().to_owned(),
+            ))
+        })
+    }
+
+    /// Evaluate a signer or writable flag expression, returns the flag and whether it's dynamic.
+    ///
+    /// Only `bool` literals are fixed, other expressions e.g `signer_pubkeys.is_empty()` depend
+    /// on the arguments of the builder function and they are `true` by default.
+    fn eval_flag(&self, flag: &Expr) -> (bool, bool) {
+        let flag = tokens_to_string(flag.to_token_stream().to_string());
+        let flag = self.substitutions.get(&flag).unwrap_or(&flag);
+        match flag.as_str() {
+            "true" => (true, false),
+            "false" => (false, false),
+            _ => (true, true),
+        }
+    }
+
+    /// Visit the body of a helper function that is defined inside the crate
+    fn visit_helper(&mut self, call: &ExprCall) {
+        let (path, name) = match &*call.func {
+            Expr::Path(path) => match path.path.segments.last() {
+                Some(segment) => (
+                    tokens_to_string(path.path.to_token_stream().to_string()),
+                    segment.ident.to_string(),
+                ),
+                None => return,
+            },
+            _ => return,
+        };
+        if self.call_stack.contains(&name) {
+            return;
+        }
+
+        // Resolve the function by its path first, methods are not indexed by path
+        let modules = self.modules;
+        let (module, sig, block) = match modules.resolve(&path, &self.module) {
+            Some(symbol) => match &symbol.item {
+                Item::Fn(item_fn) => (symbol.module.as_str(), &item_fn.sig, &*item_fn.block),
+                _ => return,
+            },
+            None => match find_fn(modules, &self.module, &name) {
+                Some(f) => f,
+                None => return,
+            },
+        };
+
+        // Map parameters to the given arguments
+        let mut substitutions = HashMap::new();
+        let params = sig.inputs.iter().filter_map(|input| match input {
+            FnArg::Typed(pat_type) => match &*pat_type.pat {
+                Pat::Ident(pat_ident) => Some(pat_ident.ident.to_string()),
+                _ => None,
+            },
+            FnArg::Receiver(_) => None,
+        });
+        for (param, arg) in params.zip(call.args.iter()) {
+            let arg = tokens_to_string(arg.to_token_stream().to_string());
+            let arg = self.substitutions.get(&arg).cloned().unwrap_or(arg);
+            substitutions.insert(param, arg);
+        }
+
+        let substitutions = std::mem::replace(&mut self.substitutions, substitutions);
+        let module = std::mem::replace(&mut self.module, module.to_owned());
+        self.call_stack.push(name);
+        self.visit_block(block);
+        self.call_stack.pop();
+        self.module = module;
+        self.substitutions = substitutions;
+    }
+
+    /// Visit the body of a loop or an iterator closure with the collection it goes over
+    fn visit_repeated(&mut self, collection: Option<&Expr>, visit: impl FnOnce(&mut Self)) {
+        let collection = collection
+            .and_then(get_collection_name)
+            .map(|name| self.substitutions.get(&name).cloned().unwrap_or(name));
+        let collection = std::mem::replace(&mut self.collection, collection);
+        self.repeated_depth += 1;
+        visit(self);
+        self.repeated_depth -= 1;
+        self.collection = collection;
+    }
+}
+
+impl<'ast> Visit<'ast> for AccountMetaVisitor<'_> {
+    fn visit_expr_call(&mut self, call: &'ast ExprCall) {
+        let func = tokens_to_string(call.func.to_token_stream().to_string());
+        let is_writable = if func.ends_with("AccountMeta::new") {
+            true
+        } else if func.ends_with("AccountMeta::new_readonly") {
+            false
+        } else {
+            visit::visit_expr_call(self, call);
+            self.visit_helper(call);
+            return;
+        };
+
+        let mut args = call.args.iter();
+        if let (Some(pubkey), Some(is_signer)) = (args.next(), args.next()) {
+            let is_signer = self.eval_flag(is_signer);
+            self.push_meta(pubkey, (is_writable, false), is_signer, call);
+        }
+    }
+
+    fn visit_expr_struct(&mut self, expr_struct: &'ast ExprStruct) {
+        let is_account_meta = expr_struct
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident == "AccountMeta")
+            .unwrap_or_default();
+        if !is_account_meta {
+            return visit::visit_expr_struct(self, expr_struct);
+        }
+
+        // e.g `AccountMeta { pubkey, is_signer: false, is_writable }`
+        let field = |name: &str| {
+            expr_struct
+                .fields
+                .iter()
+                .find_map(|field| match &field.member {
+                    Member::Named(ident) if ident == name => Some(&field.expr),
+                    _ => None,
+                })
+        };
+        if let (Some(pubkey), Some(is_signer), Some(is_writable)) =
+            (field("pubkey"), field("is_signer"), field("is_writable"))
+        {
+            let is_writable = self.eval_flag(is_writable);
+            let is_signer = self.eval_flag(is_signer);
+            self.push_meta(pubkey, is_writable, is_signer, expr_struct);
+        }
+    }
+
+    fn visit_expr_if(&mut self, expr_if: &'ast ExprIf) {
+        self.visit_expr(&expr_if.cond);
+        self.conditional_depth += 1;
+        self.visit_block(&expr_if.then_branch);
+        if let Some((_, else_branch)) = &expr_if.else_branch {
+            self.visit_expr(else_branch);
+        }
+        self.conditional_depth -= 1;
+    }
+
+    fn visit_expr_match(&mut self, expr_match: &'ast ExprMatch) {
+        self.visit_expr(&expr_match.expr);
+        self.conditional_depth += 1;
+        for arm in &expr_match.arms {
+            self.visit_arm(arm);
+        }
+        self.conditional_depth -= 1;
+    }
+
+    fn visit_expr_for_loop(&mut self, expr_for_loop: &'ast ExprForLoop) {
+        self.visit_expr(&expr_for_loop.expr);
+        self.visit_repeated(Some(&expr_for_loop.expr), |visitor| {
+            visitor.visit_block(&expr_for_loop.body)
+        });
+    }
+
+    fn visit_expr_while(&mut self, expr_while: &'ast ExprWhile) {
+        self.visit_expr(&expr_while.cond);
+        self.visit_repeated(None, |visitor| visitor.visit_block(&expr_while.body));
+    }
+
+    fn visit_expr_loop(&mut self, expr_loop: &'ast ExprLoop) {
+        self.visit_repeated(None, |visitor| visitor.visit_block(&expr_loop.body));
+    }
+
+    fn visit_expr_method_call(&mut self, method_call: &'ast ExprMethodCall) {
+        self.visit_expr(&method_call.receiver);
+
+        // Closures that are called on iterators are repeated, others(e.g `Option::map`) are
+        // conditional
+        let is_iterator = is_iterator(&method_call.receiver);
+        for arg in &method_call.args {
+            match arg {
+                Expr::Closure(closure) if is_iterator => self
+                    .visit_repeated(Some(&method_call.receiver), |visitor| {
+                        visitor.visit_expr_closure(closure)
+                    }),
+                Expr::Closure(closure) => {
+                    self.conditional_depth += 1;
+                    self.visit_expr_closure(closure);
+                    self.conditional_depth -= 1;
+                }
+                _ => self.visit_expr(arg),
+            }
+        }
+    }
+
+    fn visit_local(&mut self, local: &'ast Local) {
+        visit::visit_local(self, local);
+
+        // Addresses that are derived inside the function e.g
+        // `let (vault, _) = Pubkey::find_program_address(&[b"vault"], program_id);`
+        if let (Some(name), Some((_, init))) = (get_pda_binding(&local.pat), &local.init) {
+            if let Some(pda) = get_pda(init, self.modules, &self.module) {
+                let pda = self.substitute_pda(pda);
+                self.pdas.insert(name, pda);
+            }
+        }
+    }
+
+    fn visit_macro(&mut self, mac: &'ast Macro) {
+        // Macro bodies are not parsed by syn e.g `vec![AccountMeta::new(..), ..]`
+        if let Ok(exprs) = mac.parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated) {
+            for expr in &exprs {
+                self.visit_expr(expr);
+            }
+        } else if let Ok((expr, len)) = mac.parse_body_with(parse_repeat) {
+            // e.g `vec![AccountMeta::new_readonly(*signer, true); signers.len()]` is repeated for
+            // `signers`
+            self.visit_expr(&len);
+            self.visit_repeated(Some(&len), |visitor| visitor.visit_expr(&expr));
+        }
+    }
+}
+
+/// Parse the body of an array repeat macro e.g `vec![expr; len]`
+fn parse_repeat(input: ParseStream) -> syn::Result<(Expr, Expr)> {
+    let expr = input.parse()?;
+    input.parse::<Token![;]>()?;
+    let len = input.parse()?;
+    Ok((expr, len))
+}
+
+/// Whether the expression is an iterator e.g `signer_pubkeys.iter()`, `accounts.into_iter()` or
+/// `0..n`
+fn is_iterator(expr: &Expr) -> bool {
+    match expr {
+        Expr::MethodCall(method_call) => {
+            matches!(
+                method_call.method.to_string().as_str(),
+                "iter" | "into_iter" | "iter_mut"
+            ) || is_iterator(&method_call.receiver)
+        }
+        Expr::Range(_) => true,
+        Expr::Paren(paren) => is_iterator(&paren.expr),
+        _ => false,
+    }
+}
+
+/// Get the name of the collection that is iterated e.g `signer_pubkeys.iter()` -> `signer_pubkeys`,
+/// ranges don't have a name
+pub fn get_collection_name(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::MethodCall(method_call) => get_collection_name(&method_call.receiver),
+        Expr::Reference(reference) => get_collection_name(&reference.expr),
+        Expr::Unary(unary) => get_collection_name(&unary.expr),
+        Expr::Paren(paren) => get_collection_name(&paren.expr),
+        Expr::Path(path) => path.path.segments.last().map(|s| s.ident.to_string()),
+        Expr::Field(field) => Some(tokens_to_string(field.member.to_token_stream().to_string())),
+        _ => None,
+    }
+}
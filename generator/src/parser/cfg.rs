@@ -0,0 +1,545 @@
+use std::{collections::HashSet, path::Path};
+
+use proc_macro2::LineColumn;
+use syn::{
+    parse_quote,
+    punctuated::Punctuated,
+    spanned::Spanned,
+    visit_mut::{self, VisitMut},
+    Attribute, Block, ExprMatch, ExprStruct, Fields, File, ImplItem, Item, ItemEnum, ItemImpl,
+    ItemMod, ItemTrait, Lit, Meta, NestedMeta, Stmt, TraitItem,
+};
+use toml::Value;
+
+use crate::{constants::known_types::TARGET_CFGS, utils::dependency::read_manifest};
+
+/// Enabled features of the crate that `#[cfg(...)]` predicates are evaluated with.
+///
+/// Target options are evaluated for the Solana target e.g `target_os = "solana"`, `test` and
+/// unknown options are never set.
+#[derive(Clone, Debug, Default)]
+pub struct CfgContext {
+    features: HashSet<String>,
+}
+
+impl CfgContext {
+    /// Get the enabled features from the `[features]` table of the crate's `Cargo.toml`.
+    ///
+    /// `default` is enabled unless `default_features` is `false` and the features that the
+    /// enabled features enable are also enabled. Features of the dependencies(`dep/feature`) are
+    /// ignored.
+    pub fn new(cargo_toml_path: &Path, features: &[String], default_features: bool) -> Self {
+        let feature_table = read_manifest(cargo_toml_path)
+            .and_then(|manifest| manifest.get("features").cloned())
+            .and_then(|features| features.as_table().cloned())
+            .unwrap_or_default();
+
+        let mut queue = features
+            .iter()
+            .flat_map(|feature| feature.split(|c: char| c == ',' || c.is_whitespace()))
+            .filter(|feature| !feature.is_empty())
+            .map(|feature| feature.to_owned())
+            .collect::<Vec<_>>();
+        if default_features {
+            queue.push("default".into());
+        }
+
+        let mut enabled = HashSet::new();
+        while let Some(feature) = queue.pop() {
+            if !enabled.insert(feature.clone()) {
+                continue;
+            }
+            if let Some(implied) = feature_table.get(&feature).and_then(Value::as_array) {
+                queue.extend(
+                    implied
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .filter(|implied| !implied.contains('/') && !implied.starts_with("dep:"))
+                        .map(|implied| implied.to_owned()),
+                );
+            }
+        }
+
+        Self { features: enabled }
+    }
+
+    /// Whether the item with the given attributes is compiled, items without `#[cfg(...)]` are
+    /// always compiled. `cfg`s inside enabled `#[cfg_attr(...)]`s are also evaluated.
+    pub fn is_enabled(&self, attrs: &[Attribute]) -> bool {
+        attrs
+            .iter()
+            .filter_map(|attr| attr.parse_meta().ok())
+            .flat_map(|meta| self.expand_cfg_attr(meta))
+            .filter(|meta| meta.path().is_ident("cfg"))
+            .all(|meta| match meta {
+                Meta::List(list) if list.nested.len() == 1 => self.eval(&list.nested[0]),
+                _ => true,
+            })
+    }
+
+    /// Get the attributes of `#[cfg_attr(predicate, attrs..)]` if the predicate is true, other
+    /// attributes are returned as is
+    fn expand_cfg_attr(&self, meta: Meta) -> Vec<Meta> {
+        let list = match meta {
+            Meta::List(list) if list.path.is_ident("cfg_attr") => list,
+            meta => return vec![meta],
+        };
+
+        let mut nested = list.nested.into_iter();
+        match nested.next() {
+            Some(predicate) if self.eval(&predicate) => nested
+                .filter_map(|nested| match nested {
+                    NestedMeta::Meta(meta) => Some(meta),
+                    NestedMeta::Lit(_) => None,
+                })
+                .flat_map(|meta| self.expand_cfg_attr(meta))
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Remove the items, variants, fields and match arms that are not compiled from both the
+    /// parsed file and its content
+    pub fn strip(&self, file: &mut File, content: &mut String) {
+        let mut stripper = CfgStripper {
+            cfg: self,
+            removed: vec![],
+        };
+        stripper.visit_file_mut(file);
+
+        let line_starts = [0]
+            .into_iter()
+            .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+            .collect::<Vec<_>>();
+        let to_index = |line_column: LineColumn| {
+            let line_start = *line_starts.get(line_column.line.checked_sub(1)?)?;
+            let line = content[line_start..].lines().next().unwrap_or_default();
+            Some(
+                line_start
+                    + line
+                        .char_indices()
+                        .nth(line_column.column)
+                        .map(|(i, _)| i)
+                        .unwrap_or(line.len()),
+            )
+        };
+        let mut ranges = stripper
+            .removed
+            .into_iter()
+            .filter_map(|(start, end)| Some((to_index(start)?, to_index(end)?)))
+            .collect::<Vec<_>>();
+        ranges.sort();
+
+        // Cut from the end so that the indices stay valid, nested ranges are already removed
+        let mut last_start = content.len();
+        for (start, end) in ranges.into_iter().rev() {
+            if end > last_start || start >= end {
+                continue;
+            }
+            content.replace_range(start..end, "");
+            last_start = start;
+        }
+    }
+
+    /// Evaluate the `cfg` predicate e.g `all(feature = "x", not(test))`
+    fn eval(&self, predicate: &NestedMeta) -> bool {
+        match predicate {
+            NestedMeta::Meta(Meta::List(list)) => {
+                let mut nested = list.nested.iter();
+                if list.path.is_ident("not") && list.nested.len() == 1 {
+                    !self.eval(&list.nested[0])
+                } else if list.path.is_ident("all") {
+                    nested.all(|predicate| self.eval(predicate))
+                } else if list.path.is_ident("any") {
+                    nested.any(|predicate| self.eval(predicate))
+                } else {
+                    false
+                }
+            }
+            NestedMeta::Meta(Meta::NameValue(name_value)) => {
+                let value = match &name_value.lit {
+                    Lit::Str(value) => value.value(),
+                    _ => return false,
+                };
+                if name_value.path.is_ident("feature") {
+                    return self.features.contains(&value);
+                }
+                TARGET_CFGS.iter().any(|(name, target_value)| {
+                    name_value.path.is_ident(name) && *target_value == value
+                })
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Removes the nodes that are not compiled and keeps their positions in the source
+struct CfgStripper<'a> {
+    cfg: &'a CfgContext,
+    removed: Vec<(LineColumn, LineColumn)>,
+}
+
+impl CfgStripper<'_> {
+    /// Keep the enabled nodes and save the positions of the removed ones
+    fn retain<T: Spanned>(&mut self, nodes: Vec<T>, attrs: impl Fn(&T) -> &[Attribute]) -> Vec<T> {
+        nodes
+            .into_iter()
+            .filter(|node| {
+                let is_enabled = self.cfg.is_enabled(attrs(node));
+                if !is_enabled {
+                    let span = node.span();
+                    self.removed.push((span.start(), span.end()));
+                }
+                is_enabled
+            })
+            .collect()
+    }
+
+    /// Same as `retain` but the punctuation after the removed nodes is also removed from the
+    /// content e.g `B,` instead of `B` so that it can still be parsed
+    fn retain_punctuated<T: Spanned, P: Spanned + Default>(
+        &mut self,
+        nodes: &mut Punctuated<T, P>,
+        attrs: impl Fn(&T) -> &[Attribute],
+    ) {
+        *nodes = std::mem::take(nodes)
+            .into_pairs()
+            .filter(|pair| {
+                let is_enabled = self.cfg.is_enabled(attrs(pair.value()));
+                if !is_enabled {
+                    let start = pair.value().span().start();
+                    let end = match pair.punct() {
+                        Some(punct) => punct.span().end(),
+                        None => pair.value().span().end(),
+                    };
+                    self.removed.push((start, end));
+                }
+                is_enabled
+            })
+            .collect();
+    }
+}
+
+impl VisitMut for CfgStripper<'_> {
+    /// Replace the enabled `#[cfg_attr(...)]`s with their attributes e.g
+    /// `#[cfg_attr(feature = "borsh", derive(BorshSerialize))]` -> `#[derive(BorshSerialize)]`.
+    /// Attributes can't be added, the derives of a `cfg_attr` are merged and only the first one of
+    /// the other attributes is kept. Disabled ones are left as they are since they don't have any
+    /// effect.
+    fn visit_attribute_mut(&mut self, attr: &mut Attribute) {
+        if !attr.path.is_ident("cfg_attr") {
+            return;
+        }
+        let metas = match attr.parse_meta() {
+            Ok(meta) => self.cfg.expand_cfg_attr(meta),
+            Err(_) => return,
+        };
+
+        let derives = metas
+            .iter()
+            .map(|meta| match meta {
+                Meta::List(list) if list.path.is_ident("derive") => Some(&list.nested),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>();
+        let expanded: Attribute = match (derives, metas.first()) {
+            (_, None) => return,
+            (Some(derives), _) => {
+                let derives = derives.into_iter().flatten();
+                parse_quote!(#[derive(#(#derives),*)])
+            }
+            (None, Some(meta)) => parse_quote!(#[#meta]),
+        };
+        *attr = Attribute {
+            style: attr.style,
+            ..expanded
+        };
+    }
+
+    fn visit_file_mut(&mut self, file: &mut File) {
+        file.items = self.retain(std::mem::take(&mut file.items), get_item_attrs);
+        visit_mut::visit_file_mut(self, file);
+    }
+
+    fn visit_item_mod_mut(&mut self, item_mod: &mut ItemMod) {
+        if let Some((_, items)) = &mut item_mod.content {
+            *items = self.retain(std::mem::take(items), get_item_attrs);
+        }
+        visit_mut::visit_item_mod_mut(self, item_mod);
+    }
+
+    fn visit_item_impl_mut(&mut self, item_impl: &mut ItemImpl) {
+        item_impl.items = self.retain(std::mem::take(&mut item_impl.items), |item| match item {
+            ImplItem::Const(item) => &item.attrs,
+            ImplItem::Method(item) => &item.attrs,
+            ImplItem::Type(item) => &item.attrs,
+            ImplItem::Macro(item) => &item.attrs,
+            _ => &[],
+        });
+        visit_mut::visit_item_impl_mut(self, item_impl);
+    }
+
+    fn visit_item_trait_mut(&mut self, item_trait: &mut ItemTrait) {
+        item_trait.items = self.retain(std::mem::take(&mut item_trait.items), |item| match item {
+            TraitItem::Const(item) => &item.attrs,
+            TraitItem::Method(item) => &item.attrs,
+            TraitItem::Type(item) => &item.attrs,
+            TraitItem::Macro(item) => &item.attrs,
+            _ => &[],
+        });
+        visit_mut::visit_item_trait_mut(self, item_trait);
+    }
+
+    fn visit_item_enum_mut(&mut self, item_enum: &mut ItemEnum) {
+        self.retain_punctuated(&mut item_enum.variants, |variant| &variant.attrs);
+        visit_mut::visit_item_enum_mut(self, item_enum);
+    }
+
+    fn visit_fields_mut(&mut self, fields: &mut Fields) {
+        match fields {
+            Fields::Named(fields) => self.retain_punctuated(&mut fields.named, |f| &f.attrs),
+            Fields::Unnamed(fields) => self.retain_punctuated(&mut fields.unnamed, |f| &f.attrs),
+            Fields::Unit => {}
+        }
+        visit_mut::visit_fields_mut(self, fields);
+    }
+
+    fn visit_block_mut(&mut self, block: &mut Block) {
+        block.stmts = self.retain(std::mem::take(&mut block.stmts), |stmt| match stmt {
+            Stmt::Local(local) => &local.attrs,
+            Stmt::Item(item) => get_item_attrs(item),
+            _ => &[],
+        });
+        visit_mut::visit_block_mut(self, block);
+    }
+
+    fn visit_expr_match_mut(&mut self, expr_match: &mut ExprMatch) {
+        expr_match.arms = self.retain(std::mem::take(&mut expr_match.arms), |arm| &arm.attrs);
+        visit_mut::visit_expr_match_mut(self, expr_match);
+    }
+
+    fn visit_expr_struct_mut(&mut self, expr_struct: &mut ExprStruct) {
+        self.retain_punctuated(&mut expr_struct.fields, |field| &field.attrs);
+        visit_mut::visit_expr_struct_mut(self, expr_struct);
+    }
+}
+
+fn get_item_attrs(item: &Item) -> &[Attribute] {
+    match item {
+        Item::Const(item) => &item.attrs,
+        Item::Enum(item) => &item.attrs,
+        Item::ExternCrate(item) => &item.attrs,
+        Item::Fn(item) => &item.attrs,
+        Item::ForeignMod(item) => &item.attrs,
+        Item::Impl(item) => &item.attrs,
+        Item::Macro(item) => &item.attrs,
+        Item::Macro2(item) => &item.attrs,
+        Item::Mod(item) => &item.attrs,
+        Item::Static(item) => &item.attrs,
+        Item::Struct(item) => &item.attrs,
+        Item::Trait(item) => &item.attrs,
+        Item::TraitAlias(item) => &item.attrs,
+        Item::Type(item) => &item.attrs,
+        Item::Union(item) => &item.attrs,
+        Item::Use(item) => &item.attrs,
+        _ => &[],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use quote::ToTokens;
+
+    use super::*;
+
+    /// Create the context from a `Cargo.toml` with the given `[features]` table
+    fn create_context(
+        name: &str,
+        features_table: &str,
+        features: &[&str],
+        default: bool,
+    ) -> CfgContext {
+        let crate_dir = env::temp_dir().join(format!("nta-cfg-{name}-{}", process::id()));
+        fs::create_dir_all(&crate_dir).unwrap();
+        let cargo_toml_path = crate_dir.join("Cargo.toml");
+        fs::write(
+            &cargo_toml_path,
+            format!("[package]\nname = \"{name}\"\n\n[features]\n{features_table}"),
+        )
+        .unwrap();
+
+        let features = features.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+        let cfg = CfgContext::new(&cargo_toml_path, &features, default);
+        fs::remove_dir_all(crate_dir).unwrap();
+        cfg
+    }
+
+    fn with_features(features: &[&str]) -> CfgContext {
+        CfgContext {
+            features: features.iter().map(|f| f.to_string()).collect(),
+        }
+    }
+
+    fn is_enabled(cfg: &CfgContext, attrs: &str) -> bool {
+        let item: Item = syn::parse_str(&format!("{attrs} struct S;")).unwrap();
+        cfg.is_enabled(get_item_attrs(&item))
+    }
+
+    fn strip(cfg: &CfgContext, content: &str) -> (File, String) {
+        let mut content = content.to_owned();
+        let mut file = syn::parse_file(&content).unwrap();
+        cfg.strip(&mut file, &mut content);
+        (file, content)
+    }
+
+    #[test]
+    fn new_enables_implied_features() {
+        let table = r#"
+default = ["a"]
+a = ["b", "dep:serde", "spl-token/no-entrypoint"]
+b = ["c"]
+c = []
+d = []
+e = ["d"]
+"#;
+        let mut features = create_context("implied", table, &[], true)
+            .features
+            .into_iter()
+            .collect::<Vec<_>>();
+        features.sort();
+        assert_eq!(features, ["a", "b", "c", "default"]);
+
+        let mut features = create_context("no-default", table, &["e, c"], false)
+            .features
+            .into_iter()
+            .collect::<Vec<_>>();
+        features.sort();
+        assert_eq!(features, ["c", "d", "e"]);
+    }
+
+    #[test]
+    fn new_without_manifest() {
+        let cfg = CfgContext::new(Path::new("/nonexistent/Cargo.toml"), &["x".into()], true);
+        assert!(cfg.features.contains("x"));
+        assert!(cfg.features.contains("default"));
+    }
+
+    #[test]
+    fn eval_predicates() {
+        let cfg = with_features(&["a", "b"]);
+        let cases: &[(&str, bool)] = &[
+            ("", true),
+            ("#[derive(Debug)]", true),
+            (r#"#[cfg(feature = "a")]"#, true),
+            (r#"#[cfg(feature = "x")]"#, false),
+            (r#"#[cfg(not(feature = "x"))]"#, true),
+            (r#"#[cfg(all(feature = "a", feature = "b"))]"#, true),
+            (r#"#[cfg(all(feature = "a", feature = "x"))]"#, false),
+            (r#"#[cfg(any(feature = "x", feature = "b"))]"#, true),
+            (r#"#[cfg(any())]"#, false),
+            (r#"#[cfg(all())]"#, true),
+            (r#"#[cfg(feature = "a")] #[cfg(feature = "x")]"#, false),
+            (r#"#[cfg(target_os = "solana")]"#, true),
+            (r#"#[cfg(not(target_os = "solana"))]"#, false),
+            (r#"#[cfg(target_arch = "bpf")]"#, true),
+            (r#"#[cfg(target_arch = "x86_64")]"#, false),
+            (r#"#[cfg(target_pointer_width = "64")]"#, true),
+            ("#[cfg(test)]", false),
+            ("#[cfg(unknown)]", false),
+            (r#"#[cfg(all(not(test), feature = "a"))]"#, true),
+            (r#"#[cfg_attr(feature = "a", cfg(feature = "x"))]"#, false),
+            (r#"#[cfg_attr(feature = "x", cfg(feature = "x"))]"#, true),
+            (
+                r#"#[cfg_attr(feature = "a", cfg_attr(feature = "b", cfg(test)))]"#,
+                false,
+            ),
+        ];
+        for (attrs, expected) in cases {
+            assert_eq!(is_enabled(&cfg, attrs), *expected, "{attrs}");
+        }
+    }
+
+    #[test]
+    fn strip_nested() {
+        let cfg = with_features(&["a"]);
+        let content = r#"pub mod m {
+    #[cfg(feature = "x")]
+    pub mod inner {
+        #[cfg(feature = "a")]
+        pub struct A;
+    }
+    pub enum E {
+        A,
+        #[cfg(feature = "x")]
+        B { #[cfg(feature = "a")] c: u8 },
+    }
+}
+pub struct S {
+    a: u8,
+    #[cfg(not(feature = "a"))]
+    b: u8,
+}
+fn f(e: E) {
+    match e {
+        #[cfg(test)]
+        E::B { .. } => {}
+        _ => {}
+    }
+}
+"#;
+        let (file, content) = strip(&cfg, content);
+        for removed in ["inner", "pub struct A", "B {", "b: u8", "E::B"] {
+            assert!(!content.contains(removed), "{removed}:\n{content}");
+        }
+        for kept in ["pub enum E", "a: u8", "_ => {}"] {
+            assert!(content.contains(kept), "{kept}:\n{content}");
+        }
+
+        // The content is parsed to the same file
+        assert_eq!(
+            syn::parse_file(&content)
+                .unwrap()
+                .to_token_stream()
+                .to_string(),
+            file.to_token_stream().to_string()
+        );
+    }
+
+    #[test]
+    fn strip_multi_byte() {
+        let cfg = with_features(&[]);
+        let content =
+            "const É: &str = \"ünïcödé 🦀\"; #[cfg(test)] const T: u8 = 0; const Ö: u8 = 1;\n\
+            /* 🦀🦀 */ #[cfg(feature = \"x\")] struct X; struct Y;\n";
+        let (_, content) = strip(&cfg, content);
+        assert_eq!(
+            content,
+            "const É: &str = \"ünïcödé 🦀\";  const Ö: u8 = 1;\n/* 🦀🦀 */  struct Y;\n"
+        );
+    }
+
+    #[test]
+    fn strip_expands_cfg_attr() {
+        let cfg = with_features(&["borsh"]);
+        let content = r#"#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize), derive(Debug))]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "borsh", repr(u8))]
+pub enum E { A }
+"#;
+        let (file, _) = strip(&cfg, content);
+        let attrs = get_item_attrs(&file.items[0])
+            .iter()
+            .map(|attr| attr.to_token_stream().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            attrs,
+            [
+                "# [derive (BorshSerialize , BorshDeserialize , Debug)]",
+                "# [cfg_attr (feature = \"serde\" , derive (Serialize))]",
+                "# [repr (u8)]",
+            ]
+        );
+    }
+}
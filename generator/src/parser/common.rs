@@ -0,0 +1,177 @@
+use quote::ToTokens;
+use syn::{
+    visit_mut::{self, VisitMut},
+    Attribute, ItemEnum, ItemImpl, Lit, LitInt, Meta, NestedMeta, Path, Type, TypePath,
+};
+
+use super::{eval::eval_const_expr, module::ModuleGraph};
+use crate::utils::common::warn;
+
+/// Get the doc comments(`///` or `#[doc = ""]`) from the given attributes
+pub fn get_docs(attrs: &[Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("doc"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::NameValue(name_value)) => match name_value.lit {
+                Lit::Str(doc) => Some(doc.value()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// Get all attributes except doc comments as strings e.g `deprecated(since = "1.0.0")`
+pub fn get_attrs(attrs: &[Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter(|attr| !attr.path.is_ident("doc"))
+        .map(|attr| tokens_to_string(format!("{}{}", attr.path.to_token_stream(), attr.tokens)))
+        .collect()
+}
+
+/// Get the names of the derive macros e.g `["BorshSerialize", "BorshDeserialize"]`
+pub fn get_derives(attrs: &[Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("derive"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::List(list)) => Some(list.nested),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|nested| match nested {
+            NestedMeta::Meta(Meta::Path(path)) => get_last_segment(&path),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Get the name of the implemented trait without its module path e.g `Pack` for
+/// `impl program_pack::Pack for Mint`
+pub fn get_trait_name(item_impl: &ItemImpl) -> Option<String> {
+    item_impl
+        .trait_
+        .as_ref()
+        .and_then(|(_, path, _)| get_last_segment(path))
+}
+
+/// Get the last segment of the path as a string e.g `Pack` for `program_pack::Pack`
+pub fn get_last_segment(path: &Path) -> Option<String> {
+    path.segments
+        .last()
+        .map(|segment| segment.ident.to_string())
+}
+
+/// Get the discriminants of the enum variants, variants without an explicit discriminant are one
+/// more than the previous variant e.g `A = 5, B` -> `[5, 6]`.
+///
+/// Borsh ignores the explicit discriminants and uses the variant index unless the enum has
+/// `#[borsh(use_discriminant = true)]`.
+pub fn get_discriminants(item_enum: &ItemEnum, modules: &ModuleGraph, module: &str) -> Vec<u64> {
+    let derives = get_derives(&item_enum.attrs);
+    let is_borsh = derives
+        .iter()
+        .any(|derive| derive == "BorshSerialize" || derive == "BorshDeserialize");
+    let use_discriminant = !is_borsh
+        || get_attrs(&item_enum.attrs)
+            .iter()
+            .any(|attr| attr.replace(' ', "") == "borsh(use_discriminant=true)");
+
+    let mut next = 0;
+    item_enum
+        .variants
+        .iter()
+        .map(|variant| {
+            let discriminant = match &variant.discriminant {
+                Some((_, expr)) if use_discriminant => {
+                    let discriminant = eval_const_expr(expr, module, modules)
+                        .and_then(|discriminant| discriminant.try_into().ok());
+                    match discriminant {
+                        Some(discriminant) => discriminant,
+                        None => {
+                            warn(format!(
+                                "Could not evaluate discriminant '{}' of '{}::{}'.",
+                                tokens_to_string(expr.to_token_stream().to_string()),
+                                item_enum.ident,
+                                variant.ident
+                            ));
+                            next
+                        }
+                    }
+                }
+                _ => next,
+            };
+            next = discriminant + 1;
+            discriminant
+        })
+        .collect()
+}
+
+/// Parse the integer literal e.g `5`, `0x10` or `1_000u64`
+pub fn parse_int(value: &str) -> Option<u64> {
+    syn::parse_str::<LitInt>(value).ok()?.base10_parse().ok()
+}
+
+/// Convert the type to a string the way it would be written in source code
+pub fn type_to_string(ty: &Type) -> String {
+    tokens_to_string(ty.to_token_stream().to_string())
+}
+
+/// Remove the module paths from the type e.g `Option<crate::state::Config>` -> `Option<Config>`.
+///
+/// Returns the type string and the full paths of all types inside the type.
+pub fn strip_type_paths(ty: &Type) -> (String, Vec<String>) {
+    struct PathStripper(Vec<String>);
+
+    impl VisitMut for PathStripper {
+        fn visit_type_path_mut(&mut self, type_path: &mut TypePath) {
+            visit_mut::visit_type_path_mut(self, type_path);
+            if type_path.qself.is_some() {
+                return;
+            }
+
+            let segments = &mut type_path.path.segments;
+            self.0.push(
+                segments
+                    .iter()
+                    .map(|segment| segment.ident.to_string())
+                    .collect::<Vec<_>>()
+                    .join("::"),
+            );
+            if let Some(last) = segments.pop().map(|last| last.into_value()) {
+                segments.clear();
+                segments.push(last);
+                type_path.path.leading_colon = None;
+            }
+        }
+    }
+
+    let mut ty = ty.clone();
+    let mut stripper = PathStripper(vec![]);
+    stripper.visit_type_mut(&mut ty);
+
+    (type_to_string(&ty), stripper.0)
+}
+
+/// Remove the spaces `TokenStream` puts between every token
+pub fn tokens_to_string(tokens: impl AsRef<str>) -> String {
+    tokens
+        .as_ref()
+        .replace(" :: ", "::")
+        .replace(":: ", "::")
+        .replace(" < ", "<")
+        .replace("< ", "<")
+        .replace(" >", ">")
+        .replace(" ;", ";")
+        .replace(" ,", ",")
+        .replace("& ", "&")
+        .replace("[ ", "[")
+        .replace(" ]", "]")
+        .replace(" (", "(")
+        .replace("( ", "(")
+        .replace(" )", ")")
+        .replace(",)", ")")
+        .replace(" . ", ".")
+}
@@ -0,0 +1,133 @@
+use super::builder::{AccountMetaKind, BuilderAccountMeta};
+
+/// Words that are skipped while creating account names from the descriptions
+const SKIPPED_WORDS: [&str; 9] = [
+    "the", "a", "an", "new", "optional", "account", "accounts", "sysvar", "info",
+];
+
+/// Words that end the name part of the descriptions e.g `The mint to initialize.`
+const NAME_END_WORDS: [&str; 14] = [
+    "to", "this", "that", "for", "of", "which", "will", "must", "is", "with", "used", "being",
+    "if", "or",
+];
+
+/// Get the accounts from the SPL style account list inside the doc comments of an instruction
+/// e.g
+///
+/// ```text
+/// Accounts expected by this instruction:
+///
+///   0. `[writable]` The mint to initialize.
+///   1. `[]` (optional) Rent sysvar
+///   2. ..2+M `[signer]` M signer accounts.
+/// ```
+///
+/// Flags can be written in full(`[writable, signer]`) or short(`[ws]`). Optional accounts are
+/// `Conditional` and variadic groups(`..2+M`) are `Repeated`. Only the first list is used if the
+/// docs have multiple lists e.g single owner and multisignature owner lists of `Transfer`.
+pub fn get_doc_account_metas(docs: &[String]) -> Vec<BuilderAccountMeta> {
+    let mut metas: Vec<BuilderAccountMeta> = vec![];
+
+    for doc in docs {
+        let line = doc.trim();
+        let (prefix, rest) = match line.split_once("`[") {
+            Some(split) => split,
+            None => continue,
+        };
+        let (flags, description) = match rest.split_once("]`") {
+            Some(split) => split,
+            None => continue,
+        };
+
+        // Index of the account e.g `0.`, `2. ..2+N.` or `M. ..`
+        let prefix = prefix.trim();
+        let index = prefix.split('.').next().unwrap_or_default().trim();
+        let is_index = !index.is_empty()
+            && !index.contains(' ')
+            && index.chars().all(|c| c.is_ascii_alphanumeric() || c == '+');
+        if !prefix.contains('.') || !is_index {
+            continue;
+        }
+
+        // Second list of the same instruction
+        if index == "0" && !metas.is_empty() {
+            break;
+        }
+
+        let flags = flags
+            .split([',', ' '])
+            .filter(|flag| !flag.is_empty())
+            .collect::<Vec<_>>();
+        // e.g `writable` or `w`, `writeable` is a common typo
+        let has_flag = |full: &str, short: char| {
+            flags.iter().any(|flag| {
+                flag.starts_with(&full[..4]) || (flag.len() <= 2 && flag.contains(short))
+            })
+        };
+        let is_writable = has_flag("writable", 'w');
+        let is_signer = has_flag("signer", 's');
+
+        let description = description.trim();
+        let is_optional = flags.contains(&"optional")
+            || description.to_lowercase().starts_with("(optional)")
+            || description.to_lowercase().starts_with("optional");
+        let is_variadic = prefix.contains("..");
+
+        let kind = match (is_variadic, is_optional) {
+            (true, _) => AccountMetaKind::Repeated,
+            (false, true) => AccountMetaKind::Conditional,
+            (false, false) => AccountMetaKind::Unconditional,
+        };
+
+        let mut name = get_account_name(description);
+        if name.is_empty() {
+            name = format!("account_{}", metas.len());
+        }
+        if metas.iter().any(|meta| meta.pubkey == name) {
+            name = format!("{name}_{}", metas.len());
+        }
+
+        metas.push(BuilderAccountMeta {
+            pubkey: name,
+            is_writable,
+            is_signer,
+            is_dynamic_writable: false,
+            is_dynamic_signer: false,
+            kind,
+            source: line.to_owned(),
+            description: Some(description.to_owned()).filter(|d| !d.is_empty()),
+            owner: None,
+            sysvar: None,
+            pda: None,
+        });
+    }
+
+    metas
+}
+
+/// Create a snake_case account name from the description of the account e.g
+/// `The source account's owner/delegate.` -> `source_owner`
+fn get_account_name(description: &str) -> String {
+    let description = description
+        .trim_start_matches("(optional)")
+        .split(['.', ',', '(', ':', ';'])
+        .next()
+        .unwrap_or_default();
+
+    description
+        .split_whitespace()
+        .map(|word| {
+            // Pick the first of the alternatives e.g `owner/delegate`
+            let word = word.split('/').next().unwrap_or_default();
+            word.trim_end_matches("'s")
+                .chars()
+                .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+                .collect::<String>()
+                .to_lowercase()
+        })
+        .take_while(|word| !NAME_END_WORDS.contains(&word.as_str()))
+        .filter(|word| word.len() > 1 && !SKIPPED_WORDS.contains(&word.as_str()))
+        .take(3)
+        .collect::<Vec<_>>()
+        .join("_")
+}
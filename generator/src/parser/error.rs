@@ -0,0 +1,157 @@
+use std::{cmp::Reverse, path::Path};
+
+use quote::ToTokens;
+use syn::{
+    Attribute, GenericArgument, Item, ItemEnum, Lit, Meta, NestedMeta, PathArguments, Variant,
+    Visibility,
+};
+
+use super::{
+    common::{get_derives, get_docs, get_trait_name, tokens_to_string},
+    module::{ModuleGraph, Symbol},
+};
+use crate::constants::idl::ERROR_TRAITS;
+
+/// Custom error enum of the program e.g `TokenError`
+pub struct ErrorEnum {
+    pub name: String,
+    pub variants: Vec<ErrorVariant>,
+}
+
+pub struct ErrorVariant {
+    pub name: String,
+    pub docs: Vec<String>,
+    /// Message from `#[error("...")]`
+    pub msg: Option<String>,
+    /// Explicit discriminant e.g `5` for `NotRentExempt = 5`
+    pub discriminant: Option<String>,
+}
+
+impl ErrorEnum {
+    /// Find the error enum of the program.
+    ///
+    /// Enums that are converted to `ProgramError`(e.g `impl From<TokenError> for ProgramError`)
+    /// or implement the error traits are preferred, then the enums that derive `Error`. Only the
+    /// modules inside `path` are searched if it's given.
+    pub fn find(modules: &ModuleGraph, path: Option<&Path>) -> Option<Self> {
+        let candidate_modules = match path {
+            Some(path) => modules
+                .modules_in(path)
+                .into_iter()
+                .map(|module| module.path.as_str())
+                .collect(),
+            None => modules
+                .modules()
+                .iter()
+                .map(|module| module.path.as_str())
+                .collect::<Vec<_>>(),
+        };
+        let program_error_types = get_program_error_types(modules);
+
+        modules
+            .symbols()
+            .filter(|symbol| candidate_modules.contains(&symbol.module.as_str()))
+            .filter_map(|symbol| match &symbol.item {
+                Item::Enum(item_enum) => Some((symbol, item_enum)),
+                _ => None,
+            })
+            .filter_map(|(symbol, item_enum)| {
+                let is_public = matches!(item_enum.vis, Visibility::Public(_));
+
+                let mut score = 0;
+                if program_error_types.contains(&symbol.path) {
+                    score += 4;
+                }
+                if get_derives(&item_enum.attrs).contains(&"Error".into()) {
+                    score += 2;
+                }
+                if symbol.module.ends_with("error") || symbol.module.ends_with("errors") {
+                    score += 1;
+                }
+
+                // Any public enum is accepted if the path is given explicitly
+                match score >= 2 || (path.is_some() && is_public) {
+                    true => Some((score, item_enum)),
+                    false => None,
+                }
+            })
+            // First enum with the highest score
+            .min_by_key(|(score, _)| Reverse(*score))
+            .map(|(_, item_enum)| Self::new(item_enum))
+    }
+
+    fn new(item_enum: &ItemEnum) -> Self {
+        Self {
+            name: item_enum.ident.to_string(),
+            variants: item_enum.variants.iter().map(ErrorVariant::new).collect(),
+        }
+    }
+}
+
+impl ErrorVariant {
+    fn new(variant: &Variant) -> Self {
+        Self {
+            name: variant.ident.to_string(),
+            docs: get_docs(&variant.attrs),
+            msg: get_error_msg(&variant.attrs),
+            discriminant: variant
+                .discriminant
+                .as_ref()
+                .map(|(_, expr)| tokens_to_string(expr.to_token_stream().to_string())),
+        }
+    }
+}
+
+/// Get the types that can be converted to `ProgramError` e.g `impl From<TokenError> for
+/// ProgramError`, `impl Into<ProgramError> for TokenError` or `impl PrintProgramError for
+/// TokenError`
+fn get_program_error_types(modules: &ModuleGraph) -> Vec<String> {
+    modules
+        .impls()
+        .filter_map(|(module, item_impl, symbol)| {
+            let (_, trait_path, _) = item_impl.trait_.as_ref()?;
+            let trait_name = get_trait_name(item_impl)?;
+            let self_ty = tokens_to_string(item_impl.self_ty.to_token_stream().to_string());
+            match trait_name.as_str() {
+                "From" if self_ty.ends_with("ProgramError") => {
+                    let generic = match &trait_path.segments.last()?.arguments {
+                        PathArguments::AngleBracketed(args) => args.args.first(),
+                        _ => None,
+                    };
+                    match generic {
+                        Some(GenericArgument::Type(ty)) => modules.resolve(
+                            &tokens_to_string(ty.to_token_stream().to_string()),
+                            &module.path,
+                        ),
+                        _ => None,
+                    }
+                }
+                "Into"
+                    if trait_path
+                        .to_token_stream()
+                        .to_string()
+                        .contains("ProgramError") =>
+                {
+                    symbol
+                }
+                name if ERROR_TRAITS.contains(&name) => symbol,
+                _ => None,
+            }
+        })
+        .map(|symbol: &Symbol| symbol.path.to_owned())
+        .collect()
+}
+
+/// Get the message of the error e.g `Insufficient funds` from `#[error("Insufficient funds")]`
+fn get_error_msg(attrs: &[Attribute]) -> Option<String> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("error"))
+        .find_map(|attr| match attr.parse_meta() {
+            Ok(Meta::List(list)) => match list.nested.first() {
+                Some(NestedMeta::Lit(Lit::Str(msg))) => Some(msg.value()),
+                _ => None,
+            },
+            _ => None,
+        })
+}
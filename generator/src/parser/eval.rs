@@ -0,0 +1,377 @@
+use quote::ToTokens;
+use syn::{
+    visit_mut::{self, VisitMut},
+    BinOp, Expr, GenericArgument, Item, Lit, PathArguments, Type, TypeArray,
+};
+
+use super::{
+    common::{get_last_segment, tokens_to_string, type_to_string},
+    module::ModuleGraph,
+};
+use crate::constants::known_types::{KNOWN_CONSTS, KNOWN_TYPE_SIZES};
+
+/// Maximum amount of constants to follow while evaluating an expression
+const MAX_EVAL_DEPTH: usize = 16;
+
+/// Evaluate the constant expression e.g `1 + 32 + size_of::<Pubkey>()` or `MAX_SIGNERS * 2`.
+///
+/// Paths are resolved relative to the module, returns `None` if the expression can't be evaluated.
+pub fn eval_const_expr(expr: &Expr, module: &str, modules: &ModuleGraph) -> Option<u128> {
+    Evaluator {
+        modules,
+        module,
+        self_path: None,
+        depth: 0,
+    }
+    .eval(expr)
+}
+
+/// Evaluate the constant expression string e.g `Mint::LEN`
+pub fn eval_const(expr: &str, module: &str, modules: &ModuleGraph) -> Option<u128> {
+    eval_const_expr(&syn::parse_str(expr).ok()?, module, modules)
+}
+
+/// Replace the constant array lengths of the type with their values e.g `[Pubkey; MAX_SIGNERS]`
+/// -> `[Pubkey; 11]`, lengths that can't be evaluated are kept
+pub fn eval_array_lens(ty: &str, module: &str, modules: &ModuleGraph) -> String {
+    struct ArrayLenVisitor<'a> {
+        modules: &'a ModuleGraph,
+        module: &'a str,
+    }
+
+    impl VisitMut for ArrayLenVisitor<'_> {
+        fn visit_type_array_mut(&mut self, type_array: &mut TypeArray) {
+            visit_mut::visit_type_array_mut(self, type_array);
+            if matches!(type_array.len, Expr::Lit(_)) {
+                return;
+            }
+            if let Some(len) = eval_const_expr(&type_array.len, self.module, self.modules) {
+                type_array.len = syn::parse_str(&len.to_string()).unwrap();
+            }
+        }
+    }
+
+    match syn::parse_str::<Type>(ty) {
+        Ok(mut parsed_ty) => {
+            ArrayLenVisitor { modules, module }.visit_type_mut(&mut parsed_ty);
+            type_to_string(&parsed_ty)
+        }
+        Err(_) => ty.to_owned(),
+    }
+}
+
+struct Evaluator<'a> {
+    modules: &'a ModuleGraph,
+    /// Module the expression is defined in
+    module: &'a str,
+    /// Path of the type if the expression is an associated constant, used for `Self`
+    self_path: Option<&'a str>,
+    depth: usize,
+}
+
+impl Evaluator<'_> {
+    fn eval(&self, expr: &Expr) -> Option<u128> {
+        match expr {
+            Expr::Lit(expr_lit) => match &expr_lit.lit {
+                Lit::Int(lit_int) => lit_int.base10_parse().ok(),
+                Lit::Byte(lit_byte) => Some(lit_byte.value() as u128),
+                Lit::Bool(lit_bool) => Some(lit_bool.value as u128),
+                _ => None,
+            },
+            Expr::Binary(expr_binary) => {
+                let left = self.eval(&expr_binary.left)?;
+                let right = self.eval(&expr_binary.right)?;
+                match expr_binary.op {
+                    BinOp::Add(_) => left.checked_add(right),
+                    BinOp::Sub(_) => left.checked_sub(right),
+                    BinOp::Mul(_) => left.checked_mul(right),
+                    BinOp::Div(_) => left.checked_div(right),
+                    BinOp::Rem(_) => left.checked_rem(right),
+                    BinOp::Shl(_) => left.checked_shl(right.try_into().ok()?),
+                    BinOp::Shr(_) => left.checked_shr(right.try_into().ok()?),
+                    BinOp::BitAnd(_) => Some(left & right),
+                    BinOp::BitOr(_) => Some(left | right),
+                    BinOp::BitXor(_) => Some(left ^ right),
+                    _ => None,
+                }
+            }
+            Expr::Paren(expr_paren) => self.eval(&expr_paren.expr),
+            Expr::Group(expr_group) => self.eval(&expr_group.expr),
+            Expr::Cast(expr_cast) => self.eval(&expr_cast.expr),
+            Expr::Block(expr_block) => match expr_block.block.stmts.as_slice() {
+                [syn::Stmt::Expr(expr)] => self.eval(expr),
+                _ => None,
+            },
+            Expr::Call(expr_call) => match expr_call.func.as_ref() {
+                // `size_of::<Pubkey>()` or `std::mem::size_of::<Pubkey>()`
+                Expr::Path(expr_path) if expr_call.args.is_empty() => {
+                    let segment = expr_path.path.segments.last()?;
+                    if segment.ident != "size_of" {
+                        return None;
+                    }
+                    match &segment.arguments {
+                        PathArguments::AngleBracketed(args) => match args.args.first()? {
+                            GenericArgument::Type(ty) => self.size_of(ty),
+                            _ => None,
+                        },
+                        _ => None,
+                    }
+                }
+                _ => None,
+            },
+            Expr::Path(expr_path) => {
+                let path = tokens_to_string(expr_path.path.to_token_stream().to_string());
+                self.eval_path(&path)
+            }
+            _ => None,
+        }
+    }
+
+    /// Evaluate the constant that the path points to e.g `MAX_SIGNERS` or `Mint::LEN`
+    fn eval_path(&self, path: &str) -> Option<u128> {
+        if self.depth >= MAX_EVAL_DEPTH {
+            return None;
+        }
+
+        let path = match (path.strip_prefix("Self::"), self.self_path) {
+            (Some(rest), Some(self_path)) => format!("{self_path}::{rest}"),
+            _ => path.to_owned(),
+        };
+        let symbol = self
+            .modules
+            .resolve(&path, self.module)
+            .or_else(|| self.modules.resolve_external(&path, self.module));
+        let symbol = match symbol {
+            Some(symbol) => symbol,
+            None => {
+                let name = path.rsplit("::").next()?;
+                return KNOWN_CONSTS
+                    .iter()
+                    .find(|(known_name, _)| *known_name == name)
+                    .map(|(_, value)| *value);
+            }
+        };
+
+        let expr = match &symbol.item {
+            Item::Const(item_const) => &item_const.expr,
+            Item::Static(item_static) => &item_static.expr,
+            _ => return None,
+        };
+        // Associated constants are defined for the parent symbol e.g `crate::state::Mint::LEN`
+        let self_path = symbol
+            .path
+            .rsplit_once("::")
+            .map(|(parent, _)| parent)
+            .filter(|parent| self.modules.resolve(parent, &symbol.module).is_some());
+
+        Evaluator {
+            modules: self.modules,
+            module: &symbol.module,
+            self_path,
+            depth: self.depth + 1,
+        }
+        .eval(expr)
+    }
+
+    /// Get the size of the type in bytes for `size_of::<T>()`
+    fn size_of(&self, ty: &Type) -> Option<u128> {
+        self.layout_of(ty).map(|(size, _)| size)
+    }
+
+    /// Get the size and the alignment of the type. Tuples are only evaluated if their elements
+    /// have the same alignment, otherwise the padding depends on how Rust orders the elements.
+    fn layout_of(&self, ty: &Type) -> Option<(u128, u128)> {
+        match ty {
+            Type::Array(type_array) => {
+                let (size, align) = self.layout_of(&type_array.elem)?;
+                Some((size.checked_mul(self.eval(&type_array.len)?)?, align))
+            }
+            Type::Tuple(type_tuple) => {
+                let layouts = type_tuple
+                    .elems
+                    .iter()
+                    .map(|elem| self.layout_of(elem))
+                    .collect::<Option<Vec<_>>>()?;
+                let align = layouts.first().map_or(1, |(_, align)| *align);
+                if layouts.iter().any(|(_, elem_align)| *elem_align != align) {
+                    return None;
+                }
+                let size = layouts
+                    .iter()
+                    .try_fold(0u128, |acc, (size, _)| acc.checked_add(*size))?;
+                Some((size, align))
+            }
+            Type::Paren(type_paren) => self.layout_of(&type_paren.elem),
+            Type::Path(type_path) => {
+                let name = get_last_segment(&type_path.path)?;
+                KNOWN_TYPE_SIZES
+                    .iter()
+                    .find(|(known_name, ..)| *known_name == name)
+                    .map(|(_, size, align)| (*size, *align))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::*;
+    use crate::parser::cfg::CfgContext;
+
+    /// Create a crate from the given files and build its module graph
+    fn create_modules(name: &str, files: &[(&str, &str)]) -> ModuleGraph {
+        let src_path = env::temp_dir()
+            .join(format!("nta-eval-{name}-{}", process::id()))
+            .join("src");
+        let _ = fs::remove_dir_all(&src_path);
+        for (path, content) in files {
+            let path = src_path.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+
+        let modules = ModuleGraph::new(&src_path, CfgContext::default()).unwrap();
+        fs::remove_dir_all(src_path.parent().unwrap()).unwrap();
+        modules
+    }
+
+    fn eval_in(expr: &str, module: &str, modules: &ModuleGraph) -> Option<u128> {
+        eval_const(expr, module, modules)
+    }
+
+    #[test]
+    fn eval_literals() {
+        let modules = create_modules("literals", &[("lib.rs", "")]);
+        let cases: &[(&str, Option<u128>)] = &[
+            ("32", Some(32)),
+            ("32u64", Some(32)),
+            ("1_000usize", Some(1000)),
+            ("0xff", Some(255)),
+            ("b'a'", Some(97)),
+            ("true", Some(1)),
+            ("(1 + 2) * 3", Some(9)),
+            ("{ 10 - 4 }", Some(6)),
+            ("10 % 4 + (1 << 4)", Some(18)),
+            ("5 as u64", Some(5)),
+            ("\"str\"", None),
+        ];
+        for (expr, expected) in cases {
+            assert_eq!(eval_in(expr, "crate", &modules), *expected, "{expr}");
+        }
+    }
+
+    #[test]
+    fn eval_overflow() {
+        let modules = create_modules("overflow", &[("lib.rs", "")]);
+        let cases: &[(&str, Option<u128>)] = &[
+            ("1 - 2", None),
+            ("1 / 0", None),
+            ("340282366920938463463374607431768211455 + 1", None),
+            ("340282366920938463463374607431768211455 * 2", None),
+            ("1 << 200", None),
+        ];
+        for (expr, expected) in cases {
+            assert_eq!(eval_in(expr, "crate", &modules), *expected, "{expr}");
+        }
+    }
+
+    #[test]
+    fn eval_paths() {
+        let modules = create_modules(
+            "paths",
+            &[
+                ("lib.rs", "pub mod constants;\npub mod state;\n"),
+                (
+                    "constants.rs",
+                    "pub const SEED_LEN: usize = 32;\npub const MAX_SIGNERS: usize = 11;\n",
+                ),
+                (
+                    "state.rs",
+                    r#"
+use crate::constants::SEED_LEN;
+use solana_program::pubkey::Pubkey;
+use std::mem::size_of;
+
+pub const SLOTS: usize = SEED_LEN / 4 + 2;
+pub static TOTAL: u64 = SLOTS as u64 * 2;
+
+pub struct Vault;
+
+impl Vault {
+    pub const LEN: usize = 1 + size_of::<Pubkey>() + Self::SIGNERS_LEN;
+    pub const SIGNERS_LEN: usize = 32 * crate::constants::MAX_SIGNERS;
+}
+"#,
+                ),
+            ],
+        );
+        let cases: &[(&str, &str, Option<u128>)] = &[
+            ("SEED_LEN / 4 + 2", "crate::state", Some(10)),
+            ("SLOTS", "crate::state", Some(10)),
+            ("crate::state::SLOTS", "crate", Some(10)),
+            ("TOTAL", "crate::state", Some(20)),
+            ("Vault::LEN", "crate::state", Some(1 + 32 + 352)),
+            ("crate::state::Vault::SIGNERS_LEN", "crate", Some(352)),
+            ("size_of::<u64>()", "crate", Some(8)),
+            ("std::mem::size_of::<[u16; 4]>()", "crate", Some(8)),
+            ("core::mem::size_of::<(u8, Pubkey)>()", "crate", Some(33)),
+            ("size_of::<(u32, [u32; 2])>()", "crate", Some(12)),
+            // Padding of the mixed alignments is not known
+            ("size_of::<(u8, u64)>()", "crate", None),
+            ("size_of::<Vault>()", "crate", None),
+            ("UNKNOWN", "crate", None),
+        ];
+        for (expr, module, expected) in cases {
+            assert_eq!(eval_in(expr, module, &modules), *expected, "{expr}");
+        }
+    }
+
+    #[test]
+    fn eval_cyclic_consts() {
+        let modules = create_modules(
+            "cyclic",
+            &[(
+                "lib.rs",
+                "pub const A: usize = B + 1;\npub const B: usize = A + 1;\npub const C: usize = C;\n",
+            )],
+        );
+        assert_eq!(eval_in("A", "crate", &modules), None);
+        assert_eq!(eval_in("C * 2", "crate", &modules), None);
+    }
+
+    #[test]
+    fn eval_array_lens_of_types() {
+        let modules = create_modules(
+            "arrays",
+            &[("lib.rs", "pub const MAX_SIGNERS: usize = 11;\n")],
+        );
+        let cases = [
+            ("[Pubkey; MAX_SIGNERS]", "[Pubkey ; 11]"),
+            ("Vec<[u8; MAX_SIGNERS * 2]>", "Vec < [u8 ; 22] >"),
+            ("[u8; UNKNOWN]", "[u8 ; UNKNOWN]"),
+            ("[u8; 4]", "[u8 ; 4]"),
+        ];
+        for (ty, expected) in cases {
+            let expected = type_to_string(&syn::parse_str::<Type>(expected).unwrap());
+            assert_eq!(eval_array_lens(ty, "crate", &modules), expected, "{ty}");
+        }
+    }
+
+    #[test]
+    fn eval_depth_limit() {
+        // A chain that is longer than the depth limit can't be evaluated
+        let consts = (0..MAX_EVAL_DEPTH + 1)
+            .map(|i| format!("pub const C{i}: usize = C{} + 1;\n", i + 1))
+            .chain([format!("pub const C{}: usize = 0;\n", MAX_EVAL_DEPTH + 1)])
+            .collect::<String>();
+        let modules = create_modules("depth", &[("lib.rs", &consts)]);
+        assert_eq!(
+            eval_in("C2", "crate", &modules),
+            Some(MAX_EVAL_DEPTH as u128 - 1)
+        );
+        assert_eq!(eval_in("C0", "crate", &modules), None);
+    }
+}
@@ -0,0 +1,483 @@
+use std::{cmp::Reverse, path::Path};
+
+use quote::ToTokens;
+use serde_derive::{Deserialize, Serialize};
+use syn::{
+    visit::{self, Visit},
+    Expr, ExprCall, Field, Fields, FnArg, ImplItem, Item, ItemEnum, Member, Pat, Signature, Type,
+    Variant, Visibility,
+};
+
+use super::{
+    common::{
+        get_attrs, get_derives, get_discriminants, get_docs, strip_type_paths, tokens_to_string,
+        type_to_string,
+    },
+    module::{Module, ModuleGraph},
+    pack::{get_pack_layouts, PackLayout, PackLayouts},
+};
+use crate::{
+    constants::idl::{DESERIALIZE_FNS, INT_TYPES},
+    utils::common::warn,
+};
+
+/// Instruction enum of the program e.g `TokenInstruction`
+pub struct InstructionEnum {
+    pub name: String,
+    /// Module the enum is defined in e.g `crate::instruction`
+    pub module: String,
+    /// Encoding of the instruction tag in the instruction data
+    pub tag: InstructionTag,
+    pub variants: Vec<InstructionVariant>,
+}
+
+/// Encoding of the tag that comes before the instruction arguments
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum InstructionTag {
+    /// 1 byte e.g Borsh enums
+    #[default]
+    U8,
+    /// 2 bytes little endian
+    U16,
+    /// 4 bytes little endian e.g bincode enums
+    U32,
+    /// 8 bytes little endian
+    U64,
+    /// First 8 bytes of `sha256("global:<instruction_name>")` like Anchor programs
+    Sighash,
+}
+
+/// Single instruction(variant) of the instruction enum
+pub struct InstructionVariant {
+    pub name: String,
+    pub docs: Vec<String>,
+    pub attrs: Vec<String>,
+    /// Tag of the instruction in the instruction data
+    pub discriminant: u64,
+    /// Instruction arguments, argument structs are flattened
+    pub args: Vec<InstructionField>,
+}
+
+enum InstructionFields {
+    /// e.g `Settle,` or `FinalizeVote {}`
+    Unit,
+    /// e.g `Transfer { amount: u64 }`
+    Named(Vec<InstructionField>),
+    /// e.g `Trade(TradeArgs)` or `Swap(u64, u64)`
+    Tuple(Vec<Type>),
+}
+
+#[derive(Clone)]
+pub struct InstructionField {
+    pub name: String,
+    /// Type without module paths e.g `Option<Config>`
+    pub ty: String,
+    /// Full paths of the types used in the type e.g `["Option", "crate::state::Config"]`
+    pub type_paths: Vec<String>,
+    pub docs: Vec<String>,
+}
+
+impl InstructionEnum {
+    /// Find the instruction enum of the program.
+    ///
+    /// Enums that are deserialized from the instruction data inside the processor are preferred,
+    /// then the public enums that have `Instruction` in their names. Only the modules inside `path`
+    /// are searched if it's given.
+    pub fn find(modules: &ModuleGraph, path: Option<&Path>) -> Option<Self> {
+        let candidate_modules = match path {
+            Some(path) => modules.modules_in(path),
+            None => modules.modules().iter().collect(),
+        };
+        let instruction_data_types = get_instruction_data_types(modules);
+        let deserializable_types = get_deserializable_types(modules);
+
+        candidate_modules
+            .into_iter()
+            .flat_map(|module| {
+                module.items.iter().filter_map(move |item| match item {
+                    Item::Enum(item_enum) => Some((module, item_enum)),
+                    _ => None,
+                })
+            })
+            .filter_map(|(module, item_enum)| {
+                let name = item_enum.ident.to_string();
+                let enum_path = format!("{}::{name}", module.path);
+                let is_public = matches!(item_enum.vis, Visibility::Public(_));
+
+                let mut score = 0;
+                if instruction_data_types.contains(&enum_path) {
+                    score += 8;
+                }
+                if is_public && name.contains("Instruction") {
+                    score += 4;
+                }
+                if deserializable_types.contains(&enum_path)
+                    || get_derives(&item_enum.attrs).contains(&"BorshDeserialize".into())
+                {
+                    score += 2;
+                }
+                if module.path.ends_with("instruction") || module.path.ends_with("instructions") {
+                    score += 1;
+                }
+
+                // Any public enum is accepted if the path is given explicitly
+                match score >= 4 || (path.is_some() && is_public) {
+                    true => Some((score, module, item_enum)),
+                    false => None,
+                }
+            })
+            // First enum with the highest score
+            .min_by_key(|(score, ..)| Reverse(*score))
+            .map(|(_, module, item_enum)| Self::new(item_enum, module, modules))
+    }
+
+    fn new(item_enum: &ItemEnum, module: &Module, modules: &ModuleGraph) -> Self {
+        // Enums without a serializer are (un)packed manually, the data layout can be different
+        // from the order of the fields
+        let pack_layouts = match has_serializer(item_enum) {
+            true => PackLayouts::default(),
+            false => get_pack_layouts(item_enum, module, modules),
+        };
+
+        Self {
+            name: item_enum.ident.to_string(),
+            module: module.path.clone(),
+            tag: InstructionTag::infer(item_enum, &pack_layouts),
+            variants: item_enum
+                .variants
+                .iter()
+                .zip(get_discriminants(item_enum, modules, &module.path))
+                .map(|(variant, discriminant)| {
+                    let layout = pack_layouts.variants.get(&variant.ident.to_string());
+                    InstructionVariant::new(variant, discriminant, layout, module, modules)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl InstructionTag {
+    /// Infer the tag from the serializer of the enum, Borsh uses a `u8` tag, bincode(serde) uses a
+    /// `u32` tag and the enums that are (un)packed manually use the type of the tag that is read in
+    /// their unpack function(or written in their pack function). `#[repr]` is only used if the tag
+    /// type can't be inferred from the (un)pack functions.
+    fn infer(item_enum: &ItemEnum, pack_layouts: &PackLayouts) -> Self {
+        let derives = get_derives(&item_enum.attrs);
+        let derives = |name: &str| derives.iter().any(|derive| derive == name);
+        if derives("BorshSerialize") || derives("BorshDeserialize") {
+            return Self::U8;
+        }
+        if derives("Serialize") || derives("Deserialize") {
+            return Self::U32;
+        }
+
+        let tag_type = match &pack_layouts.tag_type {
+            Some(tag_type) => type_to_string(tag_type),
+            None => get_attrs(&item_enum.attrs)
+                .into_iter()
+                .find_map(|attr| {
+                    attr.strip_prefix("repr(")
+                        .and_then(|repr| repr.strip_suffix(')'))
+                        .map(str::to_owned)
+                })
+                .unwrap_or_default(),
+        };
+        match tag_type.as_str() {
+            "u16" | "i16" => Self::U16,
+            "u32" | "i32" => Self::U32,
+            "u64" | "i64" => Self::U64,
+            _ => Self::U8,
+        }
+    }
+
+    /// Size of the tag in bytes
+    pub fn size(&self) -> usize {
+        match self {
+            Self::U8 => 1,
+            Self::U16 => 2,
+            Self::U32 => 4,
+            Self::U64 | Self::Sighash => 8,
+        }
+    }
+}
+
+impl InstructionVariant {
+    /// Whether the variant is a placeholder for a removed or a future instruction e.g `Reserved`
+    /// or `Reserved2`, real instructions like `ClaimReservedTokens` are not
+    pub fn is_reserved(&self) -> bool {
+        self.name
+            .strip_prefix("Reserved")
+            .is_some_and(|suffix| suffix.chars().all(|c| c.is_ascii_digit() || c == '_'))
+    }
+
+    fn new(
+        variant: &Variant,
+        discriminant: u64,
+        layout: Option<&PackLayout>,
+        module: &Module,
+        modules: &ModuleGraph,
+    ) -> Self {
+        let fields = match &variant.fields {
+            Fields::Named(named) if !named.named.is_empty() => InstructionFields::Named(
+                named
+                    .named
+                    .iter()
+                    .map(|field| InstructionField::new(field, None))
+                    .collect(),
+            ),
+            Fields::Unnamed(unnamed) if !unnamed.unnamed.is_empty() => InstructionFields::Tuple(
+                unnamed
+                    .unnamed
+                    .iter()
+                    .map(|field| field.ty.clone())
+                    .collect(),
+            ),
+            _ => InstructionFields::Unit,
+        };
+
+        let mut is_flattened = false;
+        let args = match &fields {
+            InstructionFields::Unit => vec![],
+            InstructionFields::Named(fields) => fields.to_vec(),
+            InstructionFields::Tuple(types) => match types.as_slice() {
+                // Args defined in a separate struct e.g `Trade(TradeArgs)`
+                [ty] => match modules
+                    .resolve(&type_to_string(ty), &module.path)
+                    .map(|symbol| &symbol.item)
+                {
+                    Some(Item::Struct(item_struct)) => {
+                        is_flattened = true;
+                        item_struct
+                            .fields
+                            .iter()
+                            .enumerate()
+                            .map(|(i, field)| InstructionField::new(field, Some(i)))
+                            .collect()
+                    }
+                    _ => vec![InstructionField::positional("arg", ty)],
+                },
+                _ => types
+                    .iter()
+                    .enumerate()
+                    .map(|(i, ty)| InstructionField::positional(format!("arg_{i}"), ty))
+                    .collect(),
+            },
+        };
+
+        let mut instruction_variant = Self {
+            name: variant.ident.to_string(),
+            docs: get_docs(&variant.attrs),
+            attrs: get_attrs(&variant.attrs),
+            discriminant,
+            args,
+        };
+        if let Some(layout) = layout {
+            instruction_variant.apply_pack_layout(layout, is_flattened);
+        }
+
+        instruction_variant
+    }
+
+    /// Use the tag and the field order of the data layout from the `unpack`/`pack` functions,
+    /// layouts that are different from the fields are reported
+    fn apply_pack_layout(&mut self, layout: &PackLayout, is_flattened: bool) {
+        if let Some(tag) = layout.tag {
+            self.discriminant = tag;
+        }
+        // Fields of the args struct are (un)packed by the struct itself
+        if is_flattened {
+            return;
+        }
+
+        let args = layout
+            .fields
+            .iter()
+            .filter_map(|field| {
+                let mut arg = match &field.member {
+                    Member::Named(ident) => self.args.iter().find(|arg| ident == &arg.name),
+                    Member::Unnamed(index) => self.args.get(index.index as usize),
+                }?
+                .clone();
+
+                if let Some(ty) = &field.ty {
+                    let (ty, type_paths) = strip_type_paths(ty);
+                    // Bytes that are converted after they are read e.g `AuthorityType::from(t)`
+                    let is_converted =
+                        INT_TYPES.contains(&ty.as_str()) && !INT_TYPES.contains(&arg.ty.as_str());
+                    if !is_converted {
+                        arg.ty = ty;
+                        arg.type_paths = type_paths;
+                    }
+                }
+
+                Some(arg)
+            })
+            .collect::<Vec<_>>();
+
+        let to_string = |args: &[InstructionField]| {
+            args.iter()
+                .map(|arg| format!("{}: {}", arg.name, arg.ty))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let (layout_str, fields_str) = (to_string(&args), to_string(&self.args));
+        if layout_str != fields_str {
+            warn(format!(
+                "Data layout of instruction '{}' `({layout_str})` is different from its fields \
+                `({fields_str})`, using the data layout.",
+                self.name
+            ));
+            self.args = args;
+        }
+    }
+}
+
+impl InstructionField {
+    fn new(field: &Field, index: Option<usize>) -> Self {
+        let (ty, type_paths) = strip_type_paths(&field.ty);
+        Self {
+            name: match (&field.ident, index) {
+                (Some(ident), _) => ident.to_string(),
+                (None, Some(i)) => format!("arg_{i}"),
+                (None, None) => "arg".into(),
+            },
+            ty,
+            type_paths,
+            docs: get_docs(&field.attrs),
+        }
+    }
+
+    fn positional(name: impl Into<String>, ty: &Type) -> Self {
+        let (ty, type_paths) = strip_type_paths(ty);
+        Self {
+            name: name.into(),
+            ty,
+            type_paths,
+            docs: vec![],
+        }
+    }
+}
+
+/// Whether the enum derives a serializer(Borsh or serde)
+fn has_serializer(item_enum: &ItemEnum) -> bool {
+    get_derives(&item_enum.attrs).iter().any(|derive| {
+        matches!(
+            derive.as_str(),
+            "BorshSerialize" | "BorshDeserialize" | "Serialize" | "Deserialize"
+        )
+    })
+}
+
+/// Get the types that are deserialized from the instruction data inside the processor functions
+/// e.g `TokenInstruction::unpack(input)`
+fn get_instruction_data_types(modules: &ModuleGraph) -> Vec<String> {
+    struct InstructionDataVisitor<'a> {
+        modules: &'a ModuleGraph,
+        module: &'a str,
+        /// Name of the instruction data parameter e.g `instruction_data`
+        data_name: String,
+        types: Vec<String>,
+    }
+
+    impl<'ast> Visit<'ast> for InstructionDataVisitor<'_> {
+        fn visit_expr_call(&mut self, call: &'ast ExprCall) {
+            visit::visit_expr_call(self, call);
+
+            let path = match &*call.func {
+                Expr::Path(expr_path) if expr_path.path.segments.len() > 1 => &expr_path.path,
+                _ => return,
+            };
+            let fn_name = path.segments.last().unwrap().ident.to_string();
+            if !DESERIALIZE_FNS.contains(&fn_name.as_str()) {
+                return;
+            }
+
+            let uses_data = call.args.iter().any(|arg| {
+                arg.to_token_stream()
+                    .into_iter()
+                    .any(|token| token.to_string() == self.data_name)
+            });
+            if uses_data {
+                let type_path = tokens_to_string(path.to_token_stream().to_string());
+                let (type_path, _) = type_path.rsplit_once("::").unwrap();
+                if let Some(symbol) = self.modules.resolve(type_path, self.module) {
+                    self.types.push(symbol.path.to_owned());
+                }
+            }
+        }
+    }
+
+    let mut types = vec![];
+    for module in modules.modules() {
+        let fns = module.items.iter().flat_map(|item| match item {
+            Item::Fn(item_fn) => vec![(&item_fn.sig, &*item_fn.block)],
+            Item::Impl(item_impl) => item_impl
+                .items
+                .iter()
+                .filter_map(|impl_item| match impl_item {
+                    ImplItem::Method(method) => Some((&method.sig, &method.block)),
+                    _ => None,
+                })
+                .collect(),
+            _ => vec![],
+        });
+
+        for (sig, block) in fns {
+            if let Some(data_name) = get_instruction_data_name(sig) {
+                let mut visitor = InstructionDataVisitor {
+                    modules,
+                    module: &module.path,
+                    data_name,
+                    types: vec![],
+                };
+                visitor.visit_block(block);
+                types.extend(visitor.types);
+            }
+        }
+    }
+
+    types
+}
+
+/// Get the name of the instruction data parameter if the function is a processor function e.g
+/// `fn process(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8])`
+fn get_instruction_data_name(sig: &Signature) -> Option<String> {
+    let params = sig
+        .inputs
+        .iter()
+        .filter_map(|input| match input {
+            FnArg::Typed(pat_type) => Some(pat_type),
+            FnArg::Receiver(_) => None,
+        })
+        .collect::<Vec<_>>();
+
+    let has_accounts = params
+        .iter()
+        .any(|param| type_to_string(&param.ty).contains("AccountInfo"));
+    let data_param = params.last()?;
+    if !has_accounts || type_to_string(&data_param.ty) != "&[u8]" {
+        return None;
+    }
+
+    match &*data_param.pat {
+        Pat::Ident(pat_ident) => Some(pat_ident.ident.to_string()),
+        _ => None,
+    }
+}
+
+/// Get the types that have an associated deserialization function e.g `TokenInstruction::unpack`
+fn get_deserializable_types(modules: &ModuleGraph) -> Vec<String> {
+    modules
+        .impls()
+        .filter(|(_, item_impl, _)| {
+            item_impl.items.iter().any(|impl_item| match impl_item {
+                ImplItem::Method(method) => {
+                    DESERIALIZE_FNS.contains(&method.sig.ident.to_string().as_str())
+                }
+                _ => false,
+            })
+        })
+        .filter_map(|(_, _, symbol)| symbol.map(|symbol| symbol.path.to_owned()))
+        .collect()
+}
@@ -0,0 +1,12 @@
+pub mod account;
+pub mod builder;
+pub mod cfg;
+pub mod common;
+pub mod docs;
+pub mod error;
+pub mod eval;
+pub mod instruction;
+pub mod module;
+pub mod pack;
+pub mod pda;
+pub mod processor;
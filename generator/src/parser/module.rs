@@ -0,0 +1,760 @@
+use std::{
+    cell::OnceCell,
+    collections::HashMap,
+    error::Error,
+    fs::{self, DirEntry},
+    path::{Path, PathBuf},
+    ptr,
+};
+
+use quote::ToTokens;
+use syn::{Attribute, Ident, ImplItem, Item, ItemConst, ItemImpl, Lit, Meta, Type, UseTree};
+
+use super::{
+    cfg::CfgContext,
+    common::{get_docs, tokens_to_string},
+};
+use crate::{
+    constants::common::{dirname, filename},
+    generator::GeneratorResult,
+    utils::{
+        common::{debug, open_files, warn},
+        dependency::get_dependency_paths,
+    },
+};
+
+/// Maximum amount of imports to follow while resolving a path
+const MAX_RESOLVE_DEPTH: usize = 8;
+
+/// Module tree of the crate starting from `lib.rs` with every item indexed by its path
+#[derive(Default)]
+pub struct ModuleGraph {
+    /// Name of the crate root in paths, `crate` for the program and e.g `spl_token` for
+    /// dependencies
+    crate_name: String,
+    /// Dependencies of the crate, they are only loaded when a path points to them
+    dependencies: HashMap<String, Dependency>,
+    /// Modules in the order they are declared, crate root is the first module
+    modules: Vec<Module>,
+    /// Module path -> index of the module
+    module_indices: HashMap<String, usize>,
+    /// Fully qualified path -> symbol e.g `crate::state::Mint`
+    symbols: HashMap<String, Symbol>,
+    /// Symbol paths in the order they are defined
+    symbol_paths: Vec<String>,
+    /// Content of every module file without the code that is not compiled
+    files: Vec<String>,
+    /// Features that the `#[cfg(...)]` attributes of the crate are evaluated with
+    cfg: CfgContext,
+}
+
+pub struct Module {
+    /// Fully qualified path e.g `crate::state`
+    pub path: String,
+    /// File the module is defined in, inline modules share the file of their parent
+    pub file_path: PathBuf,
+    /// Inner doc comments e.g `//! Token program`
+    pub docs: Vec<String>,
+    pub items: Vec<Item>,
+    /// Imported name -> fully qualified path e.g `Mint` -> `crate::state::Mint`
+    imports: HashMap<String, String>,
+    /// Paths imported with `*` e.g `use crate::state::*`
+    glob_imports: Vec<String>,
+}
+
+struct Dependency {
+    /// Crate directory of the dependency
+    path: PathBuf,
+    modules: OnceCell<Option<ModuleGraph>>,
+}
+
+/// Item that is defined inside the crate
+pub struct Symbol {
+    /// Fully qualified path e.g `crate::state::Mint` or `crate::state::Mint::LEN`
+    pub path: String,
+    pub name: String,
+    /// Module the item is defined in, paths used inside the item are relative to this module
+    pub module: String,
+    pub item: Item,
+}
+
+impl ModuleGraph {
+    /// Follow the `mod` declarations of the crate starting from `lib.rs`, items that are not
+    /// compiled with the enabled features are skipped
+    pub fn new(src_path: impl AsRef<Path>, cfg: CfgContext) -> Result<Self, Box<dyn Error>> {
+        Self::load("crate", src_path, cfg)
+    }
+
+    fn load(
+        crate_name: &str,
+        src_path: impl AsRef<Path>,
+        cfg: CfgContext,
+    ) -> Result<Self, Box<dyn Error>> {
+        let src_path = src_path.as_ref();
+        let mut graph = Self {
+            crate_name: crate_name.to_owned(),
+            cfg,
+            dependencies: get_dependency_paths(
+                &src_path.parent().unwrap().join(filename::CARGO_TOML),
+            )
+            .into_iter()
+            .map(|(name, path)| {
+                let dependency = Dependency {
+                    path,
+                    modules: OnceCell::new(),
+                };
+                (name, dependency)
+            })
+            .collect(),
+            ..Default::default()
+        };
+
+        let root_path = [filename::LIB, filename::MAIN]
+            .iter()
+            .map(|name| src_path.join(name))
+            .find(|path| path.exists());
+        match root_path {
+            Some(root_path) => {
+                graph.load_file(crate_name.to_owned(), root_path, src_path.to_owned())?
+            }
+            None => {
+                warn(format!(
+                    "Could not find crate root in {src_path:?}, every file will be a module of the crate."
+                ));
+
+                let mut f = |dir_entry: DirEntry| -> GeneratorResult {
+                    let path = dir_entry.path();
+                    if path.extension().and_then(|ext| ext.to_str()) != Some("rs") {
+                        return Ok(());
+                    }
+
+                    // Module path from the relative path e.g `a/b.rs` -> `a::b`, `a/mod.rs` -> `a`
+                    let relative_path = path.strip_prefix(src_path).unwrap().with_extension("");
+                    let mut segments = relative_path
+                        .iter()
+                        .map(|segment| segment.to_str().unwrap().replace('-', "_"))
+                        .collect::<Vec<_>>();
+                    let dir = match segments.last().map(String::as_str) {
+                        Some("mod") => {
+                            segments.pop();
+                            path.parent().unwrap().to_owned()
+                        }
+                        _ => path.with_extension(""),
+                    };
+                    let module_path = std::iter::once(crate_name.to_owned())
+                        .chain(segments)
+                        .collect::<Vec<_>>()
+                        .join("::");
+                    graph.load_file(module_path, path, dir)
+                };
+                open_files(src_path, &mut f)?;
+            }
+        }
+
+        graph.resolve_imports();
+        graph.create_symbols();
+        debug(format!(
+            "Found {} modules and {} symbols in '{crate_name}'.",
+            graph.modules.len(),
+            graph.symbols.len()
+        ));
+
+        Ok(graph)
+    }
+
+    pub fn modules(&self) -> &[Module] {
+        &self.modules
+    }
+
+    pub fn module(&self, path: &str) -> Option<&Module> {
+        self.module_indices.get(path).map(|&i| &self.modules[i])
+    }
+
+    /// Get the modules that are defined in the given file or inside the given directory
+    pub fn modules_in(&self, path: impl AsRef<Path>) -> Vec<&Module> {
+        self.modules
+            .iter()
+            .filter(|module| module.file_path.starts_with(path.as_ref()))
+            .collect()
+    }
+
+    /// Get all symbols of the crate in the order they are defined
+    pub fn symbols(&self) -> impl Iterator<Item = &Symbol> {
+        self.symbol_paths.iter().map(|path| &self.symbols[path])
+    }
+
+    /// Get every `impl` block of the crate with the symbol of the type it's implemented for, types
+    /// that are not defined inside the crate(e.g `impl From<MyError> for ProgramError`) don't have
+    /// a symbol
+    pub fn impls(&self) -> impl Iterator<Item = (&Module, &ItemImpl, Option<&Symbol>)> {
+        self.modules.iter().flat_map(move |module| {
+            module.items.iter().filter_map(move |item| match item {
+                Item::Impl(item_impl) => {
+                    let self_ty = tokens_to_string(item_impl.self_ty.to_token_stream().to_string());
+                    let symbol = self.resolve(&self_ty, &module.path);
+                    Some((module, item_impl, symbol))
+                }
+                _ => None,
+            })
+        })
+    }
+
+    /// Get the symbols that are defined in the given module in the order they are defined
+    pub fn symbols_in<'a>(&'a self, module: &'a str) -> impl Iterator<Item = &'a Symbol> {
+        self.symbols().filter(move |symbol| symbol.module == module)
+    }
+
+    /// Content of every module file of the crate
+    pub fn all_content(&self) -> String {
+        self.files.concat()
+    }
+
+    /// Resolve the path(e.g `Mint`, `state::Mint`, `super::Mint`) the way it would be resolved
+    /// inside the given module.
+    ///
+    /// Names that can't be resolved by path fall back to the symbol with the same name if there is
+    /// only one.
+    pub fn resolve(&self, path: &str, module: &str) -> Option<&Symbol> {
+        let graph = self.get_graph(module);
+        if !ptr::eq(graph, self) {
+            return graph.resolve(path, module);
+        }
+
+        let path = get_path_without_generics(path)?;
+
+        let symbol = self
+            .get_absolute_path(path, module)
+            .and_then(|path| self.canonicalize(&path, 0))
+            .and_then(|path| self.symbols.get(&path));
+        match symbol {
+            Some(symbol) => Some(symbol),
+            None if !path.contains("::") => self.find_unique(path),
+            None => None,
+        }
+    }
+
+    /// Resolve the path that points to a dependency of the crate e.g `spl_token::state::Account`
+    /// or `Account` if it's imported from `spl_token`
+    pub fn resolve_external(&self, path: &str, module: &str) -> Option<&Symbol> {
+        let graph = self.get_graph(module);
+        if !ptr::eq(graph, self) {
+            return graph.resolve_external(path, module);
+        }
+
+        let path = get_path_without_generics(path)?;
+        let mut paths = self
+            .get_absolute_path(path, module)
+            .and_then(|path| self.canonicalize(&path, 0))
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        // Glob imports from dependencies e.g `use spl_token::state::*;`
+        if !path.contains("::") {
+            if let Some(module) = self.module(module) {
+                paths.extend(
+                    module
+                        .glob_imports
+                        .iter()
+                        .map(|glob| format!("{glob}::{path}")),
+                );
+            }
+        }
+
+        paths.iter().find_map(|path| {
+            let (crate_name, _) = path.split_once("::")?;
+            let symbol = self.get_dependency(crate_name)?.resolve(path, crate_name)?;
+            debug(format!(
+                "Found '{}' in dependency '{crate_name}'.",
+                symbol.path
+            ));
+            Some(symbol)
+        })
+    }
+
+    /// Find the struct or enum with the given name from the crate root, or anywhere in the crate
+    /// if it's not visible from the root
+    pub fn find_type(&self, name: &str) -> Option<&Symbol> {
+        let is_type = |symbol: &&Symbol| matches!(symbol.item, Item::Struct(_) | Item::Enum(_));
+        self.resolve(name, &self.crate_name)
+            .filter(is_type)
+            .or_else(|| {
+                let mut types = self
+                    .symbols()
+                    .filter(|symbol| symbol.name == name)
+                    .filter(is_type);
+                let ty = types.next();
+                if let Some(other) = types.next() {
+                    debug(format!(
+                        "Type '{name}' is defined more than once, using '{}' instead of '{}'.",
+                        ty.unwrap().path,
+                        other.path
+                    ));
+                }
+                ty
+            })
+    }
+
+    fn load_file(
+        &mut self,
+        module_path: String,
+        file_path: PathBuf,
+        dir: PathBuf,
+    ) -> GeneratorResult {
+        let mut content = fs::read_to_string(&file_path)?;
+        let mut file = match syn::parse_file(&content) {
+            Ok(file) => file,
+            Err(e) => {
+                warn(format!("Could not parse {file_path:?}: {e}"));
+                return Ok(());
+            }
+        };
+        self.cfg.strip(&mut file, &mut content);
+        self.files.push(content);
+
+        let docs = get_docs(&file.attrs);
+        self.load_items(module_path, docs, file.items, file_path, dir, false)
+    }
+
+    /// Add the module and load its child modules, `dir` is where the child module files are.
+    ///
+    /// `is_inline` is whether the module is defined with `mod name { .. }`, `#[path]` attributes
+    /// inside inline modules are relative to `dir` instead of the directory of the file.
+    fn load_items(
+        &mut self,
+        module_path: String,
+        docs: Vec<String>,
+        items: Vec<Item>,
+        file_path: PathBuf,
+        dir: PathBuf,
+        is_inline: bool,
+    ) -> GeneratorResult {
+        if self.module_indices.contains_key(&module_path) {
+            return Ok(());
+        }
+
+        let child_mods = items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Mod(item_mod) => Some(item_mod.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        self.module_indices
+            .insert(module_path.clone(), self.modules.len());
+        self.modules.push(Module {
+            path: module_path.clone(),
+            file_path: file_path.clone(),
+            docs,
+            items,
+            imports: HashMap::new(),
+            glob_imports: vec![],
+        });
+
+        for item_mod in child_mods {
+            let name = item_mod.ident.to_string();
+            let child_path = format!("{module_path}::{name}");
+            match item_mod.content {
+                Some((_, items)) => {
+                    let docs = get_docs(&item_mod.attrs);
+                    let dir = dir.join(&name);
+                    self.load_items(child_path, docs, items, file_path.clone(), dir, true)?
+                }
+                None => {
+                    let (child_file_path, child_dir) = match get_path_attr(&item_mod.attrs) {
+                        // Relative to the directory of the current file, or the directory of
+                        // the inline module
+                        Some(path) => {
+                            let path = match is_inline {
+                                true => dir.join(path),
+                                false => file_path.parent().unwrap().join(path),
+                            };
+                            let child_dir = path.parent().unwrap().to_owned();
+                            (path, child_dir)
+                        }
+                        None => {
+                            let path = dir.join(format!("{name}.rs"));
+                            match path.exists() {
+                                true => (path, dir.join(&name)),
+                                false => (dir.join(&name).join(filename::MOD), dir.join(&name)),
+                            }
+                        }
+                    };
+
+                    if !child_file_path.exists() {
+                        warn(format!(
+                            "Could not find the file of module '{child_path}', expected {child_file_path:?}."
+                        ));
+                        continue;
+                    }
+
+                    self.load_file(child_path, child_file_path, child_dir)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn resolve_imports(&mut self) {
+        for i in 0..self.modules.len() {
+            let mut uses = vec![];
+            for item in &self.modules[i].items {
+                if let Item::Use(item_use) = item {
+                    let prefix = match item_use.leading_colon {
+                        Some(_) => vec!["".to_owned()],
+                        None => vec![],
+                    };
+                    collect_use_tree(&item_use.tree, prefix, &mut uses);
+                }
+            }
+
+            let module_path = self.modules[i].path.clone();
+            for (alias, segments) in uses {
+                let path = match self.get_import_path(&segments, &module_path) {
+                    Some(path) => path,
+                    None => continue,
+                };
+                let module = &mut self.modules[i];
+                match alias {
+                    Some(alias) => {
+                        module.imports.insert(alias, path);
+                    }
+                    None => module.glob_imports.push(path),
+                }
+            }
+        }
+    }
+
+    /// Get the absolute path of the `use` path segments
+    fn get_import_path(&self, segments: &[String], module: &str) -> Option<String> {
+        let (first, rest) = segments.split_first()?;
+        let base = match first.as_str() {
+            // `use ::spl_token::..`
+            "" => return Some(rest.join("::")),
+            "crate" | "self" | "super" => {
+                return self.get_absolute_path(&segments.join("::"), module)
+            }
+            name if self.is_defined_in(name, module) => format!("{module}::{name}"),
+            // 2015 edition paths are relative to the crate root
+            name if self.is_defined_in(name, &self.crate_name) => {
+                format!("{}::{name}", self.crate_name)
+            }
+            name => name.to_owned(),
+        };
+
+        Some(
+            std::iter::once(base)
+                .chain(rest.iter().cloned())
+                .collect::<Vec<_>>()
+                .join("::"),
+        )
+    }
+
+    /// Whether the module declares an item or a child module with the given name
+    fn is_defined_in(&self, name: &str, module: &str) -> bool {
+        self.module_indices
+            .contains_key(&format!("{module}::{name}"))
+            || self
+                .module(module)
+                .map(|module| {
+                    module
+                        .items
+                        .iter()
+                        .any(|item| get_ident(item).is_some_and(|ident| ident == name))
+                })
+                .unwrap_or(false)
+    }
+
+    fn create_symbols(&mut self) {
+        for module in &self.modules {
+            for item in &module.items {
+                if let Some(ident) = get_ident(item) {
+                    let name = ident.to_string();
+                    let path = format!("{}::{name}", module.path);
+                    self.symbol_paths.push(path.clone());
+                    self.symbols.insert(
+                        path.clone(),
+                        Symbol {
+                            path,
+                            name,
+                            module: module.path.clone(),
+                            item: item.clone(),
+                        },
+                    );
+                }
+            }
+        }
+
+        // Associated constants e.g `impl Pack for Mint { const LEN: usize = 82; }`, types need to
+        // be resolved first in order to know which type the implementation is for
+        let mut associated_consts = vec![];
+        for module in &self.modules {
+            for item in &module.items {
+                let item_impl = match item {
+                    Item::Impl(item_impl) => item_impl,
+                    _ => continue,
+                };
+                let self_path = match &*item_impl.self_ty {
+                    Type::Path(type_path) => {
+                        tokens_to_string(type_path.path.to_token_stream().to_string())
+                    }
+                    _ => continue,
+                };
+                let self_path = match self.resolve(&self_path, &module.path) {
+                    Some(symbol) => symbol.path.clone(),
+                    None => continue,
+                };
+
+                for impl_item in &item_impl.items {
+                    if let ImplItem::Const(impl_const) = impl_item {
+                        let item = Item::Const(ItemConst {
+                            attrs: impl_const.attrs.clone(),
+                            vis: impl_const.vis.clone(),
+                            const_token: impl_const.const_token,
+                            ident: impl_const.ident.clone(),
+                            colon_token: impl_const.colon_token,
+                            ty: Box::new(impl_const.ty.clone()),
+                            eq_token: impl_const.eq_token,
+                            expr: Box::new(impl_const.expr.clone()),
+                            semi_token: impl_const.semi_token,
+                        });
+                        associated_consts.push(Symbol {
+                            path: format!("{self_path}::{}", impl_const.ident),
+                            name: impl_const.ident.to_string(),
+                            module: module.path.clone(),
+                            item,
+                        });
+                    }
+                }
+            }
+        }
+
+        for symbol in associated_consts {
+            if !self.symbols.contains_key(&symbol.path) {
+                self.symbol_paths.push(symbol.path.clone());
+                self.symbols.insert(symbol.path.clone(), symbol);
+            }
+        }
+    }
+
+    /// Get the canonical path of the module or the symbol the absolute path points to by following
+    /// the imports, e.g `crate::Mint` -> `crate::state::Mint` if `lib.rs` has `pub use state::Mint;`
+    fn canonicalize(&self, path: &str, depth: usize) -> Option<String> {
+        if depth > MAX_RESOLVE_DEPTH {
+            return None;
+        }
+        if self.symbols.contains_key(path) || self.module_indices.contains_key(path) {
+            return Some(path.to_owned());
+        }
+
+        // Paths of dependencies are resolved by the dependency
+        let crate_name = path.split("::").next().unwrap();
+        if crate_name != self.crate_name {
+            return self
+                .dependencies
+                .contains_key(crate_name)
+                .then(|| path.to_owned());
+        }
+
+        let (parent, name) = path.rsplit_once("::")?;
+        let parent = self.canonicalize(parent, depth + 1)?;
+        let path = format!("{parent}::{name}");
+        if self.symbols.contains_key(&path) || self.module_indices.contains_key(&path) {
+            return Some(path);
+        }
+
+        let module = self.module(&parent)?;
+        if let Some(import) = module.imports.get(name) {
+            return self.canonicalize(import, depth + 1);
+        }
+        // Only local globs, dependency globs can't be checked without loading the dependency
+        module
+            .glob_imports
+            .iter()
+            .filter(|glob| glob.starts_with(&self.crate_name))
+            .find_map(|glob| {
+                self.canonicalize(&format!("{glob}::{name}"), depth + 1)
+                    .filter(|path| path.starts_with(&self.crate_name))
+            })
+    }
+
+    /// Get the dependency with the given crate name, the dependency is loaded the first time it's
+    /// used with its default features
+    fn get_dependency(&self, crate_name: &str) -> Option<&ModuleGraph> {
+        let dependency = self.dependencies.get(crate_name)?;
+        dependency
+            .modules
+            .get_or_init(|| {
+                let src_path = dependency.path.join(dirname::SRC);
+                let cfg = CfgContext::new(&dependency.path.join(filename::CARGO_TOML), &[], true);
+                match Self::load(crate_name, src_path, cfg) {
+                    Ok(modules) => Some(modules),
+                    Err(e) => {
+                        warn(format!("Could not load dependency '{crate_name}': {e}"));
+                        None
+                    }
+                }
+            })
+            .as_ref()
+    }
+
+    /// Get the graph the module belongs to, the module can be inside a dependency or a dependency
+    /// of a dependency
+    fn get_graph(&self, module: &str) -> &ModuleGraph {
+        let crate_name = module.split("::").next().unwrap();
+        if crate_name == self.crate_name {
+            return self;
+        }
+        if let Some(graph) = self.get_dependency(crate_name) {
+            return graph;
+        }
+
+        self.dependencies
+            .values()
+            .filter_map(|dependency| dependency.modules.get()?.as_ref())
+            .map(|graph| graph.get_graph(module))
+            .find(|graph| graph.crate_name == crate_name)
+            .unwrap_or(self)
+    }
+
+    /// Convert the path that is relative to the module to an absolute path
+    fn get_absolute_path(&self, path: &str, module: &str) -> Option<String> {
+        let mut segments = path.split("::").map(str::trim);
+        let first = segments.next()?;
+        let rest = segments.collect::<Vec<_>>();
+
+        let base = match first {
+            "crate" => self.crate_name.to_owned(),
+            name if name == self.crate_name => name.to_owned(),
+            "self" => module.to_owned(),
+            "super" => {
+                let mut base = module.rsplit_once("::")?.0.to_owned();
+                let mut rest = rest.as_slice();
+                while let Some((&"super", remaining)) = rest.split_first() {
+                    base = base.rsplit_once("::")?.0.to_owned();
+                    rest = remaining;
+                }
+                return Some(
+                    std::iter::once(base.as_str())
+                        .chain(rest.iter().copied())
+                        .collect::<Vec<_>>()
+                        .join("::"),
+                );
+            }
+            name => {
+                let local = format!("{module}::{name}");
+                let root = format!("{}::{name}", self.crate_name);
+                if self.canonicalize(&local, 0).is_some() {
+                    local
+                } else if self.canonicalize(&root, 0).is_some() {
+                    root
+                } else {
+                    // External crate
+                    name.to_owned()
+                }
+            }
+        };
+
+        Some(
+            std::iter::once(base.as_str())
+                .chain(rest)
+                .collect::<Vec<_>>()
+                .join("::"),
+        )
+    }
+
+    /// Find the symbol with the given name if there is only one symbol with that name
+    fn find_unique(&self, name: &str) -> Option<&Symbol> {
+        let mut symbols = self.symbols.values().filter(|symbol| symbol.name == name);
+        match (symbols.next(), symbols.next()) {
+            (Some(symbol), None) => {
+                debug(format!(
+                    "'{name}' is not imported, using '{}'.",
+                    symbol.path
+                ));
+                Some(symbol)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Remove the generics and the leading `::` from the path e.g `::foo::Wrapper<T>` -> `foo::Wrapper`
+fn get_path_without_generics(path: &str) -> Option<&str> {
+    let path = path
+        .split('<')
+        .next()
+        .unwrap()
+        .trim()
+        .trim_start_matches("::");
+    match path.is_empty() {
+        true => None,
+        false => Some(path),
+    }
+}
+
+/// Collect `use` paths as (alias, path segments), glob imports don't have an alias
+fn collect_use_tree(
+    tree: &UseTree,
+    mut prefix: Vec<String>,
+    uses: &mut Vec<(Option<String>, Vec<String>)>,
+) {
+    match tree {
+        UseTree::Path(use_path) => {
+            prefix.push(use_path.ident.to_string());
+            collect_use_tree(&use_path.tree, prefix, uses);
+        }
+        UseTree::Name(use_name) if use_name.ident == "self" => {
+            if let Some(alias) = prefix.last().cloned() {
+                uses.push((Some(alias), prefix));
+            }
+        }
+        UseTree::Name(use_name) => {
+            let alias = use_name.ident.to_string();
+            prefix.push(alias.clone());
+            uses.push((Some(alias), prefix));
+        }
+        UseTree::Rename(use_rename) if use_rename.rename != "_" => {
+            if use_rename.ident != "self" {
+                prefix.push(use_rename.ident.to_string());
+            }
+            uses.push((Some(use_rename.rename.to_string()), prefix));
+        }
+        UseTree::Rename(_) => {}
+        UseTree::Glob(_) => uses.push((None, prefix)),
+        UseTree::Group(use_group) => {
+            for tree in &use_group.items {
+                collect_use_tree(tree, prefix.clone(), uses);
+            }
+        }
+    }
+}
+
+fn get_ident(item: &Item) -> Option<&Ident> {
+    match item {
+        Item::Struct(item_struct) => Some(&item_struct.ident),
+        Item::Enum(item_enum) => Some(&item_enum.ident),
+        Item::Const(item_const) => Some(&item_const.ident),
+        Item::Static(item_static) => Some(&item_static.ident),
+        Item::Type(item_type) => Some(&item_type.ident),
+        Item::Fn(item_fn) => Some(&item_fn.sig.ident),
+        Item::Trait(item_trait) => Some(&item_trait.ident),
+        Item::Union(item_union) => Some(&item_union.ident),
+        _ => None,
+    }
+}
+
+/// Get the value of `#[path = "..."]` attribute
+fn get_path_attr(attrs: &[Attribute]) -> Option<String> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("path"))
+        .find_map(|attr| match attr.parse_meta() {
+            Ok(Meta::NameValue(name_value)) => match name_value.lit {
+                Lit::Str(path) => Some(path.value()),
+                _ => None,
+            },
+            _ => None,
+        })
+}
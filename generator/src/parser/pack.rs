@@ -0,0 +1,632 @@
+use std::collections::HashMap;
+
+use proc_macro2::{TokenStream, TokenTree};
+use quote::ToTokens;
+use syn::{
+    visit::{self, Visit},
+    Arm, Block, Expr, ExprCall, ExprMatch, ExprStruct, FnArg, GenericArgument, ImplItem, Item,
+    ItemEnum, Local, Member, Pat, PathArguments, ReturnType, Signature, Type,
+};
+
+use super::{
+    common::{parse_int, tokens_to_string, type_to_string},
+    module::{Module, ModuleGraph},
+};
+use crate::constants::idl::{DESERIALIZE_FNS, INT_TYPES, SERIALIZE_FNS};
+
+/// Byte layouts of the instructions of a manually (un)packed instruction enum
+#[derive(Default)]
+pub struct PackLayouts {
+    /// Type of the tag that comes before the fields e.g `u8` for
+    /// `let (&tag, rest) = input.split_first()?;` or `buf.push(0);`
+    pub tag_type: Option<Type>,
+    /// Variant name -> layout
+    pub variants: HashMap<String, PackLayout>,
+}
+
+/// Byte layout of an instruction that is (un)packed manually e.g `TokenInstruction::unpack`
+pub struct PackLayout {
+    /// Tag of the instruction e.g `0` for `0 => Self::InitializeMint {..}`
+    pub tag: Option<u64>,
+    /// Fields in the order they are in the instruction data
+    pub fields: Vec<PackField>,
+}
+
+pub struct PackField {
+    /// Field name for named variants, index for tuple variants
+    pub member: Member,
+    /// Type of the field in the instruction data if it could be inferred e.g `u64` for
+    /// `u64::from_le_bytes`
+    pub ty: Option<Type>,
+}
+
+/// Function that is defined in the impl blocks of the enum or in the enum's module
+struct PackFn<'a> {
+    sig: &'a Signature,
+    block: &'a Block,
+}
+
+/// Get the byte layouts of the variants from the `unpack` and `pack` functions of the enum.
+///
+/// Variants that are not (un)packed inside a `match` on the tag are not included.
+pub fn get_pack_layouts(
+    item_enum: &ItemEnum,
+    module: &Module,
+    modules: &ModuleGraph,
+) -> PackLayouts {
+    let enum_name = item_enum.ident.to_string();
+    let enum_path = format!("{}::{enum_name}", module.path);
+
+    // Methods of the enum and the functions of the module, helpers are usually defined in them
+    let mut fns = module
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Fn(item_fn) => Some(PackFn {
+                sig: &item_fn.sig,
+                block: &item_fn.block,
+            }),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    fns.extend(
+        modules
+            .impls()
+            .filter(|(_, _, symbol)| matches!(symbol, Some(symbol) if symbol.path == enum_path))
+            .flat_map(|(_, item_impl, _)| &item_impl.items)
+            .filter_map(|impl_item| match impl_item {
+                ImplItem::Method(method) => Some(PackFn {
+                    sig: &method.sig,
+                    block: &method.block,
+                }),
+                _ => None,
+            }),
+    );
+    let helpers = fns
+        .iter()
+        .map(|f| (f.sig.ident.to_string(), f))
+        .collect::<HashMap<_, _>>();
+    let variant_names = item_enum
+        .variants
+        .iter()
+        .map(|variant| variant.ident.to_string())
+        .collect::<Vec<_>>();
+    let ctx = PackContext {
+        enum_name: &enum_name,
+        variant_names: &variant_names,
+        helpers: &helpers,
+    };
+
+    let mut layouts = PackLayouts::default();
+    for f in fns
+        .iter()
+        .filter(|f| SERIALIZE_FNS.contains(&f.sig.ident.to_string().as_str()))
+    {
+        layouts.variants.extend(ctx.get_pack_layouts(f.block));
+    }
+    // Tags are pushed as a single byte e.g `buf.push(0);`
+    if layouts.variants.values().any(|layout| layout.tag.is_some()) {
+        layouts.tag_type = syn::parse_str("u8").ok();
+    }
+
+    // `unpack` is preferred because it shows the types that are read
+    for f in fns
+        .iter()
+        .filter(|f| DESERIALIZE_FNS.contains(&f.sig.ident.to_string().as_str()))
+    {
+        layouts.variants.extend(ctx.get_unpack_layouts(f.block));
+        if let Some(tag_type) = ctx.get_unpack_tag_type(f.block) {
+            layouts.tag_type = Some(tag_type);
+        }
+    }
+
+    layouts
+}
+
+struct PackContext<'a> {
+    enum_name: &'a str,
+    variant_names: &'a [String],
+    helpers: &'a HashMap<String, &'a PackFn<'a>>,
+}
+
+impl PackContext<'_> {
+    /// Get the layouts from the `match tag {..}` of the unpack function e.g
+    ///
+    /// ```ignore
+    /// 0 => {
+    ///     let (&decimals, rest) = rest.split_first().ok_or(InvalidInstruction)?;
+    ///     let (mint_authority, _rest) = Self::unpack_pubkey(rest)?;
+    ///     Self::InitializeMint { mint_authority, decimals }
+    /// }
+    /// ```
+    fn get_unpack_layouts(&self, block: &Block) -> HashMap<String, PackLayout> {
+        let mut layouts = HashMap::new();
+        let tag_match = match find_tag_match(block) {
+            Some(tag_match) => tag_match,
+            None => return layouts,
+        };
+
+        for arm in &tag_match.arms {
+            // Values in the order they are read from the data
+            let mut values = vec![];
+            for local in get_locals(&arm.body) {
+                let name = match get_value_name(&local.pat) {
+                    Some(name) => name,
+                    None => continue,
+                };
+                let ty = local
+                    .init
+                    .as_ref()
+                    .and_then(|(_, init)| self.get_read_type(init));
+                values.push((name, ty));
+            }
+
+            for construction in self.get_constructions(arm) {
+                let mut fields = construction
+                    .values
+                    .into_iter()
+                    .filter_map(|(member, value_idents)| {
+                        values
+                            .iter()
+                            .position(|(name, _)| value_idents.contains(name))
+                            .map(|position| (position, member))
+                    })
+                    .collect::<Vec<_>>();
+                fields.sort_by_key(|(position, _)| *position);
+
+                layouts.insert(
+                    construction.variant,
+                    PackLayout {
+                        tag: construction.tag,
+                        fields: fields
+                            .into_iter()
+                            .map(|(position, member)| PackField {
+                                member,
+                                ty: values[position].1.clone(),
+                            })
+                            .collect(),
+                    },
+                );
+            }
+        }
+
+        layouts
+    }
+
+    /// Get the type of the tag that is matched in the unpack function e.g `u8` for
+    ///
+    /// ```ignore
+    /// let (&tag, rest) = input.split_first().ok_or(InvalidInstruction)?;
+    /// match tag { .. }
+    /// ```
+    fn get_unpack_tag_type(&self, block: &Block) -> Option<Type> {
+        let tag_match = find_tag_match(block)?;
+        let mut scrutinee = tag_match.expr.as_ref();
+        while let Expr::Unary(expr_unary) = scrutinee {
+            scrutinee = &expr_unary.expr;
+        }
+
+        // The tag is read in the `match` e.g `match input[0]`
+        let name = match scrutinee {
+            Expr::Path(expr_path) => expr_path.path.get_ident()?.to_string(),
+            scrutinee => return self.get_read_type(scrutinee),
+        };
+        get_block_locals(block)
+            .into_iter()
+            .filter(|local| get_value_name(&local.pat).as_deref() == Some(name.as_str()))
+            .find_map(|local| self.get_read_type(&local.init.as_ref()?.1))
+    }
+
+    /// Get the layouts from the `match self {..}` of the pack function e.g
+    ///
+    /// ```ignore
+    /// &Self::InitializeMint { ref mint_authority, decimals } => {
+    ///     buf.push(0);
+    ///     buf.push(decimals);
+    ///     buf.extend_from_slice(mint_authority.as_ref());
+    /// }
+    /// ```
+    fn get_pack_layouts(&self, block: &Block) -> HashMap<String, PackLayout> {
+        let mut layouts = HashMap::new();
+        let self_match = match find_match(block, |expr_match| {
+            expr_match
+                .arms
+                .iter()
+                .any(|arm| self.get_pat_variant(&arm.pat).is_some())
+        }) {
+            Some(self_match) => self_match,
+            None => return layouts,
+        };
+
+        for arm in &self_match.arms {
+            let (variant, bindings) = match self.get_pat_variant(&arm.pat) {
+                Some(pat_variant) => pat_variant,
+                None => continue,
+            };
+
+            let stmts = match arm.body.as_ref() {
+                Expr::Block(expr_block) => expr_block.block.stmts.iter().collect(),
+                _ => vec![],
+            };
+            let body_tokens = match stmts.as_slice() {
+                [] => vec![arm.body.to_token_stream()],
+                _ => stmts.iter().map(|stmt| stmt.to_token_stream()).collect(),
+            };
+
+            let mut tag = None;
+            let mut fields: Vec<PackField> = vec![];
+            for tokens in body_tokens {
+                let idents = get_idents(tokens.clone());
+                let binding = bindings
+                    .iter()
+                    .find(|(_, name)| idents.contains(name))
+                    .filter(|(member, _)| !fields.iter().any(|field| &field.member == member));
+                match binding {
+                    Some((member, name)) => fields.push(PackField {
+                        member: member.clone(),
+                        ty: self.get_write_type(&tokens, name),
+                    }),
+                    None if tag.is_none() && fields.is_empty() => tag = get_pushed_tag(&tokens),
+                    None => {}
+                }
+            }
+
+            layouts.insert(variant, PackLayout { tag, fields });
+        }
+
+        layouts
+    }
+
+    /// Get the variant that is constructed and the fields' values, tags come from the literal
+    /// patterns of the arms e.g `3 => Self::Transfer { amount }`
+    fn get_constructions(&self, arm: &Arm) -> Vec<Construction> {
+        struct ConstructionVisitor<'a, 'b> {
+            ctx: &'b PackContext<'a>,
+            tags: Vec<u64>,
+            constructions: Vec<Construction>,
+        }
+
+        impl<'ast> Visit<'ast> for ConstructionVisitor<'_, '_> {
+            fn visit_arm(&mut self, arm: &'ast Arm) {
+                let tags = get_pat_tags(&arm.pat);
+                if tags.is_empty() {
+                    return visit::visit_arm(self, arm);
+                }
+
+                let outer_tags = std::mem::replace(&mut self.tags, tags);
+                visit::visit_arm(self, arm);
+                self.tags = outer_tags;
+            }
+
+            fn visit_expr_struct(&mut self, expr_struct: &'ast ExprStruct) {
+                visit::visit_expr_struct(self, expr_struct);
+                if let Some(variant) = self.ctx.get_path_variant(&expr_struct.path) {
+                    self.constructions.push(Construction {
+                        variant,
+                        tag: self.tag(),
+                        values: expr_struct
+                            .fields
+                            .iter()
+                            .map(|field| {
+                                (
+                                    field.member.clone(),
+                                    get_idents(field.expr.to_token_stream()),
+                                )
+                            })
+                            .collect(),
+                    });
+                }
+            }
+
+            fn visit_expr_call(&mut self, call: &'ast ExprCall) {
+                visit::visit_expr_call(self, call);
+                let variant = match call.func.as_ref() {
+                    Expr::Path(expr_path) => self.ctx.get_path_variant(&expr_path.path),
+                    _ => None,
+                };
+                if let Some(variant) = variant {
+                    self.constructions.push(Construction {
+                        variant,
+                        tag: self.tag(),
+                        values: call
+                            .args
+                            .iter()
+                            .enumerate()
+                            .map(|(i, arg)| (Member::from(i), get_idents(arg.to_token_stream())))
+                            .collect(),
+                    });
+                }
+            }
+        }
+
+        impl ConstructionVisitor<'_, '_> {
+            fn tag(&self) -> Option<u64> {
+                match self.tags.as_slice() {
+                    [tag] => Some(*tag),
+                    _ => None,
+                }
+            }
+        }
+
+        let mut visitor = ConstructionVisitor {
+            ctx: self,
+            tags: vec![],
+            constructions: vec![],
+        };
+        visitor.visit_arm(arm);
+        visitor.constructions
+    }
+
+    /// Get the variant name if the path is a variant of the enum e.g `Self::Transfer`
+    fn get_path_variant(&self, path: &syn::Path) -> Option<String> {
+        let segments = path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect::<Vec<_>>();
+        match segments.as_slice() {
+            [.., parent, variant]
+                if (parent == "Self" || parent == self.enum_name)
+                    && self.variant_names.contains(variant) =>
+            {
+                Some(variant.to_owned())
+            }
+            _ => None,
+        }
+    }
+
+    /// Get the variant and the bound names of its fields from the pattern e.g
+    /// `&Self::Transfer { amount }`
+    fn get_pat_variant(&self, pat: &Pat) -> Option<(String, Vec<(Member, String)>)> {
+        match pat {
+            Pat::Reference(pat_ref) => self.get_pat_variant(&pat_ref.pat),
+            Pat::Struct(pat_struct) => Some((
+                self.get_path_variant(&pat_struct.path)?,
+                pat_struct
+                    .fields
+                    .iter()
+                    .filter_map(|field| Some((field.member.clone(), get_binding_name(&field.pat)?)))
+                    .collect(),
+            )),
+            Pat::TupleStruct(pat_tuple_struct) => Some((
+                self.get_path_variant(&pat_tuple_struct.path)?,
+                pat_tuple_struct
+                    .pat
+                    .elems
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, pat)| Some((Member::from(i), get_binding_name(pat)?)))
+                    .collect(),
+            )),
+            Pat::Path(pat_path) => Some((self.get_path_variant(&pat_path.path)?, vec![])),
+            _ => None,
+        }
+    }
+
+    /// Get the type that is read from the data by the expression
+    fn get_read_type(&self, expr: &Expr) -> Option<Type> {
+        let expr_str = tokens_to_string(expr.to_token_stream().to_string());
+
+        if let Some(ty) = INT_TYPES
+            .iter()
+            .find(|ty| expr_str.contains(&format!("{ty}::from_le_bytes")))
+        {
+            return syn::parse_str(ty).ok();
+        }
+
+        let helper_ty = get_idents(expr.to_token_stream())
+            .iter()
+            .filter_map(|ident| self.helpers.get(ident))
+            .find_map(|helper| Some((helper, get_unpacked_type(helper.sig)?)));
+        if let Some((helper, ty)) = helper_ty {
+            return Some(byte_tagged_option(ty, helper));
+        }
+
+        let reads_byte = ["split_first", "get(0)", "first()", "[0]"]
+            .iter()
+            .any(|read| expr_str.contains(read));
+        match reads_byte {
+            true => syn::parse_str("u8").ok(),
+            false => None,
+        }
+    }
+
+    /// Get the type that is written to the data by the statement
+    fn get_write_type(&self, tokens: &TokenStream, name: &str) -> Option<Type> {
+        let stmt_str = tokens_to_string(tokens.to_string());
+
+        let helper_ty = get_idents(tokens.clone())
+            .iter()
+            .filter_map(|ident| self.helpers.get(ident))
+            .find_map(|helper| {
+                let param = helper.sig.inputs.iter().find_map(|input| match input {
+                    FnArg::Typed(pat_type) => Some(pat_type),
+                    FnArg::Receiver(_) => None,
+                })?;
+                Some((helper, strip_reference(&param.ty)))
+            });
+        if let Some((helper, ty)) = helper_ty {
+            return Some(byte_tagged_option(ty, helper));
+        }
+
+        match stmt_str.contains(&format!("push({name})")) {
+            true => syn::parse_str("u8").ok(),
+            false => None,
+        }
+    }
+}
+
+/// Variant construction e.g `Self::Transfer { amount }`
+struct Construction {
+    variant: String,
+    tag: Option<u64>,
+    /// Fields and the identifiers used in their values
+    values: Vec<(Member, Vec<String>)>,
+}
+
+/// Find the first `match` that has integer literal patterns e.g `match tag { 0 => .. }`
+fn find_tag_match(block: &Block) -> Option<&ExprMatch> {
+    find_match(block, |expr_match| {
+        expr_match
+            .arms
+            .iter()
+            .any(|arm| !get_pat_tags(&arm.pat).is_empty())
+    })
+}
+
+/// Find the first `match` expression that satisfies the predicate
+fn find_match(block: &Block, predicate: impl Fn(&ExprMatch) -> bool) -> Option<&ExprMatch> {
+    struct MatchVisitor<'ast, P> {
+        predicate: P,
+        found: Option<&'ast ExprMatch>,
+    }
+
+    impl<'ast, P: Fn(&ExprMatch) -> bool> Visit<'ast> for MatchVisitor<'ast, P> {
+        fn visit_expr_match(&mut self, expr_match: &'ast ExprMatch) {
+            if self.found.is_some() {
+                return;
+            }
+            if (self.predicate)(expr_match) {
+                self.found = Some(expr_match);
+                return;
+            }
+            visit::visit_expr_match(self, expr_match);
+        }
+    }
+
+    let mut visitor = MatchVisitor {
+        predicate,
+        found: None,
+    };
+    visitor.visit_block(block);
+    visitor.found
+}
+
+/// Get the `let` statements in the order they are defined
+fn get_locals(expr: &Expr) -> Vec<&Local> {
+    let mut visitor = LocalVisitor(vec![]);
+    visitor.visit_expr(expr);
+    visitor.0
+}
+
+/// Get the `let` statements of the block in the order they are defined
+fn get_block_locals(block: &Block) -> Vec<&Local> {
+    let mut visitor = LocalVisitor(vec![]);
+    visitor.visit_block(block);
+    visitor.0
+}
+
+struct LocalVisitor<'ast>(Vec<&'ast Local>);
+
+impl<'ast> Visit<'ast> for LocalVisitor<'ast> {
+    fn visit_local(&mut self, local: &'ast Local) {
+        self.0.push(local);
+        visit::visit_local(self, local);
+    }
+
+    // Closures have their own scope
+    fn visit_expr_closure(&mut self, _: &'ast syn::ExprClosure) {}
+}
+
+/// Get the integer literals of the pattern e.g `[3, 4]` for `3 | 4`
+fn get_pat_tags(pat: &Pat) -> Vec<u64> {
+    match pat {
+        Pat::Lit(pat_lit) => parse_int(&pat_lit.expr.to_token_stream().to_string())
+            .into_iter()
+            .collect(),
+        Pat::Or(pat_or) => pat_or.cases.iter().flat_map(get_pat_tags).collect(),
+        _ => vec![],
+    }
+}
+
+/// Get the name of the value that is read by the `let` statement e.g `amount` for
+/// `let (amount, rest) = Self::unpack_u64(rest)?;`
+fn get_value_name(pat: &Pat) -> Option<String> {
+    match pat {
+        Pat::Tuple(pat_tuple) => pat_tuple.elems.first().and_then(get_binding_name),
+        Pat::Type(pat_type) => get_value_name(&pat_type.pat),
+        pat => get_binding_name(pat),
+    }
+}
+
+/// Get the bound name of the pattern e.g `decimals` for `&decimals` or `ref decimals`
+fn get_binding_name(pat: &Pat) -> Option<String> {
+    match pat {
+        Pat::Ident(pat_ident) => Some(pat_ident.ident.to_string()),
+        Pat::Reference(pat_ref) => get_binding_name(&pat_ref.pat),
+        Pat::Type(pat_type) => get_binding_name(&pat_type.pat),
+        _ => None,
+    }
+}
+
+/// Get the tag that is written before the fields e.g `0` for `buf.push(0);`
+fn get_pushed_tag(tokens: &TokenStream) -> Option<u64> {
+    let stmt_str = tokens_to_string(tokens.to_string());
+    let start = stmt_str.find("push(")? + "push(".len();
+    let end = start + stmt_str[start..].find(')')?;
+    parse_int(&stmt_str[start..end])
+}
+
+/// Get the type that is returned with the rest of the data e.g `Pubkey` for
+/// `fn unpack_pubkey(input: &[u8]) -> Result<(Pubkey, &[u8]), ProgramError>`
+fn get_unpacked_type(sig: &Signature) -> Option<Type> {
+    let mut ty = match &sig.output {
+        ReturnType::Type(_, ty) => ty.as_ref(),
+        ReturnType::Default => return None,
+    };
+
+    // Unwrap `Result` and `Option`
+    if let Type::Path(type_path) = ty {
+        let segment = type_path.path.segments.last()?;
+        if segment.ident == "Result" || segment.ident == "Option" {
+            ty = match &segment.arguments {
+                PathArguments::AngleBracketed(args) => match args.args.first()? {
+                    GenericArgument::Type(ty) => ty,
+                    _ => return None,
+                },
+                _ => return None,
+            };
+        }
+    }
+
+    match ty {
+        Type::Tuple(type_tuple) if type_tuple.elems.len() == 2 => {
+            type_tuple.elems.first().map(strip_reference)
+        }
+        _ => None,
+    }
+}
+
+/// Values are copied from the references e.g `[u8; 32]` for `&[u8; 32]`
+fn strip_reference(ty: &Type) -> Type {
+    match ty {
+        Type::Reference(type_ref) => strip_reference(&type_ref.elem),
+        ty => ty.clone(),
+    }
+}
+
+/// `COption` is (un)packed with a 1 byte tag in instruction helpers e.g `unpack_pubkey_option`,
+/// which is the same as Borsh `Option`
+fn byte_tagged_option(ty: Type, helper: &PackFn) -> Type {
+    let ty_str = type_to_string(&ty);
+    let body = tokens_to_string(helper.block.to_token_stream().to_string());
+    let is_byte_tagged = ["split_first", "push(0)", "push(1)"]
+        .iter()
+        .any(|op| body.contains(op));
+    match ty_str.strip_prefix("COption") {
+        Some(inner) if is_byte_tagged => syn::parse_str(&format!("Option{inner}")).unwrap_or(ty),
+        _ => ty,
+    }
+}
+
+/// Get all identifiers inside the tokens
+fn get_idents(tokens: TokenStream) -> Vec<String> {
+    tokens
+        .into_iter()
+        .flat_map(|token| match token {
+            TokenTree::Ident(ident) => vec![ident.to_string()],
+            TokenTree::Group(group) => get_idents(group.stream()),
+            _ => vec![],
+        })
+        .collect()
+}
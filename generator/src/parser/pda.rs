@@ -0,0 +1,297 @@
+use quote::ToTokens;
+use syn::{
+    visit::{self, Visit},
+    Block, Expr, ExprCall, FnArg, Item, Lit, Pat, Signature,
+};
+
+use super::{builder::find_fn, common::tokens_to_string, module::ModuleGraph};
+
+/// Maximum amount of helper functions to follow while finding the address derivation
+const MAX_PDA_DEPTH: usize = 4;
+
+/// Methods that convert the seed to bytes without changing them e.g `mint.as_ref()`
+const SEED_METHODS: [&str; 6] = [
+    "as_ref",
+    "as_bytes",
+    "to_bytes",
+    "to_le_bytes",
+    "to_vec",
+    "key",
+];
+
+/// Program derived address of an account e.g
+/// `Pubkey::find_program_address(&[b"vault", owner.as_ref()], program_id)`
+#[derive(Clone, Debug)]
+pub struct Pda {
+    pub seeds: Vec<PdaSeed>,
+    /// Program that the address is derived from e.g `program_id` or `spl_token::id()`
+    pub program_id: PdaSeed,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PdaSeed {
+    /// Constant bytes e.g `b"vault"` or `VAULT_SEED.as_bytes()`
+    Const(Vec<u8>),
+    /// Account of the instruction, index of the account
+    Account(usize),
+    /// Variable or path that the seed comes from e.g `owner` in `owner.key.as_ref()`, it's
+    /// resolved to an account or an instruction argument with the same name
+    Var(String),
+}
+
+impl Pda {
+    /// Whether the address is derived from the program itself
+    pub fn is_program_derived(&self) -> bool {
+        match &self.program_id {
+            PdaSeed::Var(var) => {
+                let var = var
+                    .trim_start_matches("crate::")
+                    .trim_start_matches("self::");
+                matches!(var, "program_id" | "id()" | "ID")
+            }
+            _ => false,
+        }
+    }
+
+    /// Replace the variables of the seeds e.g the parameters of a helper function with the
+    /// arguments it's called with, seeds that can't be replaced are kept
+    pub fn map_vars(self, f: impl Fn(&str) -> Option<PdaSeed>) -> Self {
+        let map = |seed: PdaSeed| match &seed {
+            PdaSeed::Var(var) => f(var).unwrap_or(seed),
+            _ => seed,
+        };
+        Self {
+            seeds: self.seeds.into_iter().map(map).collect(),
+            program_id: map(self.program_id),
+        }
+    }
+}
+
+/// Get the address derivation of the expression, either a `find_program_address` call or a call
+/// to a function of the crate that derives the address e.g `get_vault_address(owner, program_id)`.
+///
+/// Seeds are in terms of the variables that are given to the function, returns `None` if the
+/// expression is not a derivation or a seed can't be parsed.
+pub fn get_pda(expr: &Expr, modules: &ModuleGraph, module: &str) -> Option<Pda> {
+    get_pda_with_depth(expr, modules, module, 0)
+}
+
+/// Get the name of the variable that the address is bound to e.g `let (vault, _bump) = ..` ->
+/// `vault`
+pub fn get_pda_binding(pat: &Pat) -> Option<String> {
+    match pat {
+        Pat::Ident(pat_ident) => Some(pat_ident.ident.to_string()),
+        Pat::Type(pat_type) => get_pda_binding(&pat_type.pat),
+        Pat::Tuple(pat_tuple) => pat_tuple.elems.first().and_then(get_pda_binding),
+        _ => None,
+    }
+}
+
+fn get_pda_with_depth(
+    expr: &Expr,
+    modules: &ModuleGraph,
+    module: &str,
+    depth: usize,
+) -> Option<Pda> {
+    let call = match strip_expr(expr) {
+        Expr::Call(call) => call,
+        _ => return None,
+    };
+    let func = tokens_to_string(call.func.to_token_stream().to_string());
+    let fn_name = func.rsplit("::").next().unwrap_or_default().trim();
+
+    if matches!(fn_name, "find_program_address" | "try_find_program_address") {
+        let mut args = call.args.iter();
+        let seeds = match strip_expr(args.next()?) {
+            Expr::Array(array) => array
+                .elems
+                .iter()
+                .map(|seed| parse_seed(seed, modules, module))
+                .collect::<Option<Vec<_>>>()?,
+            _ => return None,
+        };
+        let program_id = parse_seed(args.next()?, modules, module)?;
+        return Some(Pda { seeds, program_id });
+    }
+
+    if depth >= MAX_PDA_DEPTH {
+        return None;
+    }
+    let (helper_module, sig, block) = find_helper(call, modules, module)?;
+    let pda = find_pda_in_block(block, modules, helper_module, depth + 1)?;
+
+    // Parameters of the helper are replaced with the arguments
+    let params = get_params(sig);
+    let args = call
+        .args
+        .iter()
+        .map(|arg| parse_seed(arg, modules, module))
+        .collect::<Vec<_>>();
+    let get_arg = |var: &str| {
+        let index = params.iter().position(|param| param == var)?;
+        args.get(index).cloned().flatten()
+    };
+
+    // Parameters that can't be parsed and the variables that are local to the helper can't be
+    // resolved by the callers
+    let is_resolvable = |seed: &PdaSeed| match seed {
+        PdaSeed::Var(var) if params.contains(var) => get_arg(var).is_some(),
+        PdaSeed::Var(var) => !is_local(var, block),
+        _ => true,
+    };
+    if !pda.seeds.iter().all(is_resolvable) || !is_resolvable(&pda.program_id) {
+        return None;
+    }
+
+    Some(pda.map_vars(get_arg))
+}
+
+/// Find the first address derivation inside the function body
+fn find_pda_in_block(
+    block: &Block,
+    modules: &ModuleGraph,
+    module: &str,
+    depth: usize,
+) -> Option<Pda> {
+    struct PdaFinder<'a> {
+        modules: &'a ModuleGraph,
+        module: &'a str,
+        depth: usize,
+        pda: Option<Pda>,
+    }
+
+    impl<'ast> Visit<'ast> for PdaFinder<'_> {
+        fn visit_expr_call(&mut self, call: &'ast ExprCall) {
+            if self.pda.is_some() {
+                return;
+            }
+            let expr = Expr::Call(call.clone());
+            match get_pda_with_depth(&expr, self.modules, self.module, self.depth) {
+                Some(pda) => self.pda = Some(pda),
+                None => visit::visit_expr_call(self, call),
+            }
+        }
+    }
+
+    let mut finder = PdaFinder {
+        modules,
+        module,
+        depth,
+        pda: None,
+    };
+    finder.visit_block(block);
+    finder.pda
+}
+
+/// Parse the seed expression e.g `b"vault"`, `&owner.to_bytes()` or `amount.to_le_bytes().as_ref()`
+fn parse_seed(expr: &Expr, modules: &ModuleGraph, module: &str) -> Option<PdaSeed> {
+    match strip_expr(expr) {
+        Expr::Lit(expr_lit) => match &expr_lit.lit {
+            Lit::ByteStr(byte_str) => Some(PdaSeed::Const(byte_str.value())),
+            Lit::Str(lit_str) => Some(PdaSeed::Const(lit_str.value().into_bytes())),
+            _ => None,
+        },
+        Expr::MethodCall(method_call)
+            if SEED_METHODS.contains(&&*method_call.method.to_string()) =>
+        {
+            parse_seed(&method_call.receiver, modules, module)
+        }
+        // e.g `owner_info.key`
+        Expr::Field(field) if field.member.to_token_stream().to_string() == "key" => {
+            parse_seed(&field.base, modules, module)
+        }
+        Expr::Path(path) => {
+            let path = tokens_to_string(path.path.to_token_stream().to_string());
+            // Constants of the crate e.g `pub const VAULT_SEED: &[u8] = b"vault";`
+            let value = modules
+                .resolve(&path, module)
+                .and_then(|symbol| match &symbol.item {
+                    Item::Const(item_const) => {
+                        parse_seed(&item_const.expr, modules, &symbol.module)
+                    }
+                    _ => None,
+                });
+            match value {
+                Some(PdaSeed::Const(value)) => Some(PdaSeed::Const(value)),
+                _ => Some(PdaSeed::Var(path)),
+            }
+        }
+        // e.g `spl_token::id()`
+        Expr::Call(call) if call.args.is_empty() => Some(PdaSeed::Var(tokens_to_string(
+            call.to_token_stream().to_string(),
+        ))),
+        _ => None,
+    }
+}
+
+/// Remove the references, dereferences, parentheses and the error handling of the expression
+fn strip_expr(expr: &Expr) -> &Expr {
+    match expr {
+        Expr::Reference(reference) => strip_expr(&reference.expr),
+        Expr::Unary(unary) => strip_expr(&unary.expr),
+        Expr::Paren(paren) => strip_expr(&paren.expr),
+        Expr::Try(expr_try) => strip_expr(&expr_try.expr),
+        // Address of `(address, bump)` e.g `find_program_address(..).0`
+        Expr::Field(field) if field.member.to_token_stream().to_string() == "0" => {
+            strip_expr(&field.base)
+        }
+        Expr::MethodCall(method_call)
+            if matches!(&*method_call.method.to_string(), "unwrap" | "expect") =>
+        {
+            strip_expr(&method_call.receiver)
+        }
+        _ => expr,
+    }
+}
+
+/// Find the function of the crate that is called
+fn find_helper<'a>(
+    call: &ExprCall,
+    modules: &'a ModuleGraph,
+    module: &str,
+) -> Option<(&'a str, &'a Signature, &'a Block)> {
+    let path = match &*call.func {
+        Expr::Path(path) => &path.path,
+        _ => return None,
+    };
+    let name = path.segments.last()?.ident.to_string();
+    let path = tokens_to_string(path.to_token_stream().to_string());
+    match modules.resolve(&path, module) {
+        Some(symbol) => match &symbol.item {
+            Item::Fn(item_fn) => Some((symbol.module.as_str(), &item_fn.sig, &*item_fn.block)),
+            _ => None,
+        },
+        None => find_fn(modules, module, &name),
+    }
+}
+
+fn get_params(sig: &Signature) -> Vec<String> {
+    sig.inputs
+        .iter()
+        .filter_map(|input| match input {
+            FnArg::Typed(pat_type) => match &*pat_type.pat {
+                Pat::Ident(pat_ident) => Some(pat_ident.ident.to_string()),
+                _ => None,
+            },
+            FnArg::Receiver(_) => None,
+        })
+        .collect()
+}
+
+/// Whether the variable is declared inside the block
+fn is_local(var: &str, block: &Block) -> bool {
+    struct LocalFinder<'a> {
+        var: &'a str,
+        found: bool,
+    }
+
+    impl<'ast> Visit<'ast> for LocalFinder<'_> {
+        fn visit_pat_ident(&mut self, pat_ident: &'ast syn::PatIdent) {
+            self.found |= pat_ident.ident == self.var;
+        }
+    }
+
+    let mut finder = LocalFinder { var, found: false };
+    finder.visit_block(block);
+    finder.found
+}
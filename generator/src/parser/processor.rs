@@ -0,0 +1,655 @@
+use std::collections::HashMap;
+
+use quote::ToTokens;
+use syn::{
+    visit::{self, Visit},
+    BinOp, Block, Expr, ExprBinary, ExprCall, ExprField, ExprForLoop, ExprIf, ExprLoop, ExprMatch,
+    ExprMethodCall, ExprWhile, FnArg, Ident, ImplItem, Item, Local, Member, Pat, Path, Signature,
+};
+
+use super::{
+    builder::{find_fn, get_collection_name, AccountMetaKind, BuilderAccountMeta},
+    common::tokens_to_string,
+    instruction::InstructionEnum,
+    module::ModuleGraph,
+    pda::{get_pda, get_pda_binding, Pda, PdaSeed},
+};
+use crate::{constants::known_types::SYSVARS, utils::common::snake_from_pascal};
+
+/// Suffixes that are removed from the account variable names e.g `mint_info` -> `mint`
+const ACCOUNT_INFO_SUFFIXES: [&str; 3] = ["_account_info", "_info", "_ai"];
+
+/// Methods that mutably borrow the data or the lamports of an account, or change its owner
+const WRITE_METHODS: [&str; 5] = [
+    "borrow_mut",
+    "try_borrow_mut_data",
+    "try_borrow_mut_lamports",
+    "assign",
+    "realloc",
+];
+
+/// Get the accounts of the instruction from the processor.
+///
+/// The processor function is the one that matches on the instruction variant e.g
+/// `TokenInstruction::Transfer { amount } => ..`, or `process_<instruction>` if there is no match.
+/// Accounts are the `next_account_info` calls in order, they are named after the variables that
+/// they are bound to and their flags come from how they are used e.g `if !owner.is_signer { .. }`.
+///
+/// Programs with a single instruction may not match on it, the function of the `entrypoint!` is
+/// used for them if nothing else is found.
+///
+/// Functions that don't take any accounts e.g `TokenInstruction::pack(&self)` are skipped, and if
+/// more than one function matches on the variant, the first one that takes accounts from the
+/// iterator is used.
+///
+/// Returns `None` if the processor doesn't exist.
+pub fn get_processor_accounts(
+    modules: &ModuleGraph,
+    instruction_enum: &InstructionEnum,
+    variant_name: &str,
+) -> Option<Vec<ProcessorAccount>> {
+    let enum_name = instruction_enum.name.as_str();
+    let entrypoint_fn = match instruction_enum.variants.len() {
+        1 => find_entrypoint_fn(modules),
+        _ => None,
+    };
+    let process_fn_name = format!("process_{}", snake_from_pascal(variant_name));
+    let process_fn = find_fn(modules, "", &process_fn_name)
+        .filter(|(_, sig, _)| takes_accounts(sig))
+        .map(|(module, _, block)| (module, block));
+    let candidates = find_variant_fns(modules, enum_name, variant_name)
+        .into_iter()
+        .chain(process_fn)
+        .chain(entrypoint_fn)
+        .collect::<Vec<_>>();
+
+    let visit = |module: &str, block: &Block| {
+        let mut visitor = ProcessorVisitor {
+            modules,
+            module: module.to_owned(),
+            enum_name,
+            variant_name,
+            accounts: vec![],
+            aliases: HashMap::new(),
+            conditional_depth: 0,
+            repeated_depth: 0,
+            call_stack: vec![],
+            pdas: HashMap::new(),
+        };
+        visitor.visit_block(block);
+        visitor.accounts
+    };
+    // The processor may not take any accounts e.g an instruction that only logs
+    let mut accounts = candidates
+        .iter()
+        .map(|(module, block)| visit(module, block))
+        .find(|accounts| !accounts.is_empty())
+        .or_else(|| candidates.first().map(|_| vec![]))?;
+
+    // Accounts that are not bound to a variable are named after their sysvar or their index
+    for (i, account) in accounts.iter_mut().enumerate() {
+        if account.name.is_empty() {
+            account.name = match &account.sysvar {
+                Some(sysvar) => snake_from_pascal(sysvar),
+                None => format!("account_{i}"),
+            };
+        }
+    }
+
+    Some(accounts)
+}
+
+/// Get the accounts of the instruction from the processor as account metas, they are used when
+/// there is no builder function
+pub fn get_processor_account_metas(
+    modules: &ModuleGraph,
+    instruction_enum: &InstructionEnum,
+    variant_name: &str,
+) -> Option<Vec<BuilderAccountMeta>> {
+    let accounts = get_processor_accounts(modules, instruction_enum, variant_name)?;
+    let metas = accounts
+        .into_iter()
+        .map(|account| BuilderAccountMeta {
+            pubkey: account.name,
+            is_writable: account.is_writable,
+            is_signer: account.is_signer,
+            is_dynamic_writable: false,
+            is_dynamic_signer: false,
+            kind: account.kind,
+            source: account.source,
+            description: None,
+            owner: account.owner,
+            sysvar: account.sysvar,
+            pda: account.pda,
+        })
+        .collect();
+
+    Some(metas)
+}
+
+/// Find the functions that take accounts and have a match arm for the instruction variant, free
+/// functions and methods are searched in every module
+fn find_variant_fns<'a>(
+    modules: &'a ModuleGraph,
+    enum_name: &str,
+    variant_name: &str,
+) -> Vec<(&'a str, &'a Block)> {
+    modules
+        .modules()
+        .iter()
+        .flat_map(|module| {
+            module
+                .items
+                .iter()
+                .flat_map(|item| match item {
+                    Item::Fn(item_fn) => vec![(&item_fn.sig, &*item_fn.block)],
+                    Item::Impl(item_impl) => item_impl
+                        .items
+                        .iter()
+                        .filter_map(|impl_item| match impl_item {
+                            ImplItem::Method(method) => Some((&method.sig, &method.block)),
+                            _ => None,
+                        })
+                        .collect(),
+                    _ => vec![],
+                })
+                .filter(|(sig, block)| {
+                    let mut finder = VariantArmFinder {
+                        enum_name,
+                        variant_name,
+                        found: false,
+                    };
+                    finder.visit_block(block);
+                    finder.found && takes_accounts(sig)
+                })
+                .map(move |(_, block)| (module.path.as_str(), block))
+        })
+        .collect()
+}
+
+/// Find the function that is given to `entrypoint!` e.g `entrypoint!(process_instruction)`
+fn find_entrypoint_fn(modules: &ModuleGraph) -> Option<(&str, &Block)> {
+    modules.modules().iter().find_map(|module| {
+        module.items.iter().find_map(|item| match item {
+            Item::Macro(item_macro)
+                if item_macro.mac.path.segments.last()?.ident == "entrypoint" =>
+            {
+                let path = item_macro.mac.parse_body::<Path>().ok()?;
+                let name = path.segments.last()?.ident.to_string();
+                find_fn(modules, &module.path, &name)
+                    .filter(|(_, sig, _)| takes_accounts(sig))
+                    .map(|(module, _, block)| (module, block))
+            }
+            _ => None,
+        })
+    })
+}
+
+/// Whether the function has an `AccountInfo` parameter e.g `accounts: &[AccountInfo]`
+fn takes_accounts(sig: &Signature) -> bool {
+    sig.inputs.iter().any(|input| match input {
+        FnArg::Typed(pat_type) => pat_type
+            .ty
+            .to_token_stream()
+            .to_string()
+            .contains("AccountInfo"),
+        FnArg::Receiver(_) => false,
+    })
+}
+
+/// Whether the pattern matches the variant e.g `TokenInstruction::Transfer { amount }`
+fn is_variant_pat(pat: &Pat, enum_name: &str, variant_name: &str) -> bool {
+    let is_variant_path = |path: &Path| {
+        let segments = path.segments.iter().collect::<Vec<_>>();
+        match segments[..] {
+            [.., enum_segment, variant] if variant.ident == variant_name => {
+                enum_segment.ident == enum_name || enum_segment.ident == "Self"
+            }
+            [variant] => variant.ident == variant_name,
+            _ => false,
+        }
+    };
+
+    match pat {
+        Pat::Struct(pat_struct) => is_variant_path(&pat_struct.path),
+        Pat::TupleStruct(pat_tuple_struct) => is_variant_path(&pat_tuple_struct.path),
+        Pat::Path(pat_path) => is_variant_path(&pat_path.path),
+        Pat::Or(pat_or) => pat_or
+            .cases
+            .iter()
+            .any(|pat| is_variant_pat(pat, enum_name, variant_name)),
+        _ => false,
+    }
+}
+
+struct VariantArmFinder<'a> {
+    enum_name: &'a str,
+    variant_name: &'a str,
+    found: bool,
+}
+
+impl<'ast> Visit<'ast> for VariantArmFinder<'_> {
+    fn visit_expr_match(&mut self, expr_match: &'ast ExprMatch) {
+        self.found |= expr_match
+            .arms
+            .iter()
+            .any(|arm| is_variant_pat(&arm.pat, self.enum_name, self.variant_name));
+        visit::visit_expr_match(self, expr_match);
+    }
+}
+
+/// Account that is taken from the accounts iterator of the processor
+pub struct ProcessorAccount {
+    /// Name of the variable or the parameter that the account is bound to first
+    pub name: String,
+    /// Whether the processor writes to the account or checks `is_writable`
+    pub is_writable: bool,
+    /// Whether the processor checks `is_signer`
+    pub is_signer: bool,
+    /// Whether the account is given to a cross program invocation, the invoked program may write
+    /// to it
+    pub is_cpi: bool,
+    pub kind: AccountMetaKind,
+    /// Expression that takes the account e.g `next_account_info(account_info_iter)?`
+    pub source: String,
+    /// Expected owner from the owner checks e.g `crate::ID`
+    pub owner: Option<String>,
+    /// Sysvar that is read from the account e.g `Rent`
+    pub sysvar: Option<String>,
+    /// Seeds of the account from the address checks e.g `if vault != *vault_info.key { .. }`
+    pub pda: Option<Pda>,
+}
+
+struct ProcessorVisitor<'a> {
+    modules: &'a ModuleGraph,
+    /// Module of the function that is currently being visited
+    module: String,
+    enum_name: &'a str,
+    variant_name: &'a str,
+    accounts: Vec<ProcessorAccount>,
+    /// Variable or parameter name -> account index, for the function that is being visited
+    aliases: HashMap<String, usize>,
+    conditional_depth: usize,
+    repeated_depth: usize,
+    /// Functions that are currently being visited, prevents infinite recursion
+    call_stack: Vec<String>,
+    /// Variable name -> program derived address that is bound to it
+    pdas: HashMap<String, Pda>,
+}
+
+impl<'a> ProcessorVisitor<'a> {
+    fn kind(&self) -> AccountMetaKind {
+        if self.repeated_depth > 0 {
+            AccountMetaKind::Repeated
+        } else if self.conditional_depth > 0 {
+            AccountMetaKind::Conditional
+        } else {
+            AccountMetaKind::Unconditional
+        }
+    }
+
+    fn push_account(&mut self, source: &impl ToTokens, kind: AccountMetaKind) {
+        self.accounts.push(ProcessorAccount {
+            name: String::new(),
+            is_writable: false,
+            is_signer: false,
+            is_cpi: false,
+            kind,
+            source: tokens_to_string(source.to_token_stream().to_string()),
+            owner: None,
+            sysvar: None,
+            pda: None,
+        });
+    }
+
+    /// Get the address derivation of the expression or the variable that it's bound to, seeds that
+    /// refer to the accounts are replaced with the accounts
+    fn get_pda(&self, expr: &Expr) -> Option<Pda> {
+        let pda = get_pda(expr, self.modules, &self.module).or_else(|| {
+            let name = tokens_to_string(expr.to_token_stream().to_string());
+            self.pdas
+                .get(name.trim_start_matches(['*', '&']).trim())
+                .cloned()
+        })?;
+        Some(pda.map_vars(|var| self.aliases.get(var).map(|&index| PdaSeed::Account(index))))
+    }
+
+    /// Get the index of the account that the expression refers to e.g `&authority_info`,
+    /// `mint_info.clone()` or `source.data`
+    fn account_index(&self, expr: &Expr) -> Option<usize> {
+        match expr {
+            Expr::Path(path) => self
+                .aliases
+                .get(&path.path.get_ident()?.to_string())
+                .copied(),
+            Expr::Reference(reference) => self.account_index(&reference.expr),
+            Expr::Unary(unary) => self.account_index(&unary.expr),
+            Expr::Paren(paren) => self.account_index(&paren.expr),
+            Expr::Field(field) => self.account_index(&field.base),
+            Expr::Try(expr_try) => self.account_index(&expr_try.expr),
+            Expr::MethodCall(method_call) => self.account_index(&method_call.receiver),
+            _ => None,
+        }
+    }
+
+    /// Bind the account that is taken since `start` or referred to by the expression to the
+    /// variable or the parameter name, accounts are named after the first name they are bound to
+    fn bind(&mut self, name: &str, start: usize, expr: &Expr) -> Option<usize> {
+        let index = match self.accounts.len() - start {
+            0 => self.account_index(expr)?,
+            1 => start,
+            _ => return None,
+        };
+
+        let account = &mut self.accounts[index];
+        if account.name.is_empty() && account.sysvar.is_none() {
+            let name = ACCOUNT_INFO_SUFFIXES
+                .iter()
+                .find_map(|suffix| name.strip_suffix(suffix))
+                .unwrap_or(name)
+                .trim_start_matches('_');
+            account.name = name.to_owned();
+        }
+
+        Some(index)
+    }
+
+    /// Visit the arguments of the call and the body of the function if it's defined inside the
+    /// crate, accounts that are taken or referred to by the arguments are bound to the parameters
+    fn visit_helper(&mut self, call: &ExprCall) {
+        let helper = self.find_helper(call);
+        let params = helper
+            .as_ref()
+            .map(|(_, _, sig, _)| {
+                sig.inputs
+                    .iter()
+                    .map(|input| match input {
+                        FnArg::Typed(pat_type) => match &*pat_type.pat {
+                            Pat::Ident(pat_ident) => Some(pat_ident.ident.to_string()),
+                            _ => None,
+                        },
+                        FnArg::Receiver(_) => None,
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        let mut aliases = HashMap::new();
+        for (i, arg) in call.args.iter().enumerate() {
+            let start = self.accounts.len();
+            self.visit_expr(arg);
+            if let Some(Some(param)) = params.get(i) {
+                if let Some(index) = self.bind(param, start, arg) {
+                    aliases.insert(param.to_owned(), index);
+                }
+            }
+        }
+
+        if let Some((name, module, _, block)) = helper {
+            let aliases = std::mem::replace(&mut self.aliases, aliases);
+            let module = std::mem::replace(&mut self.module, module.to_owned());
+            self.call_stack.push(name);
+            self.visit_block(block);
+            self.call_stack.pop();
+            self.module = module;
+            self.aliases = aliases;
+        }
+    }
+
+    /// Find the function that is called if it's defined inside the crate and it's given accounts
+    fn find_helper(&self, call: &ExprCall) -> Option<(String, &'a str, &'a Signature, &'a Block)> {
+        let (path, name) = match &*call.func {
+            Expr::Path(path) => (
+                tokens_to_string(path.path.to_token_stream().to_string()),
+                path.path.segments.last()?.ident.to_string(),
+            ),
+            _ => return None,
+        };
+        if self.call_stack.contains(&name) {
+            return None;
+        }
+
+        // Only follow the functions that are given accounts
+        let is_given_accounts = call.args.iter().any(|arg| {
+            let arg = arg.to_token_stream().to_string();
+            arg.contains("account")
+                || arg.contains("iter")
+                || self
+                    .aliases
+                    .keys()
+                    .any(|alias| arg.contains(alias.as_str()))
+        });
+        if !is_given_accounts {
+            return None;
+        }
+
+        let (module, sig, block) = match self.modules.resolve(&path, &self.module) {
+            Some(symbol) => match &symbol.item {
+                Item::Fn(item_fn) => (symbol.module.as_str(), &item_fn.sig, &*item_fn.block),
+                _ => return None,
+            },
+            None => find_fn(self.modules, &self.module, &name)?,
+        };
+
+        Some((name, module, sig, block))
+    }
+
+    /// Visit the body of a loop, accounts that are taken inside are repeated
+    fn visit_repeated(&mut self, visit: impl FnOnce(&mut Self)) {
+        self.repeated_depth += 1;
+        visit(self);
+        self.repeated_depth -= 1;
+    }
+}
+
+impl<'ast> Visit<'ast> for ProcessorVisitor<'_> {
+    fn visit_expr_call(&mut self, call: &'ast ExprCall) {
+        let func = tokens_to_string(call.func.to_token_stream().to_string());
+        if func.ends_with("next_account_info") {
+            return self.push_account(call, self.kind());
+        }
+
+        // e.g `Rent::from_account_info(rent_info)`
+        if func.ends_with("::from_account_info") {
+            let start = self.accounts.len();
+            visit::visit_expr_call(self, call);
+            let sysvar = func.rsplit("::").nth(1).unwrap_or_default();
+            if SYSVARS.contains(&sysvar) {
+                let index = match self.accounts.len() - start {
+                    1 => Some(start),
+                    _ => call.args.first().and_then(|arg| self.account_index(arg)),
+                };
+                if let Some(index) = index {
+                    self.accounts[index].sysvar = Some(sysvar.to_owned());
+                }
+            }
+            return;
+        }
+
+        // Accounts that are given to other programs e.g `invoke(&ix, &[source.clone(), ..])`
+        let fn_name = func.rsplit("::").next().unwrap_or_default();
+        if fn_name.starts_with("invoke") {
+            let mut collector = IdentCollector::default();
+            for arg in &call.args {
+                collector.visit_expr(arg);
+            }
+            for ident in collector.idents {
+                if let Some(index) = self.aliases.get(&ident) {
+                    self.accounts[*index].is_cpi = true;
+                }
+            }
+        }
+
+        self.visit_expr(&call.func);
+        self.visit_helper(call);
+    }
+
+    fn visit_expr_method_call(&mut self, method_call: &'ast ExprMethodCall) {
+        let method = method_call.method.to_string();
+        let receiver = tokens_to_string(method_call.receiver.to_token_stream().to_string());
+
+        // Remaining accounts e.g `account_info_iter.as_slice()`
+        if method == "as_slice" && receiver.contains("iter") {
+            return self.push_account(method_call, AccountMetaKind::Repeated);
+        }
+        // e.g `accounts_iter.next()`
+        if method == "next" && method_call.args.is_empty() && receiver.contains("iter") {
+            return self.push_account(method_call, self.kind());
+        }
+
+        if WRITE_METHODS.contains(&method.as_str()) {
+            if let Some(index) = self.account_index(&method_call.receiver) {
+                self.accounts[index].is_writable = true;
+            }
+        }
+
+        // Accounts that may not exist e.g `next_account_info(account_info_iter).ok()`
+        if method == "ok" {
+            self.conditional_depth += 1;
+            visit::visit_expr_method_call(self, method_call);
+            self.conditional_depth -= 1;
+        } else {
+            visit::visit_expr_method_call(self, method_call);
+        }
+    }
+
+    fn visit_expr_field(&mut self, expr_field: &'ast ExprField) {
+        if let Member::Named(member) = &expr_field.member {
+            if let Some(index) = self.account_index(&expr_field.base) {
+                match member.to_string().as_str() {
+                    "is_signer" => self.accounts[index].is_signer = true,
+                    "is_writable" => self.accounts[index].is_writable = true,
+                    _ => {}
+                }
+            }
+        }
+        visit::visit_expr_field(self, expr_field);
+    }
+
+    fn visit_expr_binary(&mut self, expr_binary: &'ast ExprBinary) {
+        // Owner checks e.g `if mint_info.owner != program_id { .. }`
+        if let BinOp::Eq(_) | BinOp::Ne(_) = expr_binary.op {
+            let is_owner = |expr: &Expr| {
+                let expr = tokens_to_string(expr.to_token_stream().to_string());
+                expr.trim_start_matches(['*', '&']).ends_with(".owner")
+            };
+            let sides = [
+                (&*expr_binary.left, &*expr_binary.right),
+                (&*expr_binary.right, &*expr_binary.left),
+            ];
+            for (account, owner) in sides {
+                // Address checks e.g `if vault != *vault_info.key { .. }`
+                let account_str = tokens_to_string(account.to_token_stream().to_string());
+                if account_str.ends_with(".key") {
+                    if let (Some(index), Some(pda)) =
+                        (self.account_index(account), self.get_pda(owner))
+                    {
+                        self.accounts[index].pda.get_or_insert(pda);
+                    }
+                }
+
+                match (is_owner(account), self.account_index(account)) {
+                    (true, Some(index)) if self.accounts[index].owner.is_none() => {
+                        let owner = tokens_to_string(owner.to_token_stream().to_string());
+                        let owner = owner.trim_start_matches(['*', '&']).trim();
+                        self.accounts[index].owner = Some(match owner {
+                            "program_id" => "crate::ID".into(),
+                            _ => owner.to_owned(),
+                        });
+                    }
+                    _ => {}
+                }
+            }
+        }
+        visit::visit_expr_binary(self, expr_binary);
+    }
+
+    fn visit_local(&mut self, local: &'ast Local) {
+        let start = self.accounts.len();
+        visit::visit_local(self, local);
+
+        // e.g `let (vault, bump) = Pubkey::find_program_address(&[..], program_id);`
+        if let (Some(name), Some((_, init))) = (get_pda_binding(&local.pat), &local.init) {
+            if let Some(pda) = self.get_pda(init) {
+                self.pdas.insert(name, pda);
+            }
+        }
+
+        let name = match &local.pat {
+            Pat::Ident(pat_ident) => pat_ident.ident.to_string(),
+            Pat::Type(pat_type) => match &*pat_type.pat {
+                Pat::Ident(pat_ident) => pat_ident.ident.to_string(),
+                _ => return,
+            },
+            _ => return,
+        };
+        if let Some((_, init)) = &local.init {
+            if let Some(index) = self.bind(&name, start, init) {
+                self.aliases.insert(name, index);
+            }
+        }
+    }
+
+    fn visit_expr_if(&mut self, expr_if: &'ast ExprIf) {
+        self.visit_expr(&expr_if.cond);
+        self.conditional_depth += 1;
+        self.visit_block(&expr_if.then_branch);
+        if let Some((_, else_branch)) = &expr_if.else_branch {
+            self.visit_expr(else_branch);
+        }
+        self.conditional_depth -= 1;
+    }
+
+    fn visit_expr_match(&mut self, expr_match: &'ast ExprMatch) {
+        self.visit_expr(&expr_match.expr);
+
+        // Only the arm of the instruction is visited in the instruction match
+        let variant_arm = expr_match
+            .arms
+            .iter()
+            .find(|arm| is_variant_pat(&arm.pat, self.enum_name, self.variant_name));
+        if let Some(arm) = variant_arm {
+            return self.visit_arm(arm);
+        }
+
+        self.conditional_depth += 1;
+        for arm in &expr_match.arms {
+            self.visit_arm(arm);
+        }
+        self.conditional_depth -= 1;
+    }
+
+    fn visit_expr_for_loop(&mut self, expr_for_loop: &'ast ExprForLoop) {
+        self.visit_expr(&expr_for_loop.expr);
+
+        // Items of the repeated accounts are the same accounts e.g `for signer in signers.iter()`
+        let collection = get_collection_name(&expr_for_loop.expr);
+        let index = collection.and_then(|name| self.aliases.get(&name).copied());
+        if let (Some(index), Pat::Ident(pat_ident)) = (index, &expr_for_loop.pat) {
+            self.aliases.insert(pat_ident.ident.to_string(), index);
+        }
+
+        self.visit_repeated(|visitor| visitor.visit_block(&expr_for_loop.body));
+    }
+
+    fn visit_expr_while(&mut self, expr_while: &'ast ExprWhile) {
+        self.visit_expr(&expr_while.cond);
+        self.visit_repeated(|visitor| visitor.visit_block(&expr_while.body));
+    }
+
+    fn visit_expr_loop(&mut self, expr_loop: &'ast ExprLoop) {
+        self.visit_repeated(|visitor| visitor.visit_block(&expr_loop.body));
+    }
+}
+
+/// Collects every identifier of the visited nodes
+#[derive(Default)]
+struct IdentCollector {
+    idents: Vec<String>,
+}
+
+impl<'ast> Visit<'ast> for IdentCollector {
+    fn visit_ident(&mut self, ident: &'ast Ident) {
+        self.idents.push(ident.to_string());
+    }
+}
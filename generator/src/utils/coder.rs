@@ -1,8 +1,10 @@
 use anchor_syn::idl::{EnumFields, Idl, IdlField, IdlType, IdlTypeDefinitionTy};
 
-use super::common::{
-    camel_from_pascal, camel_from_snake, error, get_const_value, get_inside_item, get_item, warn,
+use super::{
+    common::{camel_from_pascal, camel_from_snake, error, get_inside_item, warn},
+    idl::Discriminants,
 };
+use crate::parser::{eval::eval_const, module::ModuleGraph};
 
 pub enum AccountSpace {
     Normal(usize),
@@ -11,19 +13,18 @@ pub enum AccountSpace {
     NotFound,
 }
 
-pub fn get_total_space_for_account<A, C>(account_name: A, all_content: C, idl: &Idl) -> AccountSpace
-where
-    A: AsRef<str>,
-    C: AsRef<str>,
-{
+pub fn get_total_space_for_account(
+    account_name: impl AsRef<str>,
+    modules: &ModuleGraph,
+    idl: &Idl,
+) -> AccountSpace {
     let acc_name = account_name.as_ref();
-    let all_content = all_content.as_ref();
 
     // Try to calculate total space from the generated idl
     let idl_space = get_account_space_from_idl(acc_name, idl);
 
     // Check `Pack` implementation
-    let maybe_pack_space = get_account_space_from_pack(acc_name, all_content);
+    let maybe_pack_space = get_account_space_from_pack(acc_name, modules);
 
     match idl_space {
         AccountSpace::Normal(idl_space) => match maybe_pack_space {
@@ -80,45 +81,12 @@ fn get_account_space_from_idl(account_name: impl AsRef<str>, idl: &Idl) -> Accou
     AccountSpace::NotFound
 }
 
-fn get_account_space_from_pack<A, C>(account_name: A, all_content: C) -> Option<usize>
-where
-    A: AsRef<str>,
-    C: AsRef<str>,
-{
-    let acc_name = account_name.as_ref();
-    let all_content = all_content.as_ref();
-
-    if let Some(i) = all_content.find(&format!("impl Pack for {acc_name} ")) {
-        let pack_impl_content = get_item(all_content.get(i..).unwrap(), '{');
-        let len_line = pack_impl_content
-            .lines()
-            .filter(|&l| l.trim_start().starts_with("const") && l.contains("LEN"))
-            .collect::<Vec<&str>>()[0];
-        let len = len_line
-            .split_once(';')
-            .unwrap()
-            .0
-            .split_whitespace()
-            .last()
-            .unwrap();
-        // len could be defined as a constant in another place
-        let len = match len.parse::<usize>() {
-            Ok(l) => l,
-            Err(_) => {
-                // len is not a usize, check for const definition
-                let maybe_const_str = get_const_value(len, all_content);
-                if let Some(const_str) = maybe_const_str {
-                    return Some(const_str.parse::<usize>().unwrap());
-                }
-
-                return None;
-            }
-        };
-
-        return Some(len);
-    }
+fn get_account_space_from_pack(account_name: &str, modules: &ModuleGraph) -> Option<usize> {
+    // `Pack::LEN` of the account e.g `impl Pack for Mint { const LEN: usize = 82; }`
+    let account = modules.find_type(account_name)?;
+    let len = eval_const(&format!("{}::LEN", account.path), &account.module, modules)?;
 
-    None
+    len.try_into().ok()
 }
 
 pub fn get_idl_type_size(idl_type: &IdlType, idl: &Idl) -> Option<usize> {
@@ -145,6 +113,12 @@ pub fn get_idl_type_size(idl_type: &IdlType, idl: &Idl) -> Option<usize> {
                 _ => (),
             }
 
+            // `COption` has a `u32` tag and the space of the value even if it's `None`
+            if name.starts_with("COption") {
+                let inside = get_inside_item(name, '<')?.parse::<IdlType>().ok()?;
+                return Some(4 + get_idl_type_size(&inside, idl)?);
+            }
+
             if let Some(type_def) = idl
                 .types
                 .iter()
@@ -172,13 +146,13 @@ pub fn get_idl_type_size(idl_type: &IdlType, idl: &Idl) -> Option<usize> {
                                         }
                                     }
                                     EnumFields::Tuple(tuples) => {
+                                        // Tuple fields are laid out one after another
+                                        let mut tuple_size = 0usize;
                                         for tuple in tuples {
-                                            if let Some(tuple_size) = get_idl_type_size(tuple, idl)
-                                            {
-                                                if tuple_size > maximum_enum_size {
-                                                    maximum_enum_size = tuple_size
-                                                }
-                                            }
+                                            tuple_size += get_idl_type_size(tuple, idl)?;
+                                        }
+                                        if tuple_size > maximum_enum_size {
+                                            maximum_enum_size = tuple_size
                                         }
                                     }
                                 }
@@ -213,6 +187,102 @@ pub fn get_idl_type_size(idl_type: &IdlType, idl: &Idl) -> Option<usize> {
     Some(size)
 }
 
+/// Get the size and the signedness of the integer type e.g `(8, false)` for `u64`
+pub fn get_int_size(idl_type: &IdlType) -> Option<(usize, bool)> {
+    let int_size = match idl_type {
+        IdlType::U8 => (1, false),
+        IdlType::I8 => (1, true),
+        IdlType::U16 => (2, false),
+        IdlType::I16 => (2, true),
+        IdlType::U32 => (4, false),
+        IdlType::I32 => (4, true),
+        IdlType::U64 => (8, false),
+        IdlType::I64 => (8, true),
+        IdlType::U128 => (16, false),
+        IdlType::I128 => (16, true),
+        _ => return None,
+    };
+
+    Some(int_size)
+}
+
+/// Get the byte offsets of the fields in order, offsets of the fields that come after a
+/// variable-length field or an enum with different sized variants are `None`
+pub fn get_field_offsets<'a>(
+    fields: &'a [IdlField],
+    idl: &Idl,
+) -> Vec<(&'a IdlField, Option<usize>)> {
+    let mut offset = Some(0);
+    fields
+        .iter()
+        .map(|field| {
+            let field_offset = offset;
+            let size = get_idl_type_size(&field.ty, idl).filter(|_| is_fixed_size(&field.ty, idl));
+            offset = offset.zip(size).map(|(o, s)| o + s);
+            (field, field_offset)
+        })
+        .collect()
+}
+
+/// Whether every value of the type is serialized with the same number of bytes.
+///
+/// `get_idl_type_size` gives the size of the largest variant of the enums, which is the space
+/// that is needed but not the size of the other variants since only the fields of the variant are
+/// serialized.
+fn is_fixed_size(idl_type: &IdlType, idl: &Idl) -> bool {
+    match idl_type {
+        IdlType::Array(inside_type, _) => is_fixed_size(inside_type, idl),
+        IdlType::Defined(name) => {
+            if name.starts_with("COption") {
+                return get_inside_item(name, '<')
+                    .and_then(|inside| inside.parse::<IdlType>().ok())
+                    .map(|inside| is_fixed_size(&inside, idl))
+                    .unwrap_or(false);
+            }
+
+            let type_def = idl
+                .types
+                .iter()
+                .find(|&type_def| &type_def.name == name)
+                .or(idl.accounts.iter().find(|&acc_def| &acc_def.name == name));
+            match type_def.map(|type_def| &type_def.ty) {
+                Some(IdlTypeDefinitionTy::Struct { fields }) => {
+                    fields.iter().all(|field| is_fixed_size(&field.ty, idl))
+                }
+                Some(IdlTypeDefinitionTy::Enum { variants }) => {
+                    let variant_types = variants
+                        .iter()
+                        .map(|variant| match &variant.fields {
+                            Some(EnumFields::Named(fields)) => {
+                                fields.iter().map(|field| &field.ty).collect()
+                            }
+                            Some(EnumFields::Tuple(types)) => types.iter().collect(),
+                            None => vec![],
+                        })
+                        .collect::<Vec<Vec<_>>>();
+                    let variant_sizes = variant_types
+                        .iter()
+                        .map(|types| {
+                            types
+                                .iter()
+                                .map(|ty| get_idl_type_size(ty, idl))
+                                .sum::<Option<usize>>()
+                        })
+                        .collect::<Vec<_>>();
+
+                    variant_sizes.windows(2).all(|sizes| sizes[0] == sizes[1])
+                        && variant_types
+                            .iter()
+                            .flatten()
+                            .all(|ty| is_fixed_size(ty, idl))
+                }
+                None => true,
+            }
+        }
+        _ => true,
+    }
+}
+
 pub fn get_max_span(ty: &IdlType, arg_name: impl AsRef<str>, idl: &Idl) -> String {
     let arg_name = arg_name.as_ref();
 
@@ -268,16 +338,30 @@ pub fn get_max_span(ty: &IdlType, arg_name: impl AsRef<str>, idl: &Idl) -> Strin
                                         for named_field in named_field_types {
                                             needed_span.push_str(&get_max_span(
                                                 &named_field.ty,
-                                                &format!("{arg_name}.{variant_name}"),
+                                                format!("{arg_name}.{variant_name}"),
                                                 idl,
                                             ))
                                         }
                                         needed_span.push(';')
                                     }
                                     EnumFields::Tuple(field_types) => {
-                                        for field_type in field_types {
-                                            needed_span
-                                                .push_str(&get_max_span(field_type, arg_name, idl));
+                                        match field_types.as_slice() {
+                                            [field_type] => needed_span.push_str(&get_max_span(
+                                                field_type,
+                                                format!("{arg_name}.{variant_name}"),
+                                                idl,
+                                            )),
+                                            _ => {
+                                                for (i, field_type) in
+                                                    field_types.iter().enumerate()
+                                                {
+                                                    needed_span.push_str(&get_max_span(
+                                                        field_type,
+                                                        format!("{arg_name}.{variant_name}[{i}]"),
+                                                        idl,
+                                                    ));
+                                                }
+                                            }
                                         }
 
                                         needed_span.push(';')
@@ -327,20 +411,24 @@ pub fn get_max_span(ty: &IdlType, arg_name: impl AsRef<str>, idl: &Idl) -> Strin
     }
 }
 
-pub fn get_buffer_type(idl_field: &IdlField, idl: &Idl) -> String {
+pub fn get_buffer_type(idl_field: &IdlField, idl: &Idl, discriminants: &Discriminants) -> String {
     format!(
         r#"{}"{}"),"#,
-        get_buffer_type_internal(&idl_field.ty, idl),
+        get_buffer_type_internal(&idl_field.ty, idl, discriminants),
         idl_field.name
     )
 }
 
-fn get_buffer_type_internal(idl_type: &IdlType, idl: &Idl) -> String {
+fn get_buffer_type_internal(
+    idl_type: &IdlType,
+    idl: &Idl,
+    discriminants: &Discriminants,
+) -> String {
     let buffer_type = match idl_type {
         IdlType::Array(inside_type, len) => {
             return format!(
                 "B.seq({}), {len}, ",
-                get_buffer_type_internal(inside_type, idl)
+                get_buffer_type_internal(inside_type, idl, discriminants)
             );
         }
         IdlType::Bool => "B.bool",
@@ -350,15 +438,18 @@ fn get_buffer_type_internal(idl_type: &IdlType, idl: &Idl) -> String {
                 let inside_result = get_inside_item(t, '<').unwrap().parse::<IdlType>();
                 match inside_result {
                     Ok(inside) => {
-                        let inside_buffer_type = get_buffer_type_internal(&inside, idl);
+                        let inside_buffer_type =
+                            get_buffer_type_internal(&inside, idl, discriminants);
                         return format!("B.coption({inside_buffer_type}), ");
                     }
                     Err(_) => error(format!("Type {t} not parseable")),
                 }
             } else {
                 match t.as_str() {
-                    "Decimal" => return format!("B.decimal("),
-                    "&'astr" => return get_buffer_type_internal(&IdlType::String, idl),
+                    "Decimal" => return "B.decimal(".to_string(),
+                    "&'astr" => {
+                        return get_buffer_type_internal(&IdlType::String, idl, discriminants)
+                    }
                     _ => (),
                 }
             }
@@ -374,7 +465,8 @@ fn get_buffer_type_internal(idl_type: &IdlType, idl: &Idl) -> String {
                     IdlTypeDefinitionTy::Struct { fields } => {
                         let mut struct_properties = String::new();
                         for field in fields {
-                            let inside_buffer_type = get_buffer_type_internal(&field.ty, idl);
+                            let inside_buffer_type =
+                                get_buffer_type_internal(&field.ty, idl, discriminants);
                             struct_properties
                                 .push_str(&format!(r#"{}"{}"),"#, inside_buffer_type, field.name));
                         }
@@ -382,12 +474,11 @@ fn get_buffer_type_internal(idl_type: &IdlType, idl: &Idl) -> String {
                         return format!("B.struct([{struct_properties}], ");
                     }
                     IdlTypeDefinitionTy::Enum { variants } => {
-                        let mut enum_buffer_type = format!(
-                            r#"((p: string) => {{ const U = B.union(B.u8("discriminator"), null, p);"#,
-                        );
+                        let mut enum_buffer_type = r#"((p: string) => { const U = B.union(B.u8("discriminator"), null, p);"#.to_string();
 
                         for (i, variant) in variants.iter().enumerate() {
                             let variant_name = camel_from_pascal(&variant.name);
+                            let i = discriminants.variant(t, &variant.name, i);
                             match &variant.fields {
                                 Some(fields) => match fields {
                                     EnumFields::Named(named_field_types) => {
@@ -395,7 +486,11 @@ fn get_buffer_type_internal(idl_type: &IdlType, idl: &Idl) -> String {
                                         for named_field in named_field_types {
                                             properties.push_str(&format!(
                                                 r#"{}"{}"),"#,
-                                                get_buffer_type_internal(&named_field.ty, idl),
+                                                get_buffer_type_internal(
+                                                    &named_field.ty,
+                                                    idl,
+                                                    discriminants
+                                                ),
                                                 camel_from_snake(&named_field.name)
                                             ))
                                         }
@@ -405,18 +500,37 @@ fn get_buffer_type_internal(idl_type: &IdlType, idl: &Idl) -> String {
                                         ));
                                     }
                                     EnumFields::Tuple(field_types) => {
-                                        if field_types.len() != 1 {
-                                            error(format!(
-                                                "Tuple enum type for '{}' is not supported!",
-                                                variant_name
-                                            ));
-                                        }
-                                        // TODO: support tuple enum types (u64, String)
-                                        let field_type = &field_types[0];
-                                        let buffer_type =
-                                            get_buffer_type_internal(&field_type, idl);
+                                        let buffer_type = match field_types.as_slice() {
+                                            // Single field is the value of the variant
+                                            [field_type] => format!(
+                                                "{})",
+                                                get_buffer_type_internal(
+                                                    field_type,
+                                                    idl,
+                                                    discriminants
+                                                )
+                                            ),
+                                            // Fields are named by their index like Anchor e.g
+                                            // `{ foo: { 0: 1, 1: "bar" } }`
+                                            _ => {
+                                                let mut properties = String::new();
+                                                for (i, field_type) in
+                                                    field_types.iter().enumerate()
+                                                {
+                                                    properties.push_str(&format!(
+                                                        r#"{}"{i}"),"#,
+                                                        get_buffer_type_internal(
+                                                            field_type,
+                                                            idl,
+                                                            discriminants
+                                                        )
+                                                    ));
+                                                }
+                                                format!("B.struct([{properties}])")
+                                            }
+                                        };
                                         enum_buffer_type.push_str(&format!(
-                                            r#"U.addVariant({i}, {buffer_type}), "{variant_name}");"#,
+                                            r#"U.addVariant({i}, {buffer_type}, "{variant_name}");"#,
                                         ));
                                     }
                                 },
@@ -446,7 +560,7 @@ fn get_buffer_type_internal(idl_type: &IdlType, idl: &Idl) -> String {
         IdlType::I64 => "B.i64",
         IdlType::I8 => "B.i8",
         IdlType::Option(inside) => {
-            let inside_buffer_type = get_buffer_type_internal(&inside, idl);
+            let inside_buffer_type = get_buffer_type_internal(inside, idl, discriminants);
             return format!("B.option({inside_buffer_type}), ");
         }
         IdlType::PublicKey => "B.publicKey",
@@ -457,7 +571,10 @@ fn get_buffer_type_internal(idl_type: &IdlType, idl: &Idl) -> String {
         IdlType::U64 => "B.u64",
         IdlType::U8 => "B.u8",
         IdlType::Vec(inside) => {
-            return format!("B.vec({}), ", get_buffer_type_internal(inside, idl))
+            return format!(
+                "B.vec({}), ",
+                get_buffer_type_internal(inside, idl, discriminants)
+            )
         }
     };
 
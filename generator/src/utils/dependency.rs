@@ -0,0 +1,373 @@
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use toml::Value;
+
+use super::common::debug;
+use crate::constants::common::{dirname, filename};
+
+/// Dependency of the crate as it's written in `Cargo.toml`
+struct DependencySpec {
+    /// Name of the package e.g `spl-token`
+    package: String,
+    /// Version requirement e.g `3.5`
+    version: Option<String>,
+    /// Absolute path of `path = "..."` dependencies
+    path: Option<PathBuf>,
+    /// Version of the package in `Cargo.lock` if the crate has one
+    locked_version: Option<String>,
+}
+
+/// Find the source directories of the dependencies of the crate.
+///
+/// Dependencies(including the target specific ones) are looked up in this order: `path`
+/// dependencies, workspace members, `vendor/` directories and the local cargo
+/// registry(`~/.cargo/registry/src`). Versions that are locked in `Cargo.lock` are preferred over
+/// the highest matching version.
+///
+/// Returns crate name(e.g `spl_token`) -> crate directory
+pub fn get_dependency_paths(cargo_toml_path: &Path) -> HashMap<String, PathBuf> {
+    let mut paths = HashMap::new();
+
+    let manifest = match read_manifest(cargo_toml_path) {
+        Some(manifest) => manifest,
+        None => return paths,
+    };
+    let crate_dir = cargo_toml_path.parent().unwrap();
+    let workspace_root = find_workspace_root(crate_dir);
+    let locked_versions = get_locked_versions(crate_dir);
+
+    // e.g `[target.'cfg(not(target_os = "solana"))'.dependencies]`
+    let target_dependencies = manifest
+        .get("target")
+        .and_then(Value::as_table)
+        .into_iter()
+        .flat_map(|targets| targets.values())
+        .filter_map(|target| target.get("dependencies"));
+    let dependencies = manifest
+        .get("dependencies")
+        .into_iter()
+        .chain(target_dependencies)
+        .filter_map(Value::as_table)
+        .flatten();
+    for (name, value) in dependencies {
+        if paths.contains_key(&name.replace('-', "_")) {
+            continue;
+        }
+
+        let mut spec = get_dependency_spec(name, value, crate_dir, workspace_root.as_deref());
+        spec.locked_version = get_locked_version(&locked_versions, &spec);
+        let find_path = |spec: &DependencySpec| {
+            spec.path
+                .clone()
+                .or_else(|| find_workspace_member(&spec.package, workspace_root.as_deref()))
+                .or_else(|| find_vendored(spec, crate_dir))
+                .or_else(|| find_in_registry(spec))
+        };
+        let mut path = find_path(&spec);
+
+        let crate_name = name.replace('-', "_");
+        // The locked version might not be downloaded
+        if path.is_none() && spec.locked_version.is_some() {
+            debug(format!(
+                "Could not find the locked version of dependency '{crate_name}', using the highest matching version."
+            ));
+            spec.locked_version = None;
+            path = find_path(&spec);
+        }
+        match path {
+            Some(path) => {
+                debug(format!("Found dependency '{crate_name}' at {path:?}."));
+                paths.insert(crate_name, path);
+            }
+            None => debug(format!(
+                "Could not find the source of dependency '{crate_name}'."
+            )),
+        }
+    }
+
+    paths
+}
+
+/// Find the closest parent directory that has a workspace `Cargo.toml`
+pub fn find_workspace_root(crate_dir: &Path) -> Option<PathBuf> {
+    crate_dir.ancestors().map(Path::to_path_buf).find(|dir| {
+        read_manifest(&dir.join(filename::CARGO_TOML))
+            .map(|manifest| manifest.get("workspace").is_some())
+            .unwrap_or(false)
+    })
+}
+
+pub fn read_manifest(cargo_toml_path: &Path) -> Option<Value> {
+    fs::read_to_string(cargo_toml_path)
+        .ok()?
+        .parse::<Value>()
+        .ok()
+}
+
+fn get_dependency_spec(
+    name: &str,
+    value: &Value,
+    crate_dir: &Path,
+    workspace_root: Option<&Path>,
+) -> DependencySpec {
+    let mut spec = DependencySpec {
+        package: name.to_owned(),
+        version: None,
+        path: None,
+        locked_version: None,
+    };
+
+    // e.g `spl-token = { workspace = true }`
+    let mut base_dir = crate_dir.to_owned();
+    let mut value = value;
+    let workspace_value;
+    if value.get("workspace").and_then(Value::as_bool) == Some(true) {
+        if let Some(root) = workspace_root {
+            let dependency = read_manifest(&root.join(filename::CARGO_TOML)).and_then(|manifest| {
+                manifest
+                    .get("workspace")?
+                    .get("dependencies")?
+                    .get(name)
+                    .cloned()
+            });
+            if let Some(dependency) = dependency {
+                workspace_value = dependency;
+                value = &workspace_value;
+                base_dir = root.to_owned();
+            }
+        }
+    }
+
+    match value {
+        Value::String(version) => spec.version = Some(version.to_owned()),
+        Value::Table(table) => {
+            if let Some(package) = table.get("package").and_then(Value::as_str) {
+                spec.package = package.to_owned();
+            }
+            spec.version = table
+                .get("version")
+                .and_then(Value::as_str)
+                .map(str::to_owned);
+            spec.path = table
+                .get("path")
+                .and_then(Value::as_str)
+                .map(|path| base_dir.join(path))
+                .filter(|path| path.exists());
+        }
+        _ => {}
+    }
+
+    spec
+}
+
+fn find_workspace_member(package: &str, workspace_root: Option<&Path>) -> Option<PathBuf> {
+    let root = workspace_root?;
+    let manifest = read_manifest(&root.join(filename::CARGO_TOML))?;
+    let members = manifest.get("workspace")?.get("members")?.as_array()?;
+
+    members
+        .iter()
+        .filter_map(Value::as_str)
+        .flat_map(|member| match member.strip_suffix("/*") {
+            // e.g `members = ["token/*"]`
+            Some(parent) => fs::read_dir(root.join(parent))
+                .map(|dir| dir.flatten().map(|entry| entry.path()).collect())
+                .unwrap_or_default(),
+            None => vec![root.join(member)],
+        })
+        .find(|member_dir| get_package_name(member_dir).as_deref() == Some(package))
+}
+
+fn find_vendored(spec: &DependencySpec, crate_dir: &Path) -> Option<PathBuf> {
+    crate_dir
+        .ancestors()
+        .map(|dir| dir.join(dirname::VENDOR))
+        .filter(|vendor_dir| vendor_dir.is_dir())
+        .find_map(|vendor_dir| find_package_dir(&vendor_dir, spec))
+}
+
+fn find_in_registry(spec: &DependencySpec) -> Option<PathBuf> {
+    let cargo_home = env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cargo")))?;
+
+    // Every registry has its own folder e.g `github.com-1ecc6299db9ec823`
+    fs::read_dir(cargo_home.join("registry").join("src"))
+        .ok()?
+        .flatten()
+        .filter_map(|registry| find_package_dir(&registry.path(), spec))
+        .max_by(|a, b| compare_versions(&get_dir_version(a), &get_dir_version(b)))
+}
+
+/// Find the directory of the package with the locked or the highest matching version, directories
+/// are named either `<package>` or `<package>-<version>`
+fn find_package_dir(dir: &Path, spec: &DependencySpec) -> Option<PathBuf> {
+    fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.join(filename::CARGO_TOML).exists())
+        .filter(|path| {
+            let name = path.file_name().unwrap().to_str().unwrap();
+            match split_dir_name(name) {
+                (package, Some(version)) => {
+                    package == spec.package
+                        && match (&spec.locked_version, &spec.version) {
+                            (Some(locked_version), _) => version == locked_version,
+                            (None, Some(req)) => matches_version(req, version),
+                            (None, None) => true,
+                        }
+                }
+                (package, None) => package == spec.package,
+            }
+        })
+        .max_by(|a, b| compare_versions(&get_dir_version(a), &get_dir_version(b)))
+}
+
+/// Get the package versions from the closest `Cargo.lock`, package name -> versions
+fn get_locked_versions(crate_dir: &Path) -> HashMap<String, Vec<String>> {
+    let mut versions = HashMap::<String, Vec<String>>::new();
+    let lock_file = crate_dir
+        .ancestors()
+        .map(|dir| dir.join(filename::CARGO_LOCK))
+        .find(|path| path.exists())
+        .and_then(|path| read_manifest(&path));
+    let packages = lock_file
+        .as_ref()
+        .and_then(|lock_file| lock_file.get("package"))
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten();
+    for package in packages {
+        let name = package.get("name").and_then(Value::as_str);
+        let version = package.get("version").and_then(Value::as_str);
+        if let (Some(name), Some(version)) = (name, version) {
+            versions
+                .entry(name.to_owned())
+                .or_default()
+                .push(version.to_owned());
+        }
+    }
+
+    versions
+}
+
+/// Get the locked version of the dependency, the highest matching one is used if the package is
+/// locked with more than one version
+fn get_locked_version(
+    locked_versions: &HashMap<String, Vec<String>>,
+    spec: &DependencySpec,
+) -> Option<String> {
+    locked_versions
+        .get(&spec.package)?
+        .iter()
+        .filter(|version| {
+            spec.version
+                .as_ref()
+                .map(|req| matches_version(req, version))
+                .unwrap_or(true)
+        })
+        .max_by(|a, b| compare_versions(&parse_version(a), &parse_version(b)))
+        .cloned()
+}
+
+fn get_package_name(crate_dir: &Path) -> Option<String> {
+    read_manifest(&crate_dir.join(filename::CARGO_TOML))?
+        .get("package")?
+        .get("name")?
+        .as_str()
+        .map(str::to_owned)
+}
+
+/// Split the directory name to package name and version e.g `spl-token-3.5.0`
+fn split_dir_name(name: &str) -> (&str, Option<&str>) {
+    name.match_indices('-')
+        .map(|(i, _)| i)
+        .find(|&i| {
+            name.get(i + 1..)
+                .and_then(|version| version.chars().next())
+                .map(|c| c.is_ascii_digit())
+                .unwrap_or(false)
+        })
+        .map(|i| (&name[..i], Some(&name[i + 1..])))
+        .unwrap_or((name, None))
+}
+
+fn get_dir_version(path: &Path) -> Vec<u64> {
+    let name = path.file_name().unwrap().to_str().unwrap();
+    split_dir_name(name)
+        .1
+        .map(parse_version)
+        .unwrap_or_default()
+}
+
+/// Parse the numeric parts of the version e.g `1.14.6-alpha` -> `[1, 14, 6]`
+fn parse_version(version: &str) -> Vec<u64> {
+    version
+        .split(['-', '+'])
+        .next()
+        .unwrap()
+        .split('.')
+        .map_while(|part| part.trim().parse().ok())
+        .collect()
+}
+
+fn compare_versions(a: &[u64], b: &[u64]) -> Ordering {
+    a.iter().cmp(b.iter())
+}
+
+/// Check whether the version matches every comparator of the requirement e.g `^3.5`, `=1.0`,
+/// `>=0.9, <2`
+fn matches_version(req: &str, version: &str) -> bool {
+    req.split(',')
+        .all(|comparator| matches_comparator(comparator.trim(), version))
+}
+
+fn matches_comparator(comparator: &str, version: &str) -> bool {
+    let op_len = comparator
+        .find(|c: char| c.is_ascii_digit())
+        .unwrap_or(comparator.len());
+    let (op, req_version) = comparator.split_at(op_len);
+    let req_version = parse_version(req_version);
+    let version = parse_version(version);
+    if req_version.is_empty() {
+        return true;
+    }
+
+    let is_greater_or_equal = compare_versions(&version, &req_version) != Ordering::Less;
+    match op.trim() {
+        "=" => version.iter().zip(&req_version).all(|(a, b)| a == b),
+        ">=" => is_greater_or_equal,
+        ">" => compare_versions(&version, &req_version) == Ordering::Greater,
+        "<" => compare_versions(&version, &req_version) == Ordering::Less,
+        "<=" => compare_versions(&version, &req_version) != Ordering::Greater,
+        "~" => {
+            is_greater_or_equal
+                && version
+                    .iter()
+                    .zip(&req_version)
+                    .take(2)
+                    .all(|(a, b)| a == b)
+        }
+        // Caret requirements e.g `3.5` or `^0.3`, versions are compatible until the first non-zero
+        // part changes
+        _ => {
+            let compatible_len = req_version
+                .iter()
+                .position(|&part| part != 0)
+                .map(|i| i + 1)
+                .unwrap_or(req_version.len());
+            is_greater_or_equal
+                && version
+                    .iter()
+                    .zip(&req_version)
+                    .take(compatible_len)
+                    .all(|(a, b)| a == b)
+        }
+    }
+}
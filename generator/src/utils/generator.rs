@@ -1,20 +1,112 @@
-use std::{fs, path::Path};
+use std::{
+    fs::{self, DirEntry},
+    path::{Path, PathBuf},
+};
 
 use serde_derive::Deserialize;
 
+use super::{
+    common::{debug, open_files, warn},
+    dependency::find_workspace_root,
+    known_accounts::{KnownAccount, KnownAccounts},
+};
+use crate::{
+    constants::common::{dirname, filename},
+    generator::GeneratorResult,
+    parser::instruction::InstructionTag,
+};
+
 #[derive(Deserialize)]
 struct CargoToml {
-    package: Package,
+    package: Option<Package>,
+    workspace: Option<Workspace>,
+    lib: Option<Lib>,
 }
 
 #[derive(Deserialize)]
 pub struct Package {
     pub name: Option<String>,
-    pub description: Option<String>,
-    pub version: Option<String>,
-    pub license: Option<String>,
-    pub repository: Option<String>,
-    pub authors: Option<Vec<String>>,
+    pub description: Option<MaybeInherited<String>>,
+    pub version: Option<MaybeInherited<String>>,
+    pub license: Option<MaybeInherited<String>>,
+    pub repository: Option<MaybeInherited<String>>,
+    pub authors: Option<MaybeInherited<Vec<String>>>,
+    pub metadata: Option<Metadata>,
+}
+
+#[derive(Deserialize)]
+pub struct Metadata {
+    #[serde(rename = "native-to-anchor")]
+    pub native_to_anchor: Option<ProgramConfig>,
+}
+
+/// Program specific configuration from `[package.metadata.native-to-anchor]`
+#[derive(Default, Deserialize)]
+pub struct ProgramConfig {
+    #[serde(flatten)]
+    pub layout: SourceLayout,
+    /// Encoding of the instruction tag e.g `instruction-tag = "u32"`, it's inferred from the
+    /// instruction enum if not set
+    #[serde(rename = "instruction-tag")]
+    pub instruction_tag: Option<InstructionTag>,
+    /// Features to enable while evaluating `#[cfg(...)]` attributes e.g `features = ["no-entrypoint"]`
+    pub features: Option<Vec<String>>,
+    /// Whether to enable the `default` feature, `true` by default
+    #[serde(rename = "default-features")]
+    pub default_features: Option<bool>,
+    /// Programs and sysvars with fixed addresses in addition to the built-in ones
+    #[serde(rename = "known-accounts")]
+    pub known_accounts: Option<Vec<KnownAccount>>,
+}
+
+/// Paths of the program items, set from `[package.metadata.native-to-anchor]` e.g
+///
+/// ```toml
+/// [package.metadata.native-to-anchor]
+/// instruction = "src/instructions/mod.rs"
+/// state = "src/accounts"
+/// error = "src/errors.rs"
+/// ```
+///
+/// Paths are relative to the crate directory and can be either files or directories. Items are
+/// searched in the whole crate if the path is not set.
+#[derive(Default, Deserialize)]
+pub struct SourceLayout {
+    /// Where the instruction enum is defined
+    pub instruction: Option<PathBuf>,
+    /// Where the account structs are defined
+    pub state: Option<PathBuf>,
+    /// Where the error enum is defined
+    pub error: Option<PathBuf>,
+}
+
+/// Package field that can be inherited from the workspace e.g `version.workspace = true`
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum MaybeInherited<T> {
+    Value(T),
+    Inherited { workspace: bool },
+}
+
+#[derive(Deserialize)]
+struct Workspace {
+    package: Option<WorkspacePackage>,
+}
+
+/// `[workspace.package]` fields that members can inherit
+#[derive(Default, Deserialize)]
+struct WorkspacePackage {
+    description: Option<String>,
+    version: Option<String>,
+    license: Option<String>,
+    repository: Option<String>,
+    authors: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct Lib {
+    #[serde(rename = "crate-type")]
+    crate_type: Option<Vec<String>>,
 }
 
 pub struct ProgramInfo {
@@ -24,6 +116,13 @@ pub struct ProgramInfo {
     pub license: String,
     pub repository: String,
     pub author: String,
+    pub layout: SourceLayout,
+    pub instruction_tag: Option<InstructionTag>,
+    /// Features the program is compiled with
+    pub features: Vec<String>,
+    pub default_features: bool,
+    /// Programs and sysvars that have fixed addresses
+    pub known_accounts: KnownAccounts,
 }
 
 impl Default for ProgramInfo {
@@ -35,24 +134,86 @@ impl Default for ProgramInfo {
             description: "".into(),
             author: "".into(),
             repository: "".into(),
+            layout: SourceLayout::default(),
+            instruction_tag: None,
+            features: vec![],
+            default_features: true,
+            known_accounts: KnownAccounts::default(),
         }
     }
 }
 
-pub fn get_program_info(cargo_toml_path: &Path) -> ProgramInfo {
-    let cargo_toml: CargoToml =
-        toml::from_str(&fs::read_to_string(cargo_toml_path).unwrap()).unwrap();
-    let package = cargo_toml.package;
+impl<T> MaybeInherited<T> {
+    fn resolve(self, inherited: Option<T>) -> Option<T> {
+        match self {
+            Self::Value(value) => Some(value),
+            Self::Inherited { workspace: true } => inherited,
+            Self::Inherited { workspace: false } => None,
+        }
+    }
+}
+
+/// Get the program info from `Cargo.toml`.
+///
+/// Returns `None` if the manifest doesn't belong to a program e.g virtual workspace manifests and
+/// crates without `cdylib` crate type or entrypoint.
+pub fn get_program_info(cargo_toml_path: &Path) -> Option<ProgramInfo> {
+    let cargo_toml = match fs::read_to_string(cargo_toml_path)
+        .map_err(|e| e.to_string())
+        .and_then(|content| toml::from_str::<CargoToml>(&content).map_err(|e| e.to_string()))
+    {
+        Ok(cargo_toml) => cargo_toml,
+        Err(e) => {
+            warn(format!("Skipping {cargo_toml_path:?}: {e}"));
+            return None;
+        }
+    };
+
+    let package = match cargo_toml.package {
+        Some(package) => package,
+        None => {
+            debug(format!("Skipping workspace manifest {cargo_toml_path:?}."));
+            return None;
+        }
+    };
+
+    let crate_dir = cargo_toml_path.parent().unwrap();
+    let name = package.name.unwrap_or(ProgramInfo::default().name);
+    if !is_program(cargo_toml.lib, crate_dir) {
+        warn(format!(
+            "Skipping '{name}': not a program, no `cdylib` crate type or entrypoint found."
+        ));
+        return None;
+    }
+
+    // Fields that are inherited from the workspace
+    let workspace_package = match cargo_toml.workspace {
+        // Root package of the workspace
+        Some(workspace) => workspace.package,
+        None => find_workspace_root(crate_dir).and_then(get_workspace_package),
+    }
+    .unwrap_or_default();
 
-    ProgramInfo {
-        name: package.name.unwrap_or(ProgramInfo::default().name),
-        description: package
-            .description
+    let config = package
+        .metadata
+        .and_then(|metadata| metadata.native_to_anchor)
+        .unwrap_or_default();
+
+    let resolve = |field: Option<MaybeInherited<String>>, inherited: Option<String>| {
+        field.and_then(|field| field.resolve(inherited))
+    };
+
+    Some(ProgramInfo {
+        name,
+        description: resolve(package.description, workspace_package.description)
             .unwrap_or(ProgramInfo::default().description),
-        version: package.version.unwrap_or(ProgramInfo::default().version),
-        license: package.license.unwrap_or(ProgramInfo::default().license),
+        version: resolve(package.version, workspace_package.version)
+            .unwrap_or(ProgramInfo::default().version),
+        license: resolve(package.license, workspace_package.license)
+            .unwrap_or(ProgramInfo::default().license),
         author: package
             .authors
+            .and_then(|authors| authors.resolve(workspace_package.authors))
             .unwrap_or(vec![ProgramInfo::default().author])
             .iter()
             .map(|a| a.to_owned())
@@ -60,9 +221,48 @@ pub fn get_program_info(cargo_toml_path: &Path) -> ProgramInfo {
                 "" => cur,
                 _ => format!("{acc}, {cur}"),
             })
-            .unwrap(),
-        repository: package
-            .repository
+            .unwrap_or_default(),
+        repository: resolve(package.repository, workspace_package.repository)
             .unwrap_or(ProgramInfo::default().repository),
+        layout: SourceLayout {
+            instruction: config.layout.instruction.map(|path| crate_dir.join(path)),
+            state: config.layout.state.map(|path| crate_dir.join(path)),
+            error: config.layout.error.map(|path| crate_dir.join(path)),
+        },
+        instruction_tag: config.instruction_tag,
+        features: config.features.unwrap_or_default(),
+        default_features: config.default_features.unwrap_or(true),
+        known_accounts: KnownAccounts::new(config.known_accounts.unwrap_or_default()),
+    })
+}
+
+fn get_workspace_package(workspace_root: impl AsRef<Path>) -> Option<WorkspacePackage> {
+    let content = fs::read_to_string(workspace_root.as_ref().join(filename::CARGO_TOML)).ok()?;
+    toml::from_str::<CargoToml>(&content)
+        .ok()?
+        .workspace?
+        .package
+}
+
+/// Programs are compiled as `cdylib` or have an entrypoint
+fn is_program(lib: Option<Lib>, crate_dir: &Path) -> bool {
+    let is_cdylib = lib
+        .and_then(|lib| lib.crate_type)
+        .map(|crate_type| crate_type.iter().any(|ty| ty == "cdylib"))
+        .unwrap_or(false);
+    if is_cdylib {
+        return true;
     }
+
+    let mut has_entrypoint = false;
+    let mut f = |dir_entry: DirEntry| -> GeneratorResult {
+        if !has_entrypoint {
+            let content = fs::read_to_string(dir_entry.path()).unwrap_or_default();
+            has_entrypoint = content.contains("entrypoint!");
+        }
+        Ok(())
+    };
+    open_files(crate_dir.join(dirname::SRC), &mut f).ok();
+
+    has_entrypoint
 }
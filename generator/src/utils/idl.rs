@@ -1,50 +1,54 @@
-use anchor_syn::idl::IdlType;
+use std::collections::HashMap;
+
+use anchor_syn::{
+    codegen::program::common::{sighash, SIGHASH_GLOBAL_NAMESPACE},
+    idl::IdlType,
+};
 use colored::Colorize;
+use heck::MixedCase;
 use log::info;
+use syn::Item;
 
 use crate::{
-    constants::idl::ACCOUNT_SPLIT,
-    utils::common::{get_item, get_item_indices, warn},
+    constants::{
+        idl::{ACCOUNT_SPLIT, INT_TYPES},
+        known_types::PRELUDE_SYSVARS,
+    },
+    model::{ContextAccount, PdaModel, ProgramModel, SeedModel, TypeKind},
+    parser::{
+        builder::{get_builder_account_metas, AccountMetaKind, BuilderAccountMeta},
+        common::get_docs,
+        docs::get_doc_account_metas,
+        eval::eval_array_lens,
+        instruction::{InstructionEnum, InstructionTag, InstructionVariant},
+        module::ModuleGraph,
+        pda::{Pda, PdaSeed},
+        processor::get_processor_account_metas,
+    },
+    utils::{
+        common::{debug, get_inside_item_line, snake_from_pascal, warn},
+        known_accounts::KnownAccounts,
+    },
 };
 
-#[macro_export]
-macro_rules! push_or_continue {
-    ($contexts:expr, $maybe_context:expr) => {{
-        match $maybe_context {
-            Some(c) => $contexts.push_str(&c),
-            None => continue,
-        };
-    }};
-}
-
-pub fn convert_account_name(name_uncut: impl AsRef<str>) -> String {
-    let mut account_name = name_uncut.as_ref();
+pub fn convert_account_name(name_uncut: impl AsRef<str>, known_accounts: &KnownAccounts) -> String {
+    // Addresses that are returned from helpers are named after the helper e.g
+    // `get_metadata_address(mint)` -> `metadata`
+    let helper_name = get_helper_account_name(name_uncut.as_ref());
+    let name_uncut = helper_name.as_deref().unwrap_or(name_uncut.as_ref());
+    let mut account_name = name_uncut;
 
     for split in ACCOUNT_SPLIT {
-        let split_parts = name_uncut
-            .as_ref()
-            .trim()
-            .split(split)
-            .collect::<Vec<&str>>();
+        let split_parts = name_uncut.trim().split(split).collect::<Vec<&str>>();
         if split_parts.len() > 1 {
             account_name = split_parts[0];
             break;
         }
     }
 
-    account_name = if account_name.contains("system_program") {
-        "system_program"
-    } else if account_name.contains("token_program") || account_name.contains("spl_token") {
-        "token_program"
-    } else if account_name.contains("bpf_loader_upgradeable") {
-        "bpf_loader_upgradeable"
-    } else if account_name.contains("::rent") {
-        "rent"
-    } else if account_name.contains("clock") {
-        "clock"
-    } else {
-        account_name
-    };
+    if let Some(known_account) = known_accounts.find(account_name) {
+        account_name = &known_account.name;
+    }
 
     account_name = account_name.trim();
 
@@ -58,30 +62,302 @@ pub fn convert_account_name(name_uncut: impl AsRef<str>) -> String {
     account_name.to_owned()
 }
 
+/// Get the account name from the helper function that the address is returned from, `None` if the
+/// name is not a call with arguments e.g `spl_token::id()`
+fn get_helper_account_name(name: &str) -> Option<String> {
+    let (func, args) = name.trim().split_once('(')?;
+    if args.trim_end_matches(')').trim().is_empty() {
+        return None;
+    }
+
+    let fn_name = func.rsplit("::").next()?.trim();
+    let fn_name = ["get_", "find_", "derive_"]
+        .iter()
+        .fold(fn_name, |name, prefix| name.trim_start_matches(prefix));
+    let fn_name = ["_and_bump", "_address", "_pda"]
+        .iter()
+        .fold(fn_name, |name, suffix| name.trim_end_matches(suffix));
+    Some(fn_name.to_owned())
+}
+
+/// Get the program id from the `declare_id!` macro of the content
+pub fn get_program_id(content: &str) -> Option<&str> {
+    let index = content.find("declare_id!")?;
+    get_inside_item_line(content.get(index..)?, '"')
+}
+
+/// Explicit discriminants of the instructions and the enum variants.
+///
+/// Anchor IDLs don't have discriminants, they are written to the IDL as `discriminant` fields when
+/// they are not the same as the positions of the instructions or the variants.
+#[derive(Default)]
+pub struct Discriminants {
+    /// Instruction name -> discriminant
+    instructions: HashMap<String, u64>,
+    /// Enum name -> variant name -> discriminant
+    enums: HashMap<String, HashMap<String, u64>>,
+    /// Encoding of the instruction discriminants
+    instruction_tag: InstructionTag,
+}
+
+impl Discriminants {
+    pub fn instruction_tag(&self) -> InstructionTag {
+        self.instruction_tag
+    }
+
+    /// Get the discriminant of the instruction, defaults to the index of the instruction
+    pub fn instruction(&self, name: &str, index: usize) -> u64 {
+        self.instructions.get(name).copied().unwrap_or(index as u64)
+    }
+
+    /// Get the discriminant of the enum variant, defaults to the index of the variant
+    pub fn variant(&self, enum_name: &str, variant_name: &str, index: usize) -> u64 {
+        self.enums
+            .get(enum_name)
+            .and_then(|variants| variants.get(variant_name))
+            .copied()
+            .unwrap_or(index as u64)
+    }
+
+    /// Get the discriminants of the instructions and the enum variants from the model, names of
+    /// the instructions are the IDL names e.g `initializeMint`
+    pub fn from_model(model: &ProgramModel) -> Self {
+        let instructions = model
+            .instructions
+            .iter()
+            .map(|ix| (snake_from_pascal(&ix.name).to_mixed_case(), ix.discriminant))
+            .collect();
+
+        let enums = model
+            .accounts
+            .iter()
+            .chain(&model.types)
+            .filter_map(|type_def| match &type_def.kind {
+                TypeKind::Enum(variants) => {
+                    let values = variants
+                        .iter()
+                        .filter_map(|variant| {
+                            Some((variant.name.to_owned(), variant.discriminant?))
+                        })
+                        .collect::<HashMap<_, _>>();
+                    Some((type_def.name.to_owned(), values))
+                }
+                _ => None,
+            })
+            .filter(|(_, values)| !values.is_empty())
+            .collect();
+
+        Self {
+            instructions,
+            enums,
+            instruction_tag: model.instruction_tag,
+        }
+    }
+}
+
+/// Whether every discriminant is the same as its index
+pub fn is_positional(discriminants: &[(String, u64)]) -> bool {
+    discriminants
+        .iter()
+        .enumerate()
+        .all(|(i, (_, discriminant))| i as u64 == *discriminant)
+}
+
+/// Get the discriminant of the instruction from the discriminant of its variant, sighash
+/// discriminants are the little endian value of the sighash bytes
+pub fn get_instruction_discriminant(tag: InstructionTag, name: &str, discriminant: u64) -> u64 {
+    match tag {
+        InstructionTag::Sighash => {
+            u64::from_le_bytes(sighash(SIGHASH_GLOBAL_NAMESPACE, &snake_from_pascal(name)))
+        }
+        _ => discriminant,
+    }
+}
+
 pub fn is_type_defined(type_name: impl AsRef<str>) -> bool {
-    match type_name.as_ref().parse::<IdlType>() {
-        Ok(t) => match t {
-            IdlType::Defined(_) => true,
-            _ => false,
-        },
-        Err(_) => false,
+    matches!(
+        type_name.as_ref().parse::<IdlType>(),
+        Ok(IdlType::Defined(_))
+    )
+}
+
+/// Get the doc comments of the struct or enum
+pub fn get_item_docs(item: &Item) -> Vec<String> {
+    match item {
+        Item::Struct(item_struct) => get_docs(&item_struct.attrs),
+        Item::Enum(item_enum) => get_docs(&item_enum.attrs),
+        _ => vec![],
     }
 }
 
-pub fn create_context(
-    content: &str,
-    all_content: &str,
-    function_name: &str,
+/// Convert the Rust type to the IDL type the way Anchor does e.g `Box<Config>` -> `Config`
+pub fn get_idl_type(ty: impl AsRef<str>) -> IdlType {
+    let mut ty = ty.as_ref().to_owned();
+    ty.retain(|c| !c.is_whitespace());
+    let inside = ty
+        .strip_prefix("Box<")
+        .and_then(|inside| inside.strip_suffix('>'))
+        .unwrap_or(&ty);
+    inside
+        .parse()
+        .unwrap_or_else(|_| IdlType::Defined(inside.to_owned()))
+}
+
+/// Convert the IDL type to the Rust type of the dummy program e.g `publicKey` -> `Pubkey`
+pub fn get_rust_type(ty: &IdlType) -> String {
+    match ty {
+        IdlType::Bool => "bool".into(),
+        IdlType::U8 => "u8".into(),
+        IdlType::I8 => "i8".into(),
+        IdlType::U16 => "u16".into(),
+        IdlType::I16 => "i16".into(),
+        IdlType::U32 => "u32".into(),
+        IdlType::I32 => "i32".into(),
+        IdlType::F32 => "f32".into(),
+        IdlType::U64 => "u64".into(),
+        IdlType::I64 => "i64".into(),
+        IdlType::F64 => "f64".into(),
+        IdlType::U128 => "u128".into(),
+        IdlType::I128 => "i128".into(),
+        IdlType::Bytes => "Vec<u8>".into(),
+        IdlType::String => "String".into(),
+        IdlType::PublicKey => "Pubkey".into(),
+        IdlType::Defined(name) => name.to_owned(),
+        IdlType::Option(inside) => format!("Option<{}>", get_rust_type(inside)),
+        IdlType::Vec(inside) => format!("Vec<{}>", get_rust_type(inside)),
+        IdlType::Array(inside, len) => format!("[{}; {len}]", get_rust_type(inside)),
+    }
+}
+
+/// Cross-check the account metas of the builder function with the accounts from the doc comments
+/// and add the descriptions of the accounts to the account metas.
+///
+/// Builder functions are preferred if they are different.
+fn check_doc_account_metas(
     struct_name: &str,
-) -> Option<String> {
-    let mut context = String::new();
+    mut account_metas: Vec<BuilderAccountMeta>,
+    doc_account_metas: Vec<BuilderAccountMeta>,
+) -> Vec<BuilderAccountMeta> {
+    if doc_account_metas.is_empty() {
+        return account_metas;
+    }
+    if account_metas.len() != doc_account_metas.len() {
+        debug(format!(
+            "{struct_name}: builder function has {} accounts but the docs have {} accounts.",
+            account_metas.len(),
+            doc_account_metas.len()
+        ));
+        return account_metas;
+    }
+
+    for (i, (account_meta, doc_account_meta)) in
+        account_metas.iter_mut().zip(doc_account_metas).enumerate()
+    {
+        // Dynamic flags can be either of the documented flags
+        let is_writable_different = !account_meta.is_dynamic_writable
+            && account_meta.is_writable != doc_account_meta.is_writable;
+        let is_signer_different =
+            !account_meta.is_dynamic_signer && account_meta.is_signer != doc_account_meta.is_signer;
+        if is_writable_different || is_signer_different {
+            warn(format!(
+                "{struct_name}: account {i} `{}` is different from its docs \"{}\", using the builder function.",
+                account_meta.source, doc_account_meta.source
+            ));
+        }
+        account_meta.description = doc_account_meta.description;
+    }
+
+    account_metas
+}
+
+/// Add the flags and the descriptions from the docs to the accounts that are inferred from the
+/// processor, the processor may not check every flag e.g writable accounts that are passed to CPIs
+fn merge_doc_account_metas(
+    mut account_metas: Vec<BuilderAccountMeta>,
+    doc_account_metas: Vec<BuilderAccountMeta>,
+) -> Vec<BuilderAccountMeta> {
+    if account_metas.len() != doc_account_metas.len() {
+        return account_metas;
+    }
+
+    for (account_meta, doc_account_meta) in account_metas.iter_mut().zip(doc_account_metas) {
+        account_meta.is_writable |= doc_account_meta.is_writable;
+        account_meta.is_signer |= doc_account_meta.is_signer;
+        account_meta.description = doc_account_meta.description;
+    }
+
+    account_metas
+}
+
+/// Remove the module paths from the code e.g `pub mint: spl_token::state::Mint,` ->
+/// `pub mint: Mint,` because every type is defined at the root of the dummy program
+pub fn remove_module_paths(code: impl AsRef<str>) -> String {
+    let mut result = String::new();
+    // Start index of the current identifier in the result
+    let mut ident_start = None;
+    let mut chars = code.as_ref().chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == ':' && chars.peek() == Some(&':') {
+            chars.next();
+            if let Some(start) = ident_start.take() {
+                result.truncate(start);
+            }
+            continue;
+        }
+
+        if c.is_alphanumeric() || c == '_' {
+            ident_start.get_or_insert(result.len());
+        } else {
+            ident_start = None;
+        }
+        result.push(c);
+    }
+
+    result
+}
+
+pub fn create_context(
+    modules: &ModuleGraph,
+    instruction_enum: &InstructionEnum,
+    variant: &InstructionVariant,
+    known_accounts: &KnownAccounts,
+) -> Option<Vec<ContextAccount>> {
+    let module = &instruction_enum.module;
+    let struct_name = &variant.name;
+    let function_name = snake_from_pascal(struct_name);
     info!("Creating context: {}", struct_name.bold());
 
-    // Get the function
-    let function = match content.find(&format!("pub fn {function_name}(")) {
-        Some(start_index) => get_item(content.get(start_index..).unwrap(), '{'),
-        None => match all_content.find(&format!("pub fn {function_name}(")) {
-            Some(start_index) => get_item(all_content.get(start_index..).unwrap(), '{'),
+    // Get the account metas from the instruction builder function. If the function doesn't exist,
+    // the accounts are inferred from the processor and SPL style account lists in the doc comments
+    // are used if the processor doesn't exist either.
+    let doc_account_metas = get_doc_account_metas(&variant.docs);
+    // Docs are preferred over a processor without `next_account_info` calls since it may index the
+    // accounts directly e.g `accounts[0]`
+    let has_doc_accounts = !doc_account_metas.is_empty();
+    let processor_account_metas = || {
+        get_processor_account_metas(modules, instruction_enum, struct_name)
+            .filter(|metas| !metas.is_empty() || !has_doc_accounts)
+    };
+    let account_metas = match get_builder_account_metas(modules, module, &function_name) {
+        Some(account_metas) => {
+            let account_metas =
+                check_doc_account_metas(struct_name, account_metas, doc_account_metas);
+            merge_processor_pdas(account_metas, processor_account_metas())
+        }
+        None => match processor_account_metas() {
+            Some(account_metas) => {
+                debug(format!(
+                    "Function '{function_name}' not found, using the accounts from the processor of '{struct_name}'."
+                ));
+                merge_doc_account_metas(account_metas, doc_account_metas)
+            }
+            None if !doc_account_metas.is_empty() => {
+                debug(format!(
+                "Function '{function_name}' not found, using the accounts from the docs of '{struct_name}'."
+            ));
+                doc_account_metas
+            }
             None => {
                 warn(format!("Function '{function_name}' not found. This may make instruction layouts invalid, check and fix if it's needed."));
                 return None;
@@ -89,143 +365,193 @@ pub fn create_context(
         },
     };
 
-    // Get default accounts
-    let mut default_accounts = vec![];
-
-    // Get vec! start index
-    match function.find("vec!") {
-        Some(vec_start_index) => {
-            // vec!
-            let function_starting_from_vec = function.split_at(vec_start_index).1;
-            let vec_end_index = function_starting_from_vec.find(']').unwrap();
-            let default_account_metas =
-                function_starting_from_vec.get(..vec_end_index - 1).unwrap();
-
-            let account_meta_split = default_account_metas
-                .split("AccountMeta")
-                .filter(|str| str.contains("::"))
-                .map(|str| str.replace('*', ""))
-                .collect::<Vec<String>>();
-
-            for account_meta_str in &account_meta_split {
-                let name_start_index = account_meta_str.find('(').unwrap() + 1;
-                let name_end_index = account_meta_str.find(',').unwrap();
-                let raw_account_name = account_meta_str
-                    .get(name_start_index..name_end_index)
-                    .unwrap();
-
-                default_accounts.push(convert_account_name(raw_account_name).to_owned());
-            }
+    let mut fields = vec![];
+    let mut account_names = vec![];
+    for (i, account_meta) in account_metas.iter().enumerate() {
+        debug(format!("{struct_name}: {}", account_meta.source));
+
+        let mut account_name = convert_account_name(
+            account_meta.pubkey.replace(['*', '&', ' '], ""),
+            known_accounts,
+        );
+        if account_name.contains("Pubkey::default") {
+            fields.push(None);
+            continue;
         }
-        None => {
-            // Vec::with_capacity();
-            let vec_string = "Vec::with_capacity";
-            let function_starting_from_vec =
-                function.split_at(function.find(vec_string).unwrap()).1;
-            let inside_start_index = function_starting_from_vec.find('(').unwrap() + 1;
-            let inside_end_index = function_starting_from_vec.find(");").unwrap();
-
-            let total_capacity = function_starting_from_vec
-                .get(inside_start_index..inside_end_index)
-                .unwrap()
-                .split('+')
-                .map(|str| str.trim())
-                .filter(|str| str.parse::<usize>().is_ok())
-                .map(|number_str| number_str.parse::<usize>().unwrap())
-                .reduce(|accum, number| number + accum)
-                .unwrap_or_default();
-
-            let account_meta_indices =
-                get_item_indices(function_starting_from_vec, "AccountMeta::");
-            let default_account_meta_indices = account_meta_indices
-                .iter()
-                .filter(|(current_index, _)| *current_index < total_capacity)
-                .map(|(_, function_index)| *function_index)
-                .collect::<Vec<usize>>();
-
-            for i in default_account_meta_indices {
-                let starting_from_account_meta = function_starting_from_vec.get(i..).unwrap();
-                // Get inside account meta
-                let account_name_start_index = starting_from_account_meta.find('(').unwrap() + 1;
-                let account_name_end_index = starting_from_account_meta.find(',').unwrap();
-                let raw_account_name = starting_from_account_meta
-                    .get(account_name_start_index..account_name_end_index)
-                    .unwrap()
-                    .replace('*', "");
-
-                default_accounts.push(convert_account_name(&raw_account_name).to_owned());
-            }
-        }
-    };
 
-    // Get all account metas
-    let account_meta_indices = get_item_indices(&function, "AccountMeta::");
+        let account_type = match (account_meta.is_signer, &account_meta.sysvar) {
+            (true, _) => "Signer<'info>".into(),
+            (false, Some(sysvar)) if PRELUDE_SYSVARS.contains(&sysvar.as_str()) => {
+                format!("Sysvar<'info, {sysvar}>")
+            }
+            (false, _) => match known_accounts.get(&account_name) {
+                Some(acc) => acc
+                    .ty
+                    .to_owned()
+                    .unwrap_or_else(|| "UncheckedAccount<'info>".into()),
+                None => "AccountInfo<'info>".into(),
+            },
+        };
 
-    for (index, meta_index) in account_meta_indices {
-        let start_from_account_meta = function.get(meta_index..).unwrap();
-        // Get only account meta
-        let account_meta = get_item(start_from_account_meta, '(');
+        // Repeated accounts are the variadic accounts of the instruction, which only works if they
+        // are the last accounts, otherwise they are commented out.
+        let is_repeated_account = account_meta.kind == AccountMetaKind::Repeated;
+        let is_commented_out = is_repeated_account
+            && account_metas[i + 1..]
+                .iter()
+                .any(|meta| meta.kind != AccountMetaKind::Repeated);
+        if is_commented_out {
+            warn(format!(
+                "{struct_name}: variadic account `{}` is not one of the last accounts, it's commented out.",
+                account_meta.source
+            ));
+        } else if is_repeated_account && !account_name.ends_with('s') {
+            // Name of the group e.g `signer` -> `signers`
+            account_name.push('s');
+        }
 
-        let name_uncut = account_meta
-            .get(account_meta.find('(').unwrap()..account_meta.find(',').unwrap())
-            .unwrap()
-            .replace('*', "")
-            .replace('(', "");
+        // Names must be unique e.g multiple accounts in the same loop
+        if account_names.contains(&account_name) {
+            account_name = format!("{account_name}_{i}");
+        }
+        account_names.push(account_name.clone());
+
+        // Signers are given by the callers even if they have a known address
+        let address = match account_meta.is_signer || is_repeated_account {
+            true => None,
+            false => known_accounts
+                .get(&account_name)
+                .map(|acc| acc.address.to_owned()),
+        };
+        fields.push(Some(ContextAccount {
+            // Commented out accounts can't have docs
+            docs: account_meta
+                .description
+                .to_owned()
+                .filter(|_| !is_commented_out)
+                .into_iter()
+                .collect(),
+            ty: account_type,
+            is_mut: account_meta.is_writable,
+            is_signer: account_meta.is_signer,
+            is_optional: account_meta.kind == AccountMetaKind::Conditional,
+            is_variadic: is_repeated_account,
+            is_dynamic_signer: account_meta.is_dynamic_signer,
+            is_dynamic_mut: account_meta.is_dynamic_writable,
+            owner: account_meta.owner.to_owned(),
+            address,
+            pda: None,
+            is_commented_out,
+            name: account_name,
+        }));
+    }
 
-        let account_name = convert_account_name(&name_uncut);
+    // Seeds can refer to any account of the context, they are added once every account is named
+    let args = variant
+        .args
+        .iter()
+        .map(|arg| {
+            let ty = get_idl_type(eval_array_lens(&arg.ty, module, modules));
+            (arg.name.as_str(), ty)
+        })
+        .collect::<Vec<_>>();
+    for (i, account_meta) in account_metas.iter().enumerate() {
+        // Signers can't be program derived addresses
+        let pda = match (&account_meta.pda, &fields[i]) {
+            (Some(pda), Some(field)) if !account_meta.is_signer && !field.is_commented_out => pda,
+            _ => continue,
+        };
+        match get_pda_seeds(pda, &account_metas, &fields, &args, known_accounts) {
+            Some(pda) => fields[i].as_mut().unwrap().pda = Some(pda),
+            None => debug(format!(
+                "{struct_name}: seeds of account {i} `{}` can't be resolved: {pda:?}",
+                account_meta.source
+            )),
+        }
+    }
 
-        let is_mut = !account_meta.contains("new_readonly(");
-        let is_signer = !account_meta.contains(", false");
+    Some(fields.into_iter().flatten().collect())
+}
 
-        // Accounts beginning
-        if index == 0 {
-            context.push_str(&format!(
-                r#"#[derive(Accounts)]
-pub struct {struct_name}<'info> {{
-"#
-            ));
+/// Resolve the seeds of the program derived address to the accounts and the arguments of the
+/// instruction e.g `[b"vault", owner]`, `None` if a seed can't be resolved.
+fn get_pda_seeds(
+    pda: &Pda,
+    account_metas: &[BuilderAccountMeta],
+    fields: &[Option<ContextAccount>],
+    args: &[(&str, IdlType)],
+    known_accounts: &KnownAccounts,
+) -> Option<PdaModel> {
+    // Name of the account that the seed refers to
+    let account_field = |seed: &PdaSeed| {
+        let index = match seed {
+            PdaSeed::Account(index) => *index,
+            PdaSeed::Var(var) => account_metas.iter().position(|meta| {
+                let pubkey = meta.pubkey.replace(['*', '&', ' '], "");
+                pubkey == *var
+                    || convert_account_name(pubkey, known_accounts)
+                        == convert_account_name(var, known_accounts)
+            })?,
+            PdaSeed::Const(_) => return None,
+        };
+        match (&account_metas.get(index)?.kind, fields.get(index)?) {
+            (AccountMetaKind::Repeated, _) | (_, None) => None,
+            (_, Some(field)) => Some(field.name.to_owned()),
         }
+    };
 
-        let account_type = match is_signer {
-            true => "Signer<'info>",
-            false => match account_name.as_str() {
-                "system_program" => "Program<'info, System>",
-                "token_program" => "Program<'info, Token>",
-                "rent" => "Sysvar<'info, Rent>",
-                "clock" => "Sysvar<'info, Clock>",
-                _ => "AccountInfo<'info>",
-            },
+    let mut seeds = vec![];
+    for seed in &pda.seeds {
+        let arg = match seed {
+            PdaSeed::Var(var) => args.iter().find(|(name, _)| name == var),
+            _ => None,
         };
-
-        if !account_name.contains("Pubkey::default") {
-            let is_optional_account =
-                default_accounts.len() > 0 && !default_accounts.contains(&account_name.to_owned());
-
-            if is_mut {
-                if is_optional_account {
-                    // Add comment
-                    context.push_str("// ");
+        let seed = match (seed, arg) {
+            (PdaSeed::Const(value), _) => {
+                let value = String::from_utf8(value.to_owned()).ok()?;
+                let is_printable = value
+                    .chars()
+                    .all(|c| c.is_ascii_graphic() && c != '"' && c != '\\' || c == ' ');
+                is_printable.then_some(SeedModel::Const(value))?
+            }
+            (_, Some((name, ty))) => match ty {
+                IdlType::PublicKey | IdlType::String => SeedModel::Arg(name.to_string()),
+                ty if INT_TYPES.contains(&get_rust_type(ty).as_str()) => {
+                    SeedModel::Arg(name.to_string())
                 }
+                _ => return None,
+            },
+            (seed, None) => SeedModel::Account(account_field(seed)?),
+        };
+        seeds.push(seed);
+    }
 
-                context.push_str("#[account(mut)]\n");
-            }
+    let program = match pda.is_program_derived() {
+        true => None,
+        false => Some(SeedModel::Account(account_field(&pda.program_id)?)),
+    };
 
-            if is_optional_account {
-                // Add prefix
-                context.push_str("// optional_");
-            }
+    Some(PdaModel { seeds, program })
+}
 
-            context.push_str(&format!("{account_name}: {account_type},\n"));
-        }
-    }
+/// Add the seeds of the accounts from the address checks of the processor to the accounts of the
+/// builder function that don't derive them, accounts are matched by their positions
+fn merge_processor_pdas(
+    mut account_metas: Vec<BuilderAccountMeta>,
+    processor_account_metas: Option<Vec<BuilderAccountMeta>>,
+) -> Vec<BuilderAccountMeta> {
+    let processor_account_metas = match processor_account_metas {
+        Some(metas) if metas.len() == account_metas.len() => metas,
+        _ => return account_metas,
+    };
 
-    // Close Accounts '}'
-    if context.len() >= 3 {
-        let last = context.get(context.len() - 3..).unwrap().trim();
-        if last != "}" {
-            context.push_str("}\n\n");
+    for (account_meta, processor_account_meta) in
+        account_metas.iter_mut().zip(processor_account_metas)
+    {
+        if account_meta.pda.is_none() {
+            account_meta.pda = processor_account_meta.pda;
         }
     }
 
-    Some(context)
+    account_metas
 }
@@ -0,0 +1,104 @@
+use serde_derive::Deserialize;
+
+use crate::constants::known_types::KNOWN_ACCOUNTS;
+
+/// Suffixes that can come after a pattern e.g `clock_sysvar` -> `clock`
+const PATTERN_SUFFIXES: [&str; 3] = ["_account_info", "_info", "_sysvar"];
+
+/// Program or sysvar account with a fixed address e.g the token program
+#[derive(Clone, Deserialize)]
+pub struct KnownAccount {
+    /// Name of the account in snake_case e.g `token_program`
+    pub name: String,
+    /// Base58 address of the account
+    pub address: String,
+    /// Anchor type of the account e.g `Program<'info, Token>`, `UncheckedAccount<'info>` if not set
+    #[serde(rename = "type")]
+    pub ty: Option<String>,
+    /// Accounts that contain one of the patterns as a whole identifier or path segment are
+    /// renamed to this account e.g `spl_token` matches `spl_token::id()` but not `spl_token_x`
+    #[serde(default)]
+    pub patterns: Vec<String>,
+}
+
+/// Registry of the known accounts, the built-in accounts can be extended or overridden from
+/// `[package.metadata.native-to-anchor]` e.g
+///
+/// ```toml
+/// [[package.metadata.native-to-anchor.known-accounts]]
+/// name = "oracle_program"
+/// address = "Orac1e1111111111111111111111111111111111111"
+/// type = "Program<'info, Oracle>"
+/// patterns = ["oracle_program", "my_oracle"]
+/// ```
+pub struct KnownAccounts(Vec<KnownAccount>);
+
+impl KnownAccounts {
+    /// Create the registry with the user accounts, they take precedence over the built-in
+    /// accounts and the built-in accounts with the same name are replaced
+    pub fn new(user_accounts: Vec<KnownAccount>) -> Self {
+        let built_in_accounts = KNOWN_ACCOUNTS
+            .iter()
+            .filter(|(name, ..)| !user_accounts.iter().any(|acc| acc.name == *name))
+            .map(|(name, address, ty, patterns)| KnownAccount {
+                name: name.to_string(),
+                address: address.to_string(),
+                ty: ty.map(str::to_owned),
+                patterns: patterns.iter().map(|p| p.to_string()).collect(),
+            })
+            .collect::<Vec<_>>();
+
+        Self(user_accounts.into_iter().chain(built_in_accounts).collect())
+    }
+
+    /// Find the known account that the account name refers to e.g `spl_token` -> `token_program`.
+    ///
+    /// The name matches if it's the name of the known account(case and underscores are ignored
+    /// since the IDL names are camelCase e.g `token2022Program`) or if it has one of the patterns
+    /// as a whole identifier e.g `clock` matches `sysvar::clock::id()` and `clock_sysvar` but not
+    /// `clock_config`.
+    pub fn find(&self, account_name: &str) -> Option<&KnownAccount> {
+        let normalize = |name: &str| name.replace('_', "").to_lowercase();
+        self.0.iter().find(|acc| {
+            normalize(&acc.name) == normalize(account_name)
+                || acc
+                    .patterns
+                    .iter()
+                    .any(|pattern| matches_pattern(account_name, pattern))
+        })
+    }
+
+    /// Get the known account with the exact name
+    pub fn get(&self, name: &str) -> Option<&KnownAccount> {
+        self.0.iter().find(|acc| acc.name == name)
+    }
+}
+
+impl Default for KnownAccounts {
+    fn default() -> Self {
+        Self::new(vec![])
+    }
+}
+
+/// Whether the pattern is in the name and it's not a part of a longer identifier, patterns that
+/// start or end with `::` e.g `::rent` only need to be bounded on the other side
+fn matches_pattern(name: &str, pattern: &str) -> bool {
+    let is_ident_char = |c: char| c.is_alphanumeric() || c == '_';
+    name.match_indices(pattern).any(|(start, _)| {
+        let before = &name[..start];
+        let after = &name[start + pattern.len()..];
+        let is_start_bounded =
+            !pattern.starts_with(is_ident_char) || !before.ends_with(is_ident_char);
+        let is_end_bounded = !pattern.ends_with(is_ident_char)
+            || [after]
+                .into_iter()
+                .chain(
+                    PATTERN_SUFFIXES
+                        .iter()
+                        .filter_map(|suffix| after.strip_prefix(suffix)),
+                )
+                .any(|after| !after.starts_with(is_ident_char));
+
+        is_start_bounded && is_end_bounded
+    })
+}
@@ -1,4 +1,6 @@
 pub mod coder;
 pub mod common;
+pub mod dependency;
 pub mod generator;
 pub mod idl;
+pub mod known_accounts;